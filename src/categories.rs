@@ -0,0 +1,208 @@
+//! Apple Podcasts' category taxonomy, for validating the categories a feed is filed under.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Every category Apple Podcasts accepts, with the subcategories each one accepts.
+///
+/// See <https://podcasters.apple.com/support/1691-apple-podcasts-categories>
+pub const APPLE_PODCASTS_CATEGORIES: &[(&str, &[&str])] = &[
+    (
+        "Arts",
+        &[
+            "Books",
+            "Design",
+            "Fashion & Beauty",
+            "Food",
+            "Performing Arts",
+            "Visual Arts",
+        ],
+    ),
+    (
+        "Business",
+        &[
+            "Careers",
+            "Entrepreneurship",
+            "Investing",
+            "Management",
+            "Marketing",
+            "Non-Profit",
+        ],
+    ),
+    ("Comedy", &["Comedy Interviews", "Improv", "Stand-Up"]),
+    (
+        "Education",
+        &["Courses", "How To", "Language Learning", "Self-Improvement"],
+    ),
+    ("Fiction", &["Comedy Fiction", "Drama", "Science Fiction"]),
+    ("Government", &[]),
+    (
+        "Health & Fitness",
+        &[
+            "Alternative Health",
+            "Fitness",
+            "Medicine",
+            "Mental Health",
+            "Nutrition",
+            "Sexuality",
+        ],
+    ),
+    ("History", &[]),
+    (
+        "Kids & Family",
+        &[
+            "Education for Kids",
+            "Parenting",
+            "Pets & Animals",
+            "Stories for Kids",
+        ],
+    ),
+    (
+        "Leisure",
+        &[
+            "Animation & Manga",
+            "Automotive",
+            "Aviation",
+            "Crafts",
+            "Games",
+            "Hobbies",
+            "Home & Garden",
+            "Video Games",
+        ],
+    ),
+    (
+        "Music",
+        &["Music Commentary", "Music History", "Music Interviews"],
+    ),
+    (
+        "News",
+        &[
+            "Business News",
+            "Daily News",
+            "Entertainment News",
+            "News Commentary",
+            "Politics",
+            "Sports News",
+            "Tech News",
+        ],
+    ),
+    (
+        "Religion & Spirituality",
+        &[
+            "Buddhism",
+            "Christianity",
+            "Hinduism",
+            "Islam",
+            "Judaism",
+            "Religion",
+            "Spirituality",
+        ],
+    ),
+    (
+        "Science",
+        &[
+            "Astronomy",
+            "Chemistry",
+            "Earth Sciences",
+            "Life Sciences",
+            "Mathematics",
+            "Natural Sciences",
+            "Nature",
+            "Physics",
+            "Social Sciences",
+        ],
+    ),
+    (
+        "Society & Culture",
+        &[
+            "Documentary",
+            "Personal Journals",
+            "Philosophy",
+            "Places & Travel",
+            "Relationships",
+        ],
+    ),
+    (
+        "Sports",
+        &[
+            "Baseball",
+            "Basketball",
+            "Cricket",
+            "Fantasy Sports",
+            "Football",
+            "Golf",
+            "Hockey",
+            "Rugby",
+            "Running",
+            "Soccer",
+            "Swimming",
+            "Tennis",
+            "Volleyball",
+            "Wilderness",
+            "Wrestling",
+        ],
+    ),
+    ("Technology", &[]),
+    ("True Crime", &[]),
+    (
+        "TV & Film",
+        &[
+            "After Shows",
+            "Film History",
+            "Film Interviews",
+            "Film Reviews",
+            "TV Reviews",
+        ],
+    ),
+];
+
+/// A category, and optionally a subcategory, to file the feed under.
+///
+/// Parsed from `Category` or `Category/Subcategory`, e.g. `Leisure/Automotive`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Category {
+    /// The top-level category
+    pub name: String,
+
+    /// The subcategory within `name`, if any
+    pub subcategory: Option<String>,
+}
+
+impl Category {
+    /// Whether Apple Podcasts accepts this category (and subcategory)
+    pub fn is_valid(&self) -> bool {
+        APPLE_PODCASTS_CATEGORIES
+            .iter()
+            .find(|(name, _)| *name == self.name)
+            .is_some_and(|(_, subcategories)| match self.subcategory {
+                Some(ref subcategory) => subcategories.contains(&subcategory.as_str()),
+                None => true,
+            })
+    }
+}
+
+impl FromStr for Category {
+    type Err = std::convert::Infallible;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Ok(match text.split_once('/') {
+            Some((name, subcategory)) => Self {
+                name: name.trim().to_string(),
+                subcategory: Some(subcategory.trim().to_string()),
+            },
+            None => Self {
+                name: text.trim().to_string(),
+                subcategory: None,
+            },
+        })
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.subcategory {
+            Some(ref subcategory) => write!(formatter, "{}/{}", self.name, subcategory),
+            None => write!(formatter, "{}", self.name),
+        }
+    }
+}
@@ -1,24 +1,40 @@
 #[macro_use]
 extern crate log;
 
-use chrono::{NaiveDateTime, TimeZone, Utc};
+pub mod categories;
+
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone, Utc};
 
 use itertools::Itertools;
 
+use rss::extension::dublincore::{self, DublinCoreExtension};
+use rss::extension::{Extension, ExtensionBuilder, ExtensionMap};
+
 use rss::extension::itunes::{
-    ITunesCategoryBuilder, ITunesChannelExtensionBuilder, ITunesItemExtensionBuilder,
+    ITunesCategory, ITunesCategoryBuilder, ITunesChannelExtensionBuilder,
+    ITunesItemExtensionBuilder,
 };
 use rss::{
     Channel as RSSChannel, ChannelBuilder as RSSChannelBuilder,
-    EnclosureBuilder as RSSEnclosureBuilder, GuidBuilder as RSSGuidBuilder, Item as RSSItem,
-    ItemBuilder as RSSItemBuilder,
+    EnclosureBuilder as RSSEnclosureBuilder, Guid as RSSGuid, GuidBuilder as RSSGuidBuilder,
+    ImageBuilder as RSSImageBuilder, Item as RSSItem, ItemBuilder as RSSItemBuilder,
+    SourceBuilder as RSSSourceBuilder,
 };
 
+use serde::{Deserialize, Serialize};
+
+use categories::Category;
+
 use url::Url;
 
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
 use thiserror::Error as ThisError;
@@ -48,6 +64,14 @@ pub enum Error {
     #[error("all entries in \"{0}\" had a zero duration. This likely means the target playlist was a playlist of other playlists")]
     AllDownloadsEmptyError(Url),
 
+    /// Error case where the playlist URL pointed at a single video rather than a playlist
+    #[error("\"{0}\" points to a single video, not a playlist")]
+    SingleVideoError(Url),
+
+    /// Error case where `keep` was zero, which would empty the feed and delete every file, including new downloads
+    #[error("invalid keep limit: {0} (must keep at least one item)")]
+    InvalidKeepLimit(usize),
+
     /// Error case the supplied `feed_file` path was invalid
     #[error("invalid feed file path: \"{0}\"")]
     ParentPathError(PathBuf),
@@ -61,365 +85,5614 @@ pub enum Error {
         "invalid feed file path: \"{0}\" (file must have an extension - \"xml\" is a good one!)"
     )]
     FileExtensionError(PathBuf),
+
+    /// Error case where the feed file has a `json` extension while writing both formats, so the
+    /// JSON Feed written next to it would overwrite it
+    #[error("feed file \"{0}\" would be overwritten by its JSON Feed; give it an \"xml\" extension to write both formats")]
+    OutputFileCollisionError(PathBuf),
+
+    /// Error case where the requested output encoding is unknown, or can't be written as XML
+    #[error("unsupported encoding: \"{0}\"")]
+    EncodingError(String),
+
+    /// Error case where there's no feed to work with yet, as the channel hasn't been updated
+    #[error("there is no feed for \"{0}\" yet; update it from a playlist first")]
+    NoFeedError(PathBuf),
+
+    /// Error case the supplied `feed_file` was empty, and no playlist URL was given to create a
+    /// new feed from
+    #[error("feed file \"{0}\" is empty; a playlist URL is required to create a new feed")]
+    EmptyFeedError(PathBuf),
+
+    /// Error case where an updated feed failed validation
+    #[error("feed \"{0}\" is invalid: {1}")]
+    FeedValidationError(PathBuf, rss::validation::ValidationError),
+
+    /// Error case where a category isn't one Apple Podcasts accepts, when being strict
+    #[error("unknown Apple Podcasts category: \"{0}\"")]
+    CategoryError(Category),
+
+    /// Error case where writing an output file failed partway, i.e. when the disk is full
+    #[error(
+        "couldn't write \"{0}\" (failed after {1} bytes); the existing file was left unchanged"
+    )]
+    WriteError(PathBuf, u64, #[source] Box<Error>),
+
+    /// Error case where a feed is missing an element the requested profile requires
+    #[error("feed \"{0}\" doesn't meet the {1} profile: {2}")]
+    ProfileError(PathBuf, FeedProfile, String),
+
+    /// Error case where a playlist URL isn't a web URL `yt-dlp` could fetch
+    #[error("invalid playlist URL: \"{0}\" (must be an http or https URL)")]
+    PlaylistUrlError(Url),
+
+    /// Error case where a video has no single media URL to use as a remote enclosure, i.e. as its
+    /// best format merges separate video and audio streams
+    #[error("video \"{0}\" has no single media URL to point its enclosure at")]
+    RemoteEnclosureError(String),
+
+    /// Error case where a custom element's prefix has no namespace declared for it
+    #[error("no namespace is declared for the \"{0}\" prefix of a custom element")]
+    UndeclaredNamespaceError(String),
+
+    /// Error case where an item's enclosure URL isn't absolute, when being strict
+    #[error("item \"{0}\" has an enclosure URL which isn't absolute: \"{1}\"")]
+    RelativeEnclosureError(String, String),
+
+    /// Error case where two feeds would keep their media in the same directory, i.e. as their
+    /// file names share a stem, like `a.xml` and `a.rss`
+    #[error("feeds \"{0}\" and \"{1}\" would share the media directory \"{2}\"; give them distinct names or media directories")]
+    MediaDirCollisionError(PathBuf, PathBuf, PathBuf),
+
+    /// Error case where a channel's media directory holds files its feed doesn't reference, so
+    /// purging it could delete something else, unless forced
+    #[error("media directory \"{0}\" holds {1} files the feed doesn't reference, such as \"{2}\"; refusing to purge it")]
+    PurgeUnreferencedError(PathBuf, usize, PathBuf),
 }
 
 pub const PKG_NAME: &str = env!("CARGO_PKG_NAME");
 const PKG_HOMEPAGE: &str = env!("CARGO_PKG_HOMEPAGE");
 pub const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-const DEFAULT_DOWNLOAD_LIMIT: usize = 30;
+/// The number of videos downloaded per update unless `UpdateOptions::download_limit` says otherwise
+pub const DEFAULT_DOWNLOAD_LIMIT: usize = 30;
 
-/// Represents a given RSS channel, which points at a video feed.
-pub struct Channel {
-    /// Path to the input RSS feed
-    pub feed_file: PathBuf,
+/// Maximum length, in characters, Apple allows for `itunes:summary`
+const ITUNES_SUMMARY_LIMIT: usize = 4000;
 
-    /// URL to the playlist with videos to be downloaded
-    pub playlist_url: Url,
+/// Maximum length, in characters, of the channel's `itunes:keywords` when derived from its items
+const CHANNEL_KEYWORDS_LIMIT: usize = 255;
 
-    /// The RSS feed
-    pub rss_channel: Option<RSSChannel>,
+/// Maximum length, in characters, of item descriptions in a minimal feed
+const MINIMAL_DESCRIPTION_LIMIT: usize = 280;
+
+/// Prefix and URI of the Podcasting 2.0 namespace, used for `podcast:transcript`
+const PODCAST_PREFIX: &str = "podcast";
+const PODCAST_NAMESPACE: &str = "https://podcastindex.org/namespace/1.0";
+
+/// Prefix and URI of Playcaster's own namespace, used for `playcaster:downloaded`
+const PLAYCASTER_PREFIX: &str = "playcaster";
+const PLAYCASTER_NAMESPACE: &str = concat!(env!("CARGO_PKG_HOMEPAGE"), "/namespace");
+
+/// The text of a provisional feed's `playcaster:provisional` element
+const PROVISIONAL_NOTE: &str = "This is a preview; its enclosures may not have been downloaded yet";
+
+/// How items are ordered within the feed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SourceOrder {
+    /// Newest items first, by publication date
+    #[default]
+    Date,
+
+    /// Exactly the order the playlist returns its entries in, for curated playlists
+    Playlist,
 }
 
-impl Channel {
-    pub fn new_with_reader_and_url<T: BufRead>(
-        feed_file: PathBuf,
-        playlist_url: Url,
-        reader: T,
-    ) -> Result<Self, Error> {
-        if feed_file.extension().is_none() {
-            Err(Error::FileExtensionError(feed_file))
-        } else {
-            // Don't pull the URL out of the RSS channel
+/// How items' GUIDs are built.
+///
+/// Parsed from `id`, `enclosure` or `prefix:TEXT`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GuidScheme {
+    /// The source video's id
+    Id,
 
-            Ok(Self {
-                feed_file,
-                playlist_url,
-                rss_channel: RSSChannel::read_from(reader).ok(),
-            })
+    /// The item's enclosure URL, so the feed doesn't depend on the source's ids
+    Enclosure,
+
+    /// The source video's id after the given prefix, e.g. `prefix:mcm-` for `mcm-QWkUFkXcx9I`
+    Prefix(String),
+}
+
+impl GuidScheme {
+    /// The GUID for the item with the given video id and enclosure URL
+    fn guid(&self, id: &str, enclosure_url: &str) -> RSSGuid {
+        match self {
+            Self::Id => RSSGuidBuilder::default().value(id.to_string()).build(),
+            Self::Enclosure => RSSGuidBuilder::default()
+                .value(enclosure_url.to_string())
+                .permalink(false)
+                .build(),
+            Self::Prefix(prefix) => RSSGuidBuilder::default()
+                .value(format!("{}{}", prefix, id))
+                .permalink(false)
+                .build(),
         }
     }
+}
 
-    pub fn new_with_reader<T: BufRead>(feed_file: PathBuf, reader: T) -> Result<Self, Error> {
-        if feed_file.extension().is_none() {
-            Err(Error::FileExtensionError(feed_file))
-        } else {
-            let rss_channel = RSSChannel::read_from(reader)?;
-
-            let playlist_url = Url::parse(rss_channel.link())?;
+impl std::str::FromStr for GuidScheme {
+    type Err = String;
 
-            Ok(Self {
-                feed_file,
-                playlist_url,
-                rss_channel: Some(rss_channel),
-            })
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "id" => Ok(Self::Id),
+            "enclosure" => Ok(Self::Enclosure),
+            _ => text
+                .strip_prefix("prefix:")
+                .filter(|prefix| !prefix.is_empty())
+                .map(|prefix| Self::Prefix(prefix.to_string()))
+                .ok_or_else(|| {
+                    format!(
+                        "\"{}\" isn't one of `id`, `enclosure` or `prefix:TEXT`",
+                        text
+                    )
+                }),
         }
     }
+}
 
-    pub fn new_with_url(feed_file: PathBuf, playlist_url: Url) -> Result<Self, Error> {
-        if feed_file.extension().is_none() {
-            Err(Error::FileExtensionError(feed_file))
-        } else if let Ok(file) = File::open(feed_file.clone()) {
-            let reader = BufReader::new(file);
-            Self::new_with_reader_and_url(feed_file, playlist_url, reader)
-        } else {
-            Ok(Self {
-                feed_file,
-                playlist_url,
-                rss_channel: None,
+/// A custom namespaced element to add to the channel or an item, such as one a hosting platform requires.
+///
+/// Parsed from `prefix:name=value`, e.g. `spotify:countryOfOrigin=us`. Its prefix must have a
+/// namespace declared, either by `UpdateOptions::namespaces` or the existing feed.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct CustomElement {
+    /// The prefix of the element's namespace
+    pub prefix: String,
+
+    /// The element's name within its namespace
+    pub name: String,
+
+    /// The element's text, if any
+    #[serde(default)]
+    pub value: Option<String>,
+
+    /// The element's attributes
+    #[serde(default)]
+    pub attrs: BTreeMap<String, String>,
+}
+
+impl CustomElement {
+    fn to_extension(&self) -> Extension {
+        ExtensionBuilder::default()
+            .name(format!("{}:{}", self.prefix, self.name))
+            .value(self.value.clone())
+            .attrs(self.attrs.clone())
+            .build()
+    }
+}
+
+impl std::str::FromStr for CustomElement {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        text.split_once('=')
+            .and_then(|(qualified_name, value)| {
+                qualified_name
+                    .split_once(':')
+                    .map(|(prefix, name)| (prefix, name, value))
             })
-        }
+            .filter(|(prefix, name, _)| {
+                !prefix.is_empty() && !name.is_empty() && !name.contains(':')
+            })
+            .map(|(prefix, name, value)| Self {
+                prefix: prefix.to_string(),
+                name: name.to_string(),
+                value: Some(value.to_string()),
+                attrs: BTreeMap::new(),
+            })
+            .ok_or_else(|| format!("\"{}\" should be in the form `prefix:name=value`", text))
     }
+}
 
-    pub fn new(feed_file: PathBuf) -> Result<Self, Error> {
-        if feed_file.extension().is_none() {
-            Err(Error::FileExtensionError(feed_file))
-        } else {
-            let file = File::open(feed_file.clone())?;
-            let reader = BufReader::new(file);
-            Self::new_with_reader(feed_file, reader)
+/// Changes to make to the item for one video, from an overrides file; see `read_item_overrides`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ItemOverride {
+    /// Custom elements to set on the item, replacing any existing elements with the same names
+    #[serde(default)]
+    pub elements: Vec<CustomElement>,
+}
+
+/// A metadata field to order the feed's items by, instead of the `SourceOrder`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum OrderBy {
+    /// Publication date, newest first unless ascending
+    Date,
+
+    /// Title, alphabetically unless descending
+    Title,
+
+    /// Duration, longest first unless ascending
+    Duration,
+
+    /// View count, most viewed first unless ascending
+    Views,
+
+    /// Position in the playlist, first to last unless descending
+    Index,
+}
+
+impl OrderBy {
+    /// The direction each field is ordered in unless one is given
+    fn default_direction(self) -> OrderDirection {
+        match self {
+            OrderBy::Date | OrderBy::Duration | OrderBy::Views => OrderDirection::Desc,
+            OrderBy::Title | OrderBy::Index => OrderDirection::Asc,
         }
     }
+}
 
-    fn update_with_playlist(
-        &mut self,
-        base_url: Url,
-        keep: Option<usize>,
-        playlist: youtube_dl::Playlist,
-    ) -> Result<(), Error> {
-        let title = playlist
-            .title
-            .as_ref()
-            .unwrap_or(&self.playlist_url.to_string())
-            .clone();
+/// Which way to order items by an `OrderBy` field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum OrderDirection {
+    /// Smallest (or earliest, or alphabetically first) first
+    Asc,
 
-        let mut zero_duration_item_paths = vec![];
+    /// Largest (or latest, or alphabetically last) first
+    Desc,
+}
 
-        let mut rss_items: Vec<RSSItem> = match playlist.entries {
-            Some(ref entries) => entries
-                .iter()
-                .map(|video| {
-                    use hhmmss::Hhmmss;
+/// Which of a video's thumbnails to use as its item's image.
+///
+/// Parsed from `maxres`, `square`, `first` or `index:N`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThumbnailPreference {
+    /// The largest thumbnail
+    Maxres,
 
-                    let duration = match &video.duration {
-                        Some(value) => {
-                            let secs = match value {
-                                serde_json::Value::Number(secs) => secs.as_f64().unwrap_or(0.0),
-                                _ => 0.0,
-                            };
-                            Duration::new(secs as u64, 0)
-                        }
-                        None => Duration::default(),
-                    };
+    /// The largest square thumbnail
+    Square,
 
-                    let item_path = Path::new(&self.feed_file.parent().unwrap())
-                        .join(self.feed_file.file_stem().unwrap())
-                        .join(format!("{}.mp4", video.id));
+    /// The first thumbnail listed
+    First,
 
-                    if duration.is_zero() {
-                        zero_duration_item_paths.push(item_path);
-                    }
+    /// The thumbnail at the given (zero-based) position in the list
+    Index(usize),
+}
 
-                    let item_itunes_extension = ITunesItemExtensionBuilder::default()
-                        .author(title.clone())
-                        .subtitle(video.title.clone())
-                        .summary(video.description.clone())
-                        .image(video.thumbnail.clone())
-                        .duration(duration.hhmmss())
-                        .explicit("No".to_string())
-                        .build();
+impl std::str::FromStr for ThumbnailPreference {
+    type Err = String;
 
-                    let item_enclosure = RSSEnclosureBuilder::default()
-                        .url(
-                            base_url
-                                .join(&format!(
-                                    "{}/",
-                                    self.feed_file.file_stem().unwrap().to_string_lossy()
-                                ))
-                                .unwrap()
-                                .join(&format!("{}.mp4", video.id))
-                                .unwrap(),
-                        )
-                        .length(
-                            (video
-                                .filesize
-                                .unwrap_or_else(|| video.filesize_approx.unwrap_or(0.0) as i64))
-                            .to_string(),
-                        )
-                        .mime_type("video/mp4")
-                        .build();
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "maxres" => Ok(Self::Maxres),
+            "square" => Ok(Self::Square),
+            "first" => Ok(Self::First),
+            _ => text
+                .strip_prefix("index:")
+                .and_then(|index| index.parse().ok())
+                .map(Self::Index)
+                .ok_or_else(|| {
+                    format!(
+                        "\"{}\" isn't one of `maxres`, `square`, `first` or `index:N`",
+                        text
+                    )
+                }),
+        }
+    }
+}
 
-                    // video.release_date
-                    // video.upload_date
+/// The container and codecs media files are downloaded in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct MediaFormat {
+    /// File extension of the downloaded media files
+    extension: &'static str,
 
-                    let mut item = RSSItemBuilder::default();
+    /// Format selector passed to `yt-dlp`'s `--format`
+    selector: &'static str,
+}
 
-                    item.guid(RSSGuidBuilder::default().value(video.id.clone()).build())
-                        .title(video.title.clone())
-                        .description(video.description.clone())
-                        .link(video.webpage_url.clone())
-                        .enclosure(item_enclosure)
-                        .itunes_ext(item_itunes_extension);
+impl MediaFormat {
+    /// H.264 video and AAC audio in an MP4 container, for the widest player support
+    const MP4: Self = Self {
+        extension: "mp4",
+        selector: "bestvideo[ext=mp4][vcodec^=avc1]+bestaudio[ext=m4a]/best[ext=mp4][vcodec^=avc1]/best[ext=mp4]/best",
+    };
 
-                    if let Some(upload_date) = &video.upload_date {
-                        item.pub_date(
-                            Utc.from_utc_datetime(
-                                &NaiveDateTime::parse_from_str(
-                                    &format!("{}T00:00Z", upload_date),
-                                    "%Y%m%dT%H:%MZ",
-                                )
-                                .unwrap_or_else(|error| {
-                                    panic!("Error: {} parsing {:?}", error, upload_date)
-                                }),
-                            )
-                            .to_rfc2822(),
-                        );
-                    } else if let Some(release_date) = &video.release_date {
-                        item.pub_date(
-                            Utc.from_utc_datetime(
-                                &NaiveDateTime::parse_from_str(
-                                    &format!("{}T00:00Z", release_date),
-                                    "%Y%m%dT%H:%MZ",
-                                )
-                                .unwrap_or_else(|error| {
-                                    panic!("Error: {} parsing {:?}", error, release_date)
-                                }),
-                            )
-                            .to_rfc2822(),
-                        );
-                    }
+    /// VP9 video and Opus audio in a WebM container, for open codecs
+    const WEBM: Self = Self {
+        extension: "webm",
+        selector: "bestvideo[ext=webm][vcodec^=vp9]+bestaudio[ext=webm]/best[ext=webm][vcodec^=vp9]/best[ext=webm]/best",
+    };
 
-                    item.build()
-                })
-                .collect(),
-            None => vec![],
-        };
+    /// The format of an existing item's media file, going by its enclosure's file extension
+    fn of_item(item: &RSSItem) -> Self {
+        let extension = item
+            .enclosure()
+            .and_then(|enclosure| Url::parse(enclosure.url()).ok())
+            .and_then(|url| {
+                url.path_segments()
+                    .and_then(|mut segments| segments.next_back())
+                    .and_then(|file_name| Path::new(file_name).extension())
+                    .map(|extension| extension.to_string_lossy().to_string())
+            });
 
-        if !rss_items.is_empty() && zero_duration_item_paths.len() == rss_items.len() {
-            return Err(Error::AllDownloadsEmptyError(self.playlist_url.clone()));
-        } else if !zero_duration_item_paths.is_empty() {
-            warn!("One or more files were not found on disk!\nYour playlist URL might be invalid. {:?}", zero_duration_item_paths);
+        match extension {
+            Some(extension) if extension == Self::WEBM.extension => Self::WEBM,
+            Some(extension) => Self::known_extension(&extension).unwrap_or(Self::MP4),
+            _ => Self::MP4,
         }
+    }
 
-        // Retrieve the existing RSS channel, or create a new one
-        let mut rss_channel = self.rss_channel.clone().unwrap_or_else(|| {
-            let description = format!("{} podcast feed for {}", PKG_NAME, title);
+    /// The format a video was actually downloaded in, going by the extension `yt-dlp` reports.
+    ///
+    /// The selectors fall back to whatever's best when the preferred codecs aren't available, so
+    /// this may differ from the requested format.
+    fn of_video(self, video: &youtube_dl::SingleVideo) -> Self {
+        match video.ext {
+            Some(ref extension) if *extension != self.extension => Self {
+                selector: self.selector,
+                ..Self::known_extension(extension).unwrap_or(self)
+            },
+            _ => self,
+        }
+    }
 
-            let rss_itunes_category = ITunesCategoryBuilder::default().text("TV & Film").build();
+    /// The selector without its first alternative, which merges separate video and audio streams,
+    /// for formats which must be a single file at the source, i.e. for remote enclosures
+    fn single_file_selector(self) -> &'static str {
+        self.selector
+            .split_once('/')
+            .map_or(self.selector, |(_, single_file)| single_file)
+    }
 
-            let rss_itunes_extension = ITunesChannelExtensionBuilder::default()
-                .author(title.clone())
-                .subtitle(title.clone())
-                .summary(description.clone())
-                .explicit("No".to_string())
-                .category(rss_itunes_category)
-                .block("Yes".to_string())
-                .build();
+    /// A format for one of the extensions in `MIME_TYPES`, if it's one of them
+    fn known_extension(extension: &str) -> Option<Self> {
+        MIME_TYPES
+            .iter()
+            .find(|(known_extension, _)| known_extension.eq_ignore_ascii_case(extension))
+            .map(|(known_extension, _)| Self {
+                extension: known_extension,
+                selector: Self::MP4.selector,
+            })
+    }
+}
 
-            RSSChannelBuilder::default()
-                .title(title)
-                .description(description)
-                .itunes_ext(rss_itunes_extension)
-                .build()
-        });
+/// MIME types of the media file extensions `yt-dlp` commonly produces
+const MIME_TYPES: &[(&str, &str)] = &[
+    ("flac", "audio/flac"),
+    ("m4a", "audio/mp4"),
+    ("m4v", "video/x-m4v"),
+    ("mkv", "video/x-matroska"),
+    ("mov", "video/quicktime"),
+    ("mp3", "audio/mpeg"),
+    ("mp4", "video/mp4"),
+    ("ogg", "audio/ogg"),
+    ("opus", "audio/ogg"),
+    ("wav", "audio/wav"),
+    ("webm", "video/webm"),
+];
+
+/// The MIME type for media files with the given extension, looked up in `mime_map` before the
+/// built-in table. Extensions are matched case-insensitively.
+pub fn mime_type_for_extension(extension: &str, mime_map: &[(String, String)]) -> Option<String> {
+    mime_map
+        .iter()
+        .rev()
+        .find(|(map_extension, _)| map_extension.eq_ignore_ascii_case(extension))
+        .map(|(_, mime_type)| mime_type.clone())
+        .or_else(|| {
+            MIME_TYPES
+                .iter()
+                .find(|(map_extension, _)| map_extension.eq_ignore_ascii_case(extension))
+                .map(|(_, mime_type)| mime_type.to_string())
+        })
+}
 
-        rss_items.append(&mut rss_channel.items);
+/// Which of a video's dates becomes its item's publication date.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum DateSource {
+    /// The precise upload time, so same-day uploads keep their order
+    #[default]
+    Timestamp,
 
-        let mut unique_items: Vec<_> = rss_items
-            .into_iter()
-            .unique_by(|item| item.guid().unwrap().value().to_string())
-            .collect();
+    /// The day the video was uploaded
+    Upload,
 
-        if let Some(keep_item_count) = keep {
-            if unique_items.len() > keep_item_count {
-                let removed_items: Vec<_> = unique_items.drain(keep_item_count..).collect();
+    /// The day the video was released, which differs from the upload date for premieres
+    Release,
+}
 
-                for item in removed_items {
-                    let id = item.guid().unwrap().value().to_string();
+/// Kinds of link file `yt-dlp` can write next to each media file, pointing back to the video's page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum LinkFileFormat {
+    /// A Windows Internet Shortcut (`.url`)
+    Url,
 
-                    let path = Path::new(
-                        &self
-                            .feed_file
-                            .parent()
-                            .ok_or_else(|| Error::ParentPathError(self.feed_file.clone()))?,
-                    )
-                    .join(
-                        self.feed_file
-                            .file_stem()
-                            .ok_or_else(|| Error::FileStemError(self.feed_file.clone()))?,
-                    )
-                    .join(format!("{}.mp4", id));
+    /// A macOS Website Location (`.webloc`)
+    Webloc,
 
-                    debug!("Attempting to remove file: {:?}", path);
+    /// A Linux desktop entry (`.desktop`)
+    Desktop,
+}
 
-                    std::fs::remove_file(path)
-                        .unwrap_or_else(|err| warn!("Couldn't remove file: {:?}", err));
-                }
-            }
+impl LinkFileFormat {
+    /// The `yt-dlp` option which writes this kind of link file
+    fn downloader_arg(self) -> &'static str {
+        match self {
+            Self::Url => "--write-url-link",
+            Self::Webloc => "--write-webloc-link",
+            Self::Desktop => "--write-desktop-link",
         }
+    }
+}
 
-        if let Some(ref mut channel_itunes_ext) = rss_channel.itunes_ext {
-            for item in &unique_items {
-                if let Some(ref item_ext) = item.itunes_ext {
-                    channel_itunes_ext.image.clone_from(&item_ext.image);
-                    break;
-                }
+/// Which of a video's fields becomes its item's title.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum TitleSource {
+    /// The video's title
+    #[default]
+    Title,
+
+    /// The name of the track, for music
+    Track,
+
+    /// The video's secondary title
+    #[value(alias = "alt_title")]
+    AltTitle,
+}
+
+/// How to tell apart items which share a title.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum TitleDisambiguator {
+    /// Append the publication date, e.g. `Title (2022-02-06)`
+    Date,
+
+    /// Append the source video's id, e.g. `Title [QWkUFkXcx9I]`
+    Id,
+}
+
+impl TitleDisambiguator {
+    /// The suffix telling this item apart from others with the same title
+    fn suffix(self, item: &RSSItem) -> Option<String> {
+        match self {
+            Self::Date => {
+                item_pub_date(item).map(|pub_date| format!(" ({})", pub_date.format("%Y-%m-%d")))
             }
+            Self::Id => item_id(item).map(|id| format!(" [{}]", id)),
         }
+    }
+}
 
-        rss_channel.set_link(
-            playlist
-                .webpage_url
-                .unwrap_or_else(|| self.playlist_url.to_string()),
-        );
-        rss_channel.set_generator(format!("{}/{} ({})", PKG_NAME, PKG_VERSION, PKG_HOMEPAGE));
-        rss_channel.set_items(unique_items);
+/// Options controlling how a channel is updated.
+#[derive(Clone, Debug)]
+pub struct UpdateOptions {
+    /// Maximum number of videos to download, or 0 to download the whole playlist
+    pub download_limit: usize,
 
-        self.rss_channel = Some(rss_channel);
+    /// Maximum number of items to keep in the feed; older items and their files are removed.
+    /// Must be at least 1, as keeping nothing would delete even the files just downloaded.
+    pub keep: Option<usize>,
 
-        Ok(())
-    }
+    /// Maximum number of items to keep the media files of, counting from the newest. Older items
+    /// stay in the feed (up to `keep`), but their files are removed, so their enclosures will be
+    /// unavailable unless the files are downloaded again. Like `keep`, must be at least 1.
+    pub keep_files: Option<usize>,
 
-    pub fn update(&mut self, base_url: Url, keep: Option<usize>) -> Result<(), Error> {
-        self.update_with_args(base_url, DEFAULT_DOWNLOAD_LIMIT, keep, vec![])
-    }
+    /// Maximum number of new items to add to the feed in one run, newest first, leaving any others
+    /// to be downloaded and added by later runs. Unlike `download_limit`, this only counts videos
+    /// which aren't in the feed yet.
+    pub max_new_per_run: Option<usize>,
 
-    pub fn update_with_args(
-        &mut self,
-        base_url: Url,
-        download_limit: usize,
-        keep: Option<usize>,
-        additional_args: Vec<String>,
-    ) -> Result<(), Error> {
-        let mut ytdl = YoutubeDl::new(self.playlist_url.clone());
+    /// Keep every entry of the playlist, even ones repeating a video already listed, rather than
+    /// only its first appearance. The repeats share a GUID, which many podcast apps will merge or
+    /// reject, so this is only for feeds which repeat content on purpose.
+    pub no_dedup: bool,
 
-        ytdl.youtube_dl_path("yt-dlp");
+    /// Additional arguments to be passed to `yt-dlp`
+    pub additional_args: Vec<String>,
 
-        ytdl.extra_arg("--playlist-end")
-            .extra_arg(download_limit.to_string());
+    /// URL to set as the channel's `<docs>` element
+    pub docs: Option<Url>,
 
-        ytdl.extra_arg("--format")
-            .extra_arg("bestvideo[ext=mp4][vcodec^=avc1]+bestaudio[ext=m4a]/best[ext=mp4][vcodec^=avc1]/best[ext=mp4]/best");
+    /// Format sort order passed to `yt-dlp`'s `--format-sort`, refining the default format selection
+    pub format_sort: Option<String>,
 
-        ytdl.extra_arg("--no-simulate");
+    /// Upgrade `http` image URLs to `https`, rather than just warning about them
+    pub require_https_images: bool,
 
-        additional_args.into_iter().for_each(|arg| {
-            ytdl.extra_arg(arg);
-        });
+    /// How items are ordered within the feed
+    pub order: SourceOrder,
 
-        // NOTE: Required because `yt-dlp` prints progress to stdout and breaks YoutubeDl when `--no-simulate` is specified
-        ytdl.extra_arg("--no-progress");
-        ytdl.extra_arg("--no-overwrites");
-        ytdl.extra_arg("--output").extra_arg(
-            Path::new(
-                &self
-                    .feed_file
-                    .parent()
-                    .ok_or_else(|| Error::ParentPathError(self.feed_file.clone()))?,
-            )
-            .join(
-                self.feed_file
-                    .file_stem()
-                    .ok_or_else(|| Error::FileStemError(self.feed_file.clone()))?,
-            )
-            .join("%(id)s.%(ext)s")
-            .to_string_lossy(),
-        );
+    /// Order the items by a metadata field instead, overriding `order`. Items missing the field
+    /// (e.g. the views of older items no longer in the playlist) sort last.
+    pub order_by: Option<OrderBy>,
 
-        let result = ytdl.run()?;
+    /// Which way to order by `order_by`, otherwise that field's natural direction
+    pub order_dir: Option<OrderDirection>,
 
-        trace!("{:#?}", result);
+    /// Directory for `yt-dlp` to store its cache in, rather than its default location
+    pub cache_dir: Option<PathBuf>,
 
-        if let YoutubeDlOutput::Playlist(playlist) = result {
-            self.update_with_playlist(base_url, keep, *playlist)
-        } else {
-            panic!("This URL points to a single video, not a channel!")
-        }
-    }
-}
+    /// Disable `yt-dlp`'s filesystem cache entirely
+    pub no_cache_dir: bool,
 
-#[cfg(test)]
-mod test {
-    fn get_new_video() -> youtube_dl::SingleVideo {
-        youtube_dl::SingleVideo {
-            abr: Some(129.478),
-            acodec: Some("mp4a.40.2".to_string()),
-            age_limit: Some(0),
-            album: None,
-            album_artist: None,
-            album_type: None,
-            alt_title: None,
-            artist: None,
-            asr: Some(44100.0),
-            automatic_captions: None,
-            average_rating: None,
-            categories: None,
-            channel: Some("Mighty Car Mods".to_string()),
-            channel_id: Some("UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
-            channel_url: Some("https://www.youtube.com/channel/UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
-            chapter: None,
-            chapter_id: None,
-            chapter_number: None,
+    /// Accept a playlist URL which points to a single video, producing a one-item feed
+    pub single_ok: bool,
+
+    /// Build the channel's `itunes:summary` from this many of the newest episode titles,
+    /// rather than leaving the generic summary
+    pub summary_from_episodes: Option<usize>,
+
+    /// Update an existing feed's title (and the iTunes author and subtitle) to match the playlist's
+    /// current title, rather than preserving the title the feed was created with
+    pub sync_title: bool,
+
+    /// The channel's `<description>`, which may be HTML, rather than one naming the playlist. Only
+    /// used for new feeds, unless `sync_description` is set.
+    pub description: Option<String>,
+
+    /// The `<itunes:author>` of the channel and every item, replacing the existing channel's.
+    /// Otherwise, an item's author is its video's uploader, then its video's channel, then the
+    /// playlist's uploader, then the playlist's title; the channel's is the last two of those.
+    pub author: Option<String>,
+
+    /// Replace an existing feed's description with `description`, rather than keeping its original one
+    pub sync_description: bool,
+
+    /// Apple Podcasts categories to file the feed under, replacing the existing categories.
+    /// New feeds default to "TV & Film".
+    pub categories: Vec<Category>,
+
+    /// Keywords to set as the channel's `itunes:keywords`. Otherwise, they're derived from the
+    /// keywords of its items, which come from their videos' tags and categories.
+    pub channel_keywords: Vec<String>,
+
+    /// Custom elements to set on the channel, replacing any existing elements with the same names
+    pub channel_elements: Vec<CustomElement>,
+
+    /// Namespaces to declare for `channel_elements` and `item_overrides`, as `(prefix, uri)` pairs
+    pub namespaces: Vec<(String, String)>,
+
+    /// Changes to make to the items for particular videos, by video id
+    pub item_overrides: HashMap<String, ItemOverride>,
+
+    /// Set each item's `dc:language` from its video's language, for channels which mix languages
+    pub per_item_language: bool,
+
+    /// Number each item's `itunes:episode` by its video's position in the playlist. Items whose
+    /// position isn't known are left unnumbered, rather than all being numbered zero.
+    pub episode_numbers: bool,
+
+    /// Emit `categories` sorted by name and subcategory, rather than in the order given.
+    /// Either way, repeated categories are only emitted once.
+    pub sort_categories: bool,
+
+    /// Which of each video's dates to use as its item's publication date. If the video doesn't
+    /// have that date, the others are tried in the order timestamp, upload date, release date.
+    pub date_source: DateSource,
+
+    /// Which of each video's fields to use as its item's title. If the video doesn't have that
+    /// field, its title is used instead.
+    pub title_source: TitleSource,
+
+    /// Append a suffix to the titles of items which share a title with another item in the feed,
+    /// so apps grouping episodes by title can tell them apart. Unique titles are left alone, and
+    /// lose any suffix an earlier update gave them.
+    pub disambiguate_titles: Option<TitleDisambiguator>,
+
+    /// Treat problems with the feed's contents, like unknown categories, as errors rather than warnings
+    pub strict: bool,
+
+    /// Query parameters to append to every new item's enclosure URL, e.g. for analytics
+    pub enclosure_query: Vec<(String, String)>,
+
+    /// Update items already in the feed with the playlist's current title, description and images,
+    /// rather than keeping the metadata they were added with. Their publication dates are kept either way.
+    pub prefer_fresh_metadata: bool,
+
+    /// Use each new item's enclosure URL as its GUID, rather than the source video's id,
+    /// so the feed doesn't depend on the source's ids
+    pub guid_from_enclosure: bool,
+
+    /// How to build each new item's GUID, overriding `guid_from_enclosure`. Use
+    /// `Channel::migrate_guids` to move an existing feed's items to a new scheme.
+    pub guid_scheme: Option<GuidScheme>,
+
+    /// Omit all iTunes extension elements (and their namespace) from the feed
+    pub no_itunes: bool,
+
+    /// Embed chapter markers into the downloaded media files. Requires `ffmpeg`.
+    pub embed_chapters: bool,
+
+    /// Embed `yt-dlp`'s metadata for each video into its media file, rather than a sidecar file.
+    /// `yt-dlp` can only do this for Matroska (`mkv` or `mka`) files, so MP4 and WebM files are
+    /// left as they are. Requires `ffmpeg`.
+    pub embed_info_json: bool,
+
+    /// Have `yt-dlp` write a link file to each video's page next to its media file. Like other
+    /// sidecar files, it's removed along with the media file when the item is dropped.
+    pub link_file: Option<LinkFileFormat>,
+
+    /// Which of each video's thumbnails to use as its item's image, rather than `yt-dlp`'s pick.
+    /// Falls back to `yt-dlp`'s pick if there's no thumbnail matching the preference.
+    pub thumbnail_preference: Option<ThumbnailPreference>,
+
+    /// Format to convert thumbnails to via `yt-dlp`'s `--convert-thumbnails`, e.g. `jpg`
+    pub convert_thumbnails: Option<String>,
+
+    /// Embed each video's thumbnail into its media file as cover art, for local players. Unless
+    /// `--write-thumbnail` is among the additional arguments, the thumbnail files downloaded for
+    /// this are removed afterwards, so they aren't served. Requires `ffmpeg`.
+    pub embed_thumbnail: bool,
+
+    /// Pass `--restrict-filenames` to `yt-dlp`, so media files' names are ASCII-only and free of
+    /// spaces and shell characters, for ids which aren't already. The names `yt-dlp` gives the
+    /// files are read back from it, and items whose names differ from their ids record the ids in
+    /// a `playcaster:id` element.
+    pub restrict_filenames: bool,
+
+    /// Give each new item a `<source>` element pointing at the playlist it came from, titled after
+    /// the playlist
+    pub with_source: bool,
+
+    /// Record when each new item's media file was downloaded in a `playcaster:downloaded`
+    /// element, as distinct from its `pub_date`. `yt-dlp` is passed `--no-mtime`, so the files'
+    /// modification times are when they were downloaded, rather than when they were uploaded.
+    pub write_downloaded_timestamp: bool,
+
+    /// Mark the feed as provisional with a `playcaster:provisional` element, for previews built
+    /// with `skip_download`, whose enclosures point at files which may not exist yet. Otherwise,
+    /// any such mark left by an earlier update is removed.
+    pub provisional: bool,
+
+    /// Settings to store in the feed's channel elements, replacing any stored by an earlier
+    /// update; see `FeedSettings`. Otherwise, any stored settings are left as they are.
+    pub feed_settings: Option<FeedSettings>,
+
+    /// Read the duration of downloaded files which have none in their metadata, using `ffprobe`
+    pub probe_duration: bool,
+
+    /// Move the files of items removed by `keep` into this directory, rather than deleting them
+    pub trash_dir: Option<PathBuf>,
+
+    /// Don't delete the files of items removed by `keep`; list them in the summary's
+    /// `pending_deletions` instead, so they can be confirmed and deleted separately
+    pub confirm_deletes: bool,
+
+    /// Download livestreams from their start with `yt-dlp`'s `--live-from-start`, so recordings of
+    /// streams are captured in full
+    pub live_from_start: bool,
+
+    /// Two-letter ISO 3166 country code passed to `yt-dlp`'s `--geo-bypass-country`, so extractors
+    /// which localise titles and descriptions by region return the same metadata on every run
+    pub geo_bypass_country: Option<String>,
+
+    /// Seconds to sleep before each download, passed to `yt-dlp`'s `--sleep-interval`, to avoid
+    /// being throttled or banned on large playlists
+    pub sleep_interval: Option<f64>,
+
+    /// Sleep a random number of seconds between `sleep_interval` and this before each download,
+    /// passed to `yt-dlp`'s `--max-sleep-interval`
+    pub max_sleep_interval: Option<f64>,
+
+    /// Seconds to sleep between the requests made while extracting metadata, passed to
+    /// `yt-dlp`'s `--sleep-requests`
+    pub sleep_requests: Option<f64>,
+
+    /// Expressions passed to `yt-dlp`'s `--match-filter`, e.g. `view_count > 1000 & duration < 1200`.
+    /// These are applied by `yt-dlp` as it downloads, so rejected entries never reach the feed.
+    /// An entry is kept if it matches any of them; combine conditions with `&` within one expression.
+    pub match_filters: Vec<String>,
+
+    /// Attempt members-only and premium videos even when `additional_args` doesn't give `yt-dlp`
+    /// cookies or credentials to sign in with. Otherwise they're filtered out, as they'd only fail.
+    pub include_members_only: bool,
+
+    /// Skip videos with fewer views than this, both with `yt-dlp`'s `--match-filter` and when
+    /// building the feed
+    pub min_views: Option<u64>,
+
+    /// Keep videos whose view count isn't known when filtering by `min_views`, rather than skipping them
+    pub include_unknown_views: bool,
+
+    /// Skip videos lasting exactly any of these numbers of seconds, such as the lengths of known
+    /// placeholders, both with `yt-dlp`'s `--match-filter` and when building the feed
+    pub skip_durations: Vec<u64>,
+
+    /// Remove the channel's media directory if it's left empty after old files are removed
+    pub prune_empty_dirs: bool,
+
+    /// Before downloading, delete partial downloads older than this; see `Channel::clean_stale_partials`
+    pub clean_stale_partials: Option<Duration>,
+
+    /// Languages to download subtitles in, e.g. `en`, each emitted as a `podcast:transcript`.
+    /// Languages a video has no subtitles in are skipped.
+    pub sub_langs: Vec<String>,
+
+    /// MIME types for media file extensions, as `(extension, mime_type)` pairs, which extend or
+    /// override the built-in table used for enclosures. Later pairs win.
+    pub mime_map: Vec<(String, String)>,
+
+    /// Prefer VP9/Opus formats in a WebM container over the default H.264/AAC in MP4,
+    /// passing `--prefer-free-formats` to `yt-dlp`
+    pub prefer_free_formats: bool,
+
+    /// Run `yt-dlp` in simulate mode and skip all media file operations, while still producing the feed.
+    /// Intended for exercising feed generation in CI without heavy downloads.
+    pub skip_download: bool,
+
+    /// Point enclosures at the source's own media URLs, as `yt-dlp` reports them, rather than
+    /// downloading the media to serve it locally. Only single-file formats can be used, and the
+    /// source URLs may expire, so feeds need updating regularly. Each enclosure URL carries its
+    /// video's id as its fragment.
+    pub remote_enclosures: bool,
+}
+
+impl Default for UpdateOptions {
+    fn default() -> Self {
+        Self {
+            download_limit: DEFAULT_DOWNLOAD_LIMIT,
+            keep: None,
+            keep_files: None,
+            max_new_per_run: None,
+            no_dedup: false,
+            additional_args: vec![],
+            docs: None,
+            format_sort: None,
+            require_https_images: false,
+            order: SourceOrder::default(),
+            order_by: None,
+            order_dir: None,
+            cache_dir: None,
+            no_cache_dir: false,
+            single_ok: false,
+            summary_from_episodes: None,
+            sync_title: false,
+            description: None,
+            author: None,
+            sync_description: false,
+            categories: vec![],
+            channel_keywords: vec![],
+            channel_elements: vec![],
+            namespaces: vec![],
+            item_overrides: HashMap::new(),
+            sort_categories: false,
+            date_source: DateSource::default(),
+            title_source: TitleSource::default(),
+            disambiguate_titles: None,
+            strict: false,
+            enclosure_query: vec![],
+            prefer_fresh_metadata: false,
+            guid_from_enclosure: false,
+            guid_scheme: None,
+            no_itunes: false,
+            embed_chapters: false,
+            embed_info_json: false,
+            link_file: None,
+            thumbnail_preference: None,
+            convert_thumbnails: None,
+            embed_thumbnail: false,
+            restrict_filenames: false,
+            with_source: false,
+            write_downloaded_timestamp: false,
+            provisional: false,
+            feed_settings: None,
+            probe_duration: false,
+            trash_dir: None,
+            confirm_deletes: false,
+            live_from_start: false,
+            per_item_language: false,
+            episode_numbers: false,
+            geo_bypass_country: None,
+            sleep_interval: None,
+            max_sleep_interval: None,
+            sleep_requests: None,
+            match_filters: vec![],
+            include_members_only: false,
+            min_views: None,
+            include_unknown_views: false,
+            skip_durations: vec![],
+            prune_empty_dirs: false,
+            clean_stale_partials: None,
+            sub_langs: vec![],
+            mime_map: vec![],
+            prefer_free_formats: false,
+            skip_download: false,
+            remote_enclosures: false,
+        }
+    }
+}
+
+impl UpdateOptions {
+    /// How new items' GUIDs are built
+    fn guid_scheme(&self) -> GuidScheme {
+        match self.guid_scheme {
+            Some(ref guid_scheme) => guid_scheme.clone(),
+            None if self.guid_from_enclosure => GuidScheme::Enclosure,
+            None => GuidScheme::Id,
+        }
+    }
+
+    /// The format new media files are downloaded in
+    fn media_format(&self) -> MediaFormat {
+        if self.prefer_free_formats {
+            MediaFormat::WEBM
+        } else {
+            MediaFormat::MP4
+        }
+    }
+}
+
+/// What happened to an individual playlist entry during an update.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadStatus {
+    /// The media file was downloaded by this update
+    Downloaded,
+
+    /// The media file was already on disk, so wasn't downloaded again
+    Cached,
+
+    /// The entry wasn't expected to be downloaded, i.e. downloads were skipped or it had no duration
+    Skipped,
+
+    /// The media file was expected, but isn't on disk after downloading
+    Failed,
+}
+
+/// The outcome of an update for a single playlist entry.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DownloadResult {
+    /// The entry's id
+    pub id: String,
+
+    /// What happened to the entry
+    pub status: DownloadStatus,
+
+    /// Size of the media file on disk, in bytes
+    pub bytes: u64,
+
+    /// Path to the entry's media file
+    pub path: PathBuf,
+
+    /// Roughly how long `yt-dlp` spent on the entry, in whole seconds, from when it started on it
+    /// until it started on the next one (or finished). Only known for entries it downloaded.
+    pub elapsed: Option<u64>,
+}
+
+/// A summary of the changes made by an update.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct UpdateSummary {
+    /// Number of items newly added to the feed
+    pub added: usize,
+
+    /// Number of items removed from the feed by `keep`
+    pub removed: usize,
+
+    /// Number of items in the feed after the update
+    pub total: usize,
+
+    /// Number of entries whose media was downloaded
+    pub downloaded: usize,
+
+    /// Number of entries whose media was already on disk
+    pub cached: usize,
+
+    /// Number of entries which weren't downloaded
+    pub skipped: usize,
+
+    /// Number of entries whose media failed to download
+    pub failed: usize,
+
+    /// The outcome for each entry in the playlist
+    pub downloads: Vec<DownloadResult>,
+
+    /// Media files of items removed by `keep` which weren't deleted, pending confirmation
+    pub pending_deletions: Vec<PathBuf>,
+
+    /// Ids of entries left out of the feed because their enclosure URL couldn't be built
+    pub invalid_enclosures: Vec<String>,
+
+    /// Time spent running the downloader, in seconds
+    pub elapsed: f64,
+
+    /// Average download speed over the run, in bytes per second, counting only newly downloaded media
+    pub avg_speed: f64,
+
+    /// Files this run added to the media directory, including sidecar files like subtitles, for
+    /// syncing only what changed
+    pub created_files: Vec<PathBuf>,
+
+    /// Files this run removed from the media directory (or moved to the `trash_dir`)
+    pub deleted_files: Vec<PathBuf>,
+}
+
+impl UpdateSummary {
+    fn set_downloads(&mut self, downloads: Vec<DownloadResult>) {
+        let count = |status| {
+            downloads
+                .iter()
+                .filter(|download| download.status == status)
+                .count()
+        };
+
+        self.downloaded = count(DownloadStatus::Downloaded);
+        self.cached = count(DownloadStatus::Cached);
+        self.skipped = count(DownloadStatus::Skipped);
+        self.failed = count(DownloadStatus::Failed);
+        self.downloads = downloads;
+    }
+
+    fn set_elapsed(&mut self, elapsed: Duration) {
+        let downloaded_bytes: u64 = self
+            .downloads
+            .iter()
+            .filter(|download| download.status == DownloadStatus::Downloaded)
+            .map(|download| download.bytes)
+            .sum();
+
+        self.elapsed = elapsed.as_secs_f64();
+
+        self.avg_speed = if self.elapsed > 0.0 {
+            downloaded_bytes as f64 / self.elapsed
+        } else {
+            0.0
+        };
+    }
+}
+
+/// Options controlling how a feed is written out.
+#[derive(Clone, Debug)]
+pub struct WriteOptions {
+    /// Pretty-print the XML, rather than writing it tersely
+    pub pretty: bool,
+
+    /// Character encoding to write the feed in, and declare in its XML declaration
+    pub encoding: String,
+
+    /// Formats to write the feed in, each to its own file
+    pub formats: Vec<OutputFormat>,
+
+    /// Strip optional elements from the RSS feed to make it as small as possible; see `minimal_feed`
+    pub minimal: bool,
+
+    /// Tailor the RSS feed to a directory or validator, failing if it lacks anything the profile requires
+    pub profile: Option<FeedProfile>,
+
+    /// Write each output file's preview file instead, leaving the feed itself alone; see `preview_file`
+    pub preview: bool,
+
+    /// Also write the playlist's metadata from the latest update to `Channel::channel_info_file`
+    pub channel_info: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            pretty: true,
+            encoding: "UTF-8".to_string(),
+            formats: vec![OutputFormat::Rss],
+            minimal: false,
+            profile: None,
+            preview: false,
+            channel_info: false,
+        }
+    }
+}
+
+/// Directories and validators the RSS feed can be tailored to, with `WriteOptions::profile`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum FeedProfile {
+    /// Apple Podcasts, which requires the iTunes artwork, category, author and explicit flag,
+    /// with `itunes:explicit` written as `true` or `false`
+    Apple,
+
+    /// Google's podcast directories, which require the channel's link and artwork, with the
+    /// artwork also written as a standard RSS `<image>`
+    Google,
+
+    /// Plain RSS 2.0, omitting every namespaced element (iTunes, Dublin Core, Podcasting 2.0 and so on)
+    #[value(name = "strict-2.0")]
+    Strict20,
+}
+
+impl std::fmt::Display for FeedProfile {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use clap::ValueEnum;
+
+        match self.to_possible_value() {
+            Some(value) => write!(formatter, "{}", value.get_name()),
+            None => write!(formatter, "{:?}", self),
+        }
+    }
+}
+
+/// Formats a feed can be written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// An RSS podcast feed, written to the feed file itself
+    Rss,
+
+    /// A JSON Feed (<https://jsonfeed.org>), written next to the feed file with a `json` extension
+    Jsonfeed,
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use clap::ValueEnum;
+
+        match self.to_possible_value() {
+            Some(value) => write!(formatter, "{}", value.get_name()),
+            None => write!(formatter, "{:?}", self),
+        }
+    }
+}
+
+/// The oldest `yt-dlp` release with every option Playcaster passes it (`--sleep-requests`, the
+/// `availability` match filter field and so on)
+pub const MIN_YT_DLP_VERSION: &str = "2022.04.08";
+
+/// Whether a `yt-dlp` version (`YYYY.MM.DD`, perhaps with a further nightly build number) is at
+/// least `MIN_YT_DLP_VERSION`
+pub fn yt_dlp_version_ok(version: &str) -> bool {
+    let parse = |version: &str| {
+        version
+            .split('.')
+            .map(|part| part.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .ok()
+    };
+
+    match (parse(version.trim()), parse(MIN_YT_DLP_VERSION)) {
+        (Some(version), Some(minimum)) => version >= minimum,
+        _ => false,
+    }
+}
+
+/// Playcaster's version and the detected `yt-dlp` version, for bug reports.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct VersionReport {
+    /// Playcaster's own version
+    pub playcaster: String,
+
+    /// The downloader's version, if it could be run
+    pub yt_dlp: Option<String>,
+
+    /// Whether the downloader's version is at least `MIN_YT_DLP_VERSION`
+    pub yt_dlp_ok: bool,
+}
+
+impl VersionReport {
+    pub fn new<D: Downloader>(downloader: &D) -> Self {
+        let yt_dlp = downloader.version();
+
+        Self {
+            playcaster: PKG_VERSION.to_string(),
+            yt_dlp_ok: yt_dlp.as_deref().is_some_and(yt_dlp_version_ok),
+            yt_dlp,
+        }
+    }
+}
+
+/// The result of updating a single channel, for machine-readable run output.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ChannelSummary {
+    /// Path to the channel's RSS feed file
+    pub feed_file: PathBuf,
+
+    /// The playlist URL the channel was updated from
+    pub playlist_url: Url,
+
+    /// The changes made by the update
+    #[serde(flatten)]
+    pub summary: UpdateSummary,
+}
+
+/// A line of a channel's history file, recording one run's summary and when it finished.
+#[derive(Serialize)]
+struct HistoryEntry<'a> {
+    timestamp: String,
+
+    #[serde(flatten)]
+    channel: &'a ChannelSummary,
+}
+
+/// Appends a line recording `summary` to the JSON Lines history file at `path`, timestamped now,
+/// creating the file if need be. See `Channel::history_file` for the default path.
+pub fn append_history(path: &Path, summary: &ChannelSummary) -> Result<(), Error> {
+    let entry = HistoryEntry {
+        timestamp: Utc::now().to_rfc3339(),
+        channel: summary,
+    };
+
+    let mut line = serde_json::to_string(&entry).map_err(std::io::Error::from)?;
+    line.push('\n');
+
+    // Written in one go, so concurrent runs don't interleave their lines
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(line.as_bytes())?;
+
+    Ok(())
+}
+
+/// The status `exit_code` gives when asked to signal that a run added nothing, distinct from the
+/// status for errors so scripts can tell the two apart.
+pub const NO_NEW_ITEMS_EXIT_CODE: u8 = 3;
+
+/// The process exit status for a successful run. That's 0, unless `fail_if_no_new` is set and none
+/// of the channels gained any items, in which case it's `NO_NEW_ITEMS_EXIT_CODE`.
+pub fn exit_code(summaries: &[ChannelSummary], fail_if_no_new: bool) -> u8 {
+    if fail_if_no_new && summaries.iter().all(|channel| channel.summary.added == 0) {
+        NO_NEW_ITEMS_EXIT_CODE
+    } else {
+        0
+    }
+}
+
+/// An inconsistency `Channel::check` found between a feed and the media on disk.
+#[derive(ThisError, Debug)]
+pub enum CheckProblem {
+    /// The feed file doesn't contain a feed
+    #[error("there is no feed")]
+    NoFeed,
+
+    /// The feed doesn't pass validation
+    #[error("the feed is invalid: {0}")]
+    Invalid(rss::validation::ValidationError),
+
+    /// An item has no enclosure
+    #[error("item \"{0}\" has no enclosure")]
+    MissingEnclosure(String),
+
+    /// An item's enclosure URL can't be parsed
+    #[error("item \"{0}\" has a malformed enclosure URL \"{1}\": {2}")]
+    MalformedEnclosureUrl(String, String, url::ParseError),
+
+    /// An item's enclosure isn't served from under the base URL
+    #[error("item \"{0}\" has an enclosure outside the base URL: {1}")]
+    ForeignEnclosureUrl(String, Url),
+
+    /// An item's media file isn't on disk
+    #[error("item \"{0}\" is missing its media file {1:?}")]
+    MissingMedia(String, PathBuf),
+}
+
+/// A difference `diff_items` found between an old and a new version of a feed, labelled with the
+/// item's title or id.
+#[derive(ThisError, Clone, Debug, PartialEq, Eq)]
+pub enum ItemChange {
+    /// An item which is only in the new feed
+    #[error("+ {0}")]
+    Added(String),
+
+    /// An item which is only in the old feed
+    #[error("- {0}")]
+    Removed(String),
+
+    /// An item in both feeds, with the elements which differ between them
+    #[error("~ {0} ({fields})", fields = .1.join(", "))]
+    Modified(String, Vec<&'static str>),
+}
+
+/// Lists the items added, removed and modified between two versions of a feed, matching them up
+/// as updates do. Added and modified items come in the new feed's order, followed by removed ones.
+pub fn diff_items(old: Option<&RSSChannel>, new: &RSSChannel) -> Vec<ItemChange> {
+    let old_items = old.map(|old| &old.items[..]).unwrap_or_default();
+
+    let label = |item: &RSSItem| {
+        let id = item_id(item);
+
+        match (item.title(), id) {
+            (Some(title), Some(id)) => format!("{:?} [{}]", title, id),
+            (Some(title), None) => format!("{:?}", title),
+            (None, id) => id.unwrap_or_default(),
+        }
+    };
+
+    let old_by_key: HashMap<_, _> = old_items
+        .iter()
+        .filter_map(|item| item_dedup_key(item).map(|key| (key, item)))
+        .collect();
+
+    let new_keys: HashSet<_> = new.items.iter().filter_map(item_dedup_key).collect();
+
+    let mut changes = vec![];
+
+    for item in &new.items {
+        let old_item = match item_dedup_key(item).and_then(|key| old_by_key.get(&key)) {
+            Some(old_item) => old_item,
+            None => {
+                changes.push(ItemChange::Added(label(item)));
+                continue;
+            }
+        };
+
+        let fields: Vec<_> = [
+            ("title", old_item.title != item.title),
+            ("link", old_item.link != item.link),
+            ("description", old_item.description != item.description),
+            ("pubDate", old_item.pub_date != item.pub_date),
+            ("enclosure", old_item.enclosure != item.enclosure),
+            ("guid", old_item.guid != item.guid),
+            ("source", old_item.source != item.source),
+            ("itunes", old_item.itunes_ext != item.itunes_ext),
+            (
+                "dublin core",
+                old_item.dublin_core_ext != item.dublin_core_ext,
+            ),
+            ("extensions", old_item.extensions != item.extensions),
+        ]
+        .into_iter()
+        .filter_map(|(field, changed)| changed.then_some(field))
+        .collect();
+
+        if !fields.is_empty() {
+            changes.push(ItemChange::Modified(label(item), fields));
+        }
+    }
+
+    changes.extend(
+        old_items
+            .iter()
+            .filter(|item| item_dedup_key(item).is_none_or(|key| !new_keys.contains(&key)))
+            .map(|item| ItemChange::Removed(label(item))),
+    );
+
+    changes
+}
+
+/// Statistics about a channel's feed and the media on disk, from `Channel::stats`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChannelStats {
+    /// Number of items in the feed
+    pub item_count: usize,
+
+    /// Total size in bytes of the items' media files which are on disk
+    pub media_bytes: u64,
+
+    /// The earliest valid `pub_date` among the items, if any
+    pub oldest_pub_date: Option<DateTime<FixedOffset>>,
+
+    /// The latest valid `pub_date` among the items, if any
+    pub newest_pub_date: Option<DateTime<FixedOffset>>,
+
+    /// Number of items whose media file isn't on disk
+    pub missing_media: usize,
+}
+
+/// The files `Channel::purge` deletes to retire a channel, from `Channel::purge_plan`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PurgePlan {
+    /// The feed file and the other files Playcaster writes beside it which exist, such as the JSON
+    /// Feed, preview, history and channel info files
+    pub feed_files: Vec<PathBuf>,
+
+    /// The media directory, if it exists, which is deleted along with everything in it
+    pub media_dir: Option<PathBuf>,
+
+    /// Files in the media directory which belong to the feed's items
+    pub referenced_files: Vec<PathBuf>,
+
+    /// Files in the media directory which none of the feed's items reference
+    pub unreferenced_files: Vec<PathBuf>,
+}
+
+/// Per-channel settings stored in the feed itself, as `playcaster:keep`, `playcaster:limit` and
+/// `playcaster:category` channel elements, so each feed can carry its own configuration.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FeedSettings {
+    /// Maximum number of items to keep in the feed; see `UpdateOptions::keep`
+    pub keep: Option<usize>,
+
+    /// Maximum number of videos to download; see `UpdateOptions::download_limit`
+    pub download_limit: Option<usize>,
+
+    /// Apple Podcasts categories to file the feed under; see `UpdateOptions::categories`
+    pub categories: Vec<Category>,
+}
+
+impl FeedSettings {
+    /// Fills in any settings not given here from `stored`, e.g. those read from a feed with
+    /// `Channel::feed_settings`
+    pub fn or(self, stored: FeedSettings) -> Self {
+        Self {
+            keep: self.keep.or(stored.keep),
+            download_limit: self.download_limit.or(stored.download_limit),
+            categories: match self.categories.is_empty() {
+                true => stored.categories,
+                false => self.categories,
+            },
+        }
+    }
+
+    /// Applies these settings to `options`, leaving alone any which aren't given, and has the
+    /// update store them in the feed
+    pub fn apply_to(&self, options: &mut UpdateOptions) {
+        if let Some(keep) = self.keep {
+            options.keep = Some(keep);
+        }
+
+        if let Some(download_limit) = self.download_limit {
+            options.download_limit = download_limit;
+        }
+
+        if !self.categories.is_empty() {
+            options.categories = self.categories.clone();
+        }
+
+        options.feed_settings = Some(self.clone());
+    }
+}
+
+/// Something which can run `yt-dlp` (or a stand-in for it) against a URL.
+pub trait Downloader {
+    /// Runs the downloader against `url` with the given arguments, returning its parsed JSON output
+    fn run(&self, url: &Url, args: &[String]) -> Result<YoutubeDlOutput, Error>;
+
+    /// Reads the downloader's version, from `yt-dlp --version` by default.
+    ///
+    /// Returns `None` if the version couldn't be determined, i.e. if `yt-dlp` isn't installed.
+    fn version(&self) -> Option<String> {
+        let output = std::process::Command::new("yt-dlp")
+            .arg("--version")
+            .output()
+            .map_err(|err| warn!("Couldn't run yt-dlp: {:?}", err))
+            .ok()?;
+
+        if !output.status.success() {
+            warn!("yt-dlp --version failed");
+            return None;
+        }
+
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .filter(|version| !version.is_empty())
+    }
+
+    /// Reads the duration of a downloaded media file, using `ffprobe` by default.
+    ///
+    /// Returns `None` if the duration couldn't be determined.
+    fn probe_duration(&self, path: &Path) -> Option<Duration> {
+        let output = std::process::Command::new("ffprobe")
+            .args([
+                "-v",
+                "error",
+                "-show_entries",
+                "format=duration",
+                "-of",
+                "default=noprint_wrappers=1:nokey=1",
+            ])
+            .arg(path)
+            .output()
+            .map_err(|err| warn!("Couldn't run ffprobe: {:?}", err))
+            .ok()?;
+
+        if !output.status.success() {
+            warn!("ffprobe failed for {:?}", path);
+            return None;
+        }
+
+        String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+    }
+}
+
+/// The default `Downloader`, which runs `yt-dlp` from the `PATH`.
+pub struct YtDlp;
+
+impl Downloader for YtDlp {
+    fn run(&self, url: &Url, args: &[String]) -> Result<YoutubeDlOutput, Error> {
+        let mut ytdl = YoutubeDl::new(url.clone());
+
+        ytdl.youtube_dl_path("yt-dlp");
+
+        args.iter().for_each(|arg| {
+            ytdl.extra_arg(arg);
+        });
+
+        Ok(ytdl.run()?)
+    }
+}
+
+/// Reads a list of playlist URLs, one per line, skipping blank lines and `#` comments
+pub fn read_playlists<T: BufRead>(reader: T) -> Result<Vec<Url>, Error> {
+    let mut playlist_urls = vec![];
+
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        playlist_urls.push(Url::parse(line)?);
+    }
+
+    Ok(playlist_urls)
+}
+
+/// Reads a playlist from `yt-dlp --dump-single-json` output captured earlier, for building a feed
+/// without running `yt-dlp`. Output for a single video is wrapped in a one-entry playlist.
+pub fn read_playlist_json<T: Read>(reader: T) -> Result<youtube_dl::Playlist, Error> {
+    let value: serde_json::Value = serde_json::from_reader(reader).map_err(std::io::Error::from)?;
+
+    // `yt-dlp` marks playlists with their `_type`, as `youtube_dl` itself checks
+    let playlist = if value["_type"] == "playlist" {
+        serde_json::from_value(value)
+    } else {
+        serde_json::from_value(value).map(playlist_from_single_video)
+    };
+
+    Ok(playlist.map_err(std::io::Error::from)?)
+}
+
+/// Environment variable holding `yt-dlp` arguments to pass on every run, such as a proxy or rate
+/// limit; see `default_args`.
+pub const DEFAULT_ARGS_VAR: &str = "PLAYCASTER_DEFAULT_ARGS";
+
+/// The `yt-dlp` arguments from the `DEFAULT_ARGS_VAR` environment variable, split as with
+/// `split_default_args`.
+///
+/// These go before any other additional arguments, which come after Playcaster's own, so the
+/// defaults override Playcaster's and are themselves overridden by arguments given for a run.
+pub fn default_args() -> Vec<String> {
+    std::env::var(DEFAULT_ARGS_VAR)
+        .map(|args| split_default_args(&args))
+        .unwrap_or_default()
+}
+
+/// Splits the value of the `DEFAULT_ARGS_VAR` environment variable into arguments on whitespace
+pub fn split_default_args(value: &str) -> Vec<String> {
+    value.split_whitespace().map(String::from).collect()
+}
+
+/// Reads additional `yt-dlp` arguments, one per line, skipping blank lines and `#` comments.
+///
+/// Each line is a single argument, so an option and its value go on separate lines.
+pub fn read_args_file<T: BufRead>(reader: T) -> Result<Vec<String>, Error> {
+    let mut args = vec![];
+
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        args.push(line.to_string());
+    }
+
+    Ok(args)
+}
+
+/// Reads an item overrides file: a JSON object mapping video ids to the changes to make to their
+/// items, e.g. `{"QWkUFkXcx9I": {"elements": [{"prefix": "podcast", "name": "soundbite", ...}]}}`
+pub fn read_item_overrides<T: Read>(reader: T) -> Result<HashMap<String, ItemOverride>, Error> {
+    Ok(serde_json::from_reader(reader).map_err(std::io::Error::from)?)
+}
+
+/// Whether to delete the files an update left pending; see `UpdateSummary::pending_deletions`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeletionDecision {
+    /// Delete them, as they were confirmed up front
+    Delete,
+
+    /// Leave them, as there are none, or there's nobody to ask about them
+    Keep,
+
+    /// Ask whether to delete them at the terminal
+    Ask,
+}
+
+/// Decides what to do about `pending_deletions` before anyone's asked: files `confirmed` up front
+/// (i.e. with `--yes`) are deleted, otherwise they're only asked about when `interactive`.
+pub fn deletion_decision(
+    pending_deletions: &[PathBuf],
+    confirmed: bool,
+    interactive: bool,
+) -> DeletionDecision {
+    match (pending_deletions.is_empty(), confirmed, interactive) {
+        (true, _, _) => DeletionDecision::Keep,
+        (false, true, _) => DeletionDecision::Delete,
+        (false, false, true) => DeletionDecision::Ask,
+        (false, false, false) => DeletionDecision::Keep,
+    }
+}
+
+/// Deletes a media file, or if `trash_dir` is given, moves it in there instead.
+pub fn remove_media_file(path: &Path, trash_dir: Option<&Path>) -> std::io::Result<()> {
+    let trash_dir = match trash_dir {
+        Some(trash_dir) => trash_dir,
+        None => {
+            debug!("Attempting to remove file: {:?}", path);
+            return std::fs::remove_file(path);
+        }
+    };
+
+    let file_name = path.file_name().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{:?} has no file name", path),
+        )
+    })?;
+
+    let destination = trash_dir.join(file_name);
+
+    debug!(
+        "Attempting to move file to trash: {:?} -> {:?}",
+        path, destination
+    );
+
+    std::fs::create_dir_all(trash_dir)?;
+
+    // Renaming fails across filesystems, so fall back to copying
+    if std::fs::rename(path, &destination).is_err() {
+        std::fs::copy(path, &destination)?;
+        std::fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
+
+/// Derives a feed file path within `feed_dir` for the given playlist URL.
+///
+/// The name is taken from the URL's `list` query parameter if it has one (as YouTube playlists do),
+/// otherwise from the last segment of its path, falling back to its host. The name is slugged, but
+/// an existing feed file named before names were slugged is still used.
+pub fn feed_file_for_playlist(feed_dir: &Path, playlist_url: &Url) -> PathBuf {
+    let name = playlist_url
+        .query_pairs()
+        .find(|(key, _)| key == "list")
+        .map(|(_, value)| value.to_string())
+        .or_else(|| {
+            playlist_url
+                .path_segments()
+                .and_then(|segments| segments.rev().find(|segment| !segment.is_empty()))
+                .map(|segment| segment.to_string())
+        })
+        .or_else(|| playlist_url.host_str().map(|host| host.to_string()))
+        .unwrap_or_else(|| PKG_NAME.to_string());
+
+    let feed_file = feed_dir.join(format!("{}.xml", slugify(&name)));
+    let legacy_feed_file = feed_dir.join(format!("{}.xml", name));
+
+    // Keep using a feed file created before names were slugged, as long as it's within `feed_dir`
+    if feed_file != legacy_feed_file
+        && !feed_file.exists()
+        && legacy_feed_file.parent() == Some(feed_dir)
+        && legacy_feed_file.is_file()
+    {
+        debug!("Using existing feed file: {:?}", legacy_feed_file);
+        return legacy_feed_file;
+    }
+
+    feed_file
+}
+
+/// Turns `name` into a slug which is safe to use as both a file name and a URL path segment.
+///
+/// ASCII letters, digits, `-` and `_` are kept, and each run of any other characters becomes a
+/// single `-`. Names with nothing left are replaced with the package name.
+pub fn slugify(name: &str) -> String {
+    let slug = name
+        .split(|character: char| {
+            !(character.is_ascii_alphanumeric() || character == '-' || character == '_')
+        })
+        .filter(|part| !part.is_empty())
+        .join("-");
+
+    let slug = slug.trim_matches('-');
+
+    if slug.is_empty() {
+        PKG_NAME.to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// Options controlling how a batch of channels, i.e. from a playlists file, is updated.
+#[derive(Clone, Debug, Default)]
+pub struct BatchOptions {
+    /// Stop the whole batch when a channel's feed fails validation, rather than skipping it
+    pub abort_on_validation_error: bool,
+
+    /// Set (i.e. from a signal handler) to stop the batch once the current channel is finished
+    pub shutdown: Arc<AtomicBool>,
+
+    /// How long to wait between one channel's update and the next, to avoid rate limits
+    pub inter_channel_delay: Option<Duration>,
+
+    /// Write each channel in the background while the next one updates, with up to this many more
+    /// waiting to be written, for `update_and_write_channels`. Otherwise each channel is written
+    /// before the next one starts.
+    pub write_queue: Option<usize>,
+}
+
+static SHUTDOWN: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+/// Makes Ctrl-C set `shutdown`, i.e. `BatchOptions::shutdown`, rather than exiting immediately,
+/// so a batch never stops partway through writing a feed. Only the first call has any effect.
+///
+/// Ctrl-C also reaches any `yt-dlp` run in progress, which `update_channels` allows for.
+#[cfg(unix)]
+pub fn install_shutdown_handler(shutdown: Arc<AtomicBool>) {
+    extern "C" fn handle_sigint(_: libc::c_int) {
+        if let Some(shutdown) = SHUTDOWN.get() {
+            shutdown.store(true, Ordering::SeqCst);
+        }
+    }
+
+    if SHUTDOWN.set(shutdown).is_ok() {
+        unsafe {
+            libc::signal(
+                libc::SIGINT,
+                handle_sigint as extern "C" fn(libc::c_int) as libc::sighandler_t,
+            );
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn install_shutdown_handler(_shutdown: Arc<AtomicBool>) {}
+
+/// Sleeps for `duration`, waking early if `shutdown` is set in the meantime
+fn sleep_unless_shutdown(duration: Duration, shutdown: &AtomicBool) {
+    let deadline = std::time::Instant::now() + duration;
+
+    while !shutdown.load(Ordering::SeqCst) {
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+
+        if remaining.is_zero() {
+            break;
+        }
+
+        std::thread::sleep(remaining.min(Duration::from_millis(100)));
+    }
+}
+
+/// Checks that no two of `channels` keep their media in the same directory, which would mix their
+/// files, and each one's pruning delete the other's.
+///
+/// Feeds in the same directory whose file names share a stem (or slug to the same one) have the
+/// same media directory unless they're given distinct ones with `Channel::media_dir`.
+pub fn check_media_dirs(channels: &[Channel]) -> Result<(), Error> {
+    let mut feed_files: HashMap<PathBuf, &Path> = HashMap::new();
+
+    for channel in channels {
+        let media_dir = channel.media_dir()?;
+
+        if let Some(other) = feed_files.insert(media_dir.clone(), &channel.feed_file) {
+            return Err(Error::MediaDirCollisionError(
+                other.to_path_buf(),
+                channel.feed_file.clone(),
+                media_dir,
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that no other RSS feed file beside `channel`'s would share its media directory, as with
+/// `check_media_dirs`, for feeds updated separately rather than in one batch.
+///
+/// Channels given their own `Channel::media_dir` aren't checked.
+pub fn check_sibling_media_dirs(channel: &Channel) -> Result<(), Error> {
+    if channel.media_dir.is_some() {
+        return Ok(());
+    }
+
+    let media_dir = channel.media_dir()?;
+
+    let parent = channel
+        .feed_file
+        .parent()
+        .ok_or_else(|| Error::ParentPathError(channel.feed_file.clone()))?;
+
+    // A bare file name's parent is empty, meaning the current directory
+    let dir = match parent.as_os_str().is_empty() {
+        true => Path::new("."),
+        false => parent,
+    };
+
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(error.into()),
+    };
+
+    for entry in entries {
+        let file_name = entry?.file_name();
+        let sibling = parent.join(&file_name);
+
+        let is_feed = sibling
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .is_some_and(|extension| ["xml", "rss"].contains(&extension.as_str()));
+
+        if !is_feed
+            || file_name == channel.feed_file.file_name().unwrap_or_default()
+            || !sibling.is_file()
+        {
+            continue;
+        }
+
+        let other = Channel {
+            feed_file: sibling,
+            playlist_url: channel.playlist_url.clone(),
+            rss_channel: None,
+            media_dir: None,
+            url_path_prefix: None,
+            channel_info: None,
+        };
+
+        if other.media_dir()? == media_dir {
+            return Err(Error::MediaDirCollisionError(
+                other.feed_file,
+                channel.feed_file.clone(),
+                media_dir,
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs `update` for each channel in turn, collecting the results.
+///
+/// A channel whose update fails with `Error::FeedValidationError` is logged and skipped so the
+/// rest of the batch still runs, unless `abort_on_validation_error` is set. Any other error stops
+/// the batch.
+///
+/// Channels paused with a `playcaster:disabled` element in their feed (see `Channel::is_disabled`)
+/// are logged and skipped.
+///
+/// Once `shutdown` is set, no further channels are started, and the results so far are returned.
+/// A channel whose update fails once `shutdown` is set, as Ctrl-C interrupted its `yt-dlp` run
+/// too, is taken to have been interrupted rather than to have failed.
+///
+/// With an `inter_channel_delay`, each channel after the first waits that long before starting.
+pub fn update_channels<I, F, T>(
+    channels: I,
+    options: &BatchOptions,
+    mut update: F,
+) -> Result<Vec<T>, Error>
+where
+    I: IntoIterator<Item = Channel>,
+    F: FnMut(Channel) -> Result<T, Error>,
+{
+    let mut results = vec![];
+
+    for_each_channel(channels, options, |channel| {
+        results.push(update(channel)?);
+        Ok(true)
+    })?;
+
+    Ok(results)
+}
+
+/// Runs `update` for each channel in turn as `update_channels` does, then `write` with each
+/// update's result, collecting what it returns, in the channels' order.
+///
+/// With a `write_queue`, each channel is written on another thread while the next one updates,
+/// overlapping one's file writes with the other's downloads. Updates wait once that many
+/// channels are waiting to be written, so the queue stays bounded. A write failing is handled as
+/// an update failing would be, and stops any further updates if that stops the batch. Once
+/// `shutdown` is set, channels already updated are still written.
+pub fn update_and_write_channels<I, U, W, P, T>(
+    channels: I,
+    options: &BatchOptions,
+    mut update: U,
+    mut write: W,
+) -> Result<Vec<T>, Error>
+where
+    I: IntoIterator<Item = Channel>,
+    U: FnMut(Channel) -> Result<P, Error>,
+    W: FnMut(P) -> Result<T, Error> + Send,
+    P: Send,
+    T: Send,
+{
+    let write_queue = match options.write_queue {
+        Some(write_queue) => write_queue,
+        None => return update_channels(channels, options, |channel| write(update(channel)?)),
+    };
+
+    let (sender, receiver) = std::sync::mpsc::sync_channel::<(PathBuf, P)>(write_queue);
+
+    std::thread::scope(|scope| {
+        let writer = scope.spawn(move || {
+            let mut results = vec![];
+
+            for (feed_file, updated) in receiver {
+                match with_log_context(&feed_file, || write(updated)) {
+                    Ok(result) => results.push(result),
+                    Err(error) => {
+                        if !continue_batch(&feed_file, error, options)? {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            Ok(results)
+        });
+
+        // The writer only hangs up once it's stopped the batch, which joining it reports
+        let updated = for_each_channel(channels, options, |channel| {
+            let feed_file = channel.feed_file.clone();
+            let updated = update(channel)?;
+            Ok(sender.send((feed_file, updated)).is_ok())
+        });
+
+        drop(sender);
+
+        let written = writer
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+
+        updated?;
+        written
+    })
+}
+
+/// Runs `f` for each channel in turn, for `update_channels`, until it returns `false`.
+///
+/// Paused channels are skipped, and `inter_channel_delay` and `shutdown` are honoured between
+/// channels. Errors are handled with `continue_batch`.
+fn for_each_channel<I, F>(channels: I, options: &BatchOptions, mut f: F) -> Result<(), Error>
+where
+    I: IntoIterator<Item = Channel>,
+    F: FnMut(Channel) -> Result<bool, Error>,
+{
+    let mut started = 0;
+
+    for channel in channels {
+        if channel.is_disabled() {
+            info!("Skipping {:?}, as it's paused", channel.feed_file);
+            continue;
+        }
+
+        if let (Some(delay), true) = (options.inter_channel_delay, started > 0) {
+            debug!(
+                "Waiting {:?} before updating {:?}",
+                delay, channel.feed_file
+            );
+            sleep_unless_shutdown(delay, &options.shutdown);
+        }
+
+        if options.shutdown.load(Ordering::SeqCst) {
+            info!("Shutting down before updating {:?}", channel.feed_file);
+            break;
+        }
+
+        let feed_file = channel.feed_file.clone();
+        started += 1;
+
+        let carry_on = match with_log_context(&feed_file, || f(channel)) {
+            Ok(carry_on) => carry_on,
+            Err(error) => continue_batch(&feed_file, error, options)?,
+        };
+
+        if !carry_on {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether a batch carries on after a channel fails with `error`: invalid feeds are skipped
+/// unless `abort_on_validation_error` is set, and any error once `shutdown` is set is taken as an
+/// interruption, stopping the batch. Any other error is returned, failing it.
+fn continue_batch(feed_file: &Path, error: Error, options: &BatchOptions) -> Result<bool, Error> {
+    match error {
+        Error::FeedValidationError(feed_file, error) if !options.abort_on_validation_error => {
+            error!("Skipping invalid feed {:?}: {}", feed_file, error);
+            Ok(true)
+        }
+        error if options.shutdown.load(Ordering::SeqCst) => {
+            info!(
+                "Shutting down, as updating {:?} was interrupted: {}",
+                feed_file, error
+            );
+            Ok(false)
+        }
+        error => Err(error),
+    }
+}
+
+thread_local! {
+    /// The feed file of the channel this thread is working on, if any; see `log_context`
+    static LOG_CONTEXT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// The feed file of the channel the current thread is working on, for loggers to add to each
+/// line, so lines logged while updating several channels can be told apart.
+pub fn log_context() -> Option<String> {
+    LOG_CONTEXT.with(|context| context.borrow().clone())
+}
+
+/// Runs `f` with `feed_file` as the `log_context`, restoring the previous context afterwards
+pub fn with_log_context<T>(feed_file: &Path, f: impl FnOnce() -> T) -> T {
+    let _log_context = LogContext::enter(feed_file);
+    f()
+}
+
+/// Sets the `log_context` until it's dropped, when the previous context is restored
+struct LogContext(Option<String>);
+
+impl LogContext {
+    fn enter(feed_file: &Path) -> Self {
+        let context = feed_file.display().to_string();
+        Self(LOG_CONTEXT.with(|current| current.replace(Some(context))))
+    }
+}
+
+impl Drop for LogContext {
+    fn drop(&mut self) {
+        LOG_CONTEXT.with(|current| *current.borrow_mut() = self.0.take());
+    }
+}
+
+/// The file a preview of the feed at `path` is written to, with `.preview` before its extension,
+/// e.g. `mightycarmods.preview.xml`
+pub fn preview_file(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+    match path.extension() {
+        Some(extension) => {
+            path.with_file_name(format!("{}.preview.{}", stem, extension.to_string_lossy()))
+        }
+        None => path.with_file_name(format!("{}.preview", stem)),
+    }
+}
+
+/// A hidden temporary file next to `path`, for writing to before moving it into place.
+///
+/// Its name includes the process id and a random component, so concurrent writes from this or
+/// other processes, e.g. parallel updates sharing a feed directory, each get their own.
+fn temp_path(path: &Path) -> Result<PathBuf, Error> {
+    use std::hash::{BuildHasher, Hasher};
+    use std::sync::atomic::AtomicU64;
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::FileStemError(path.to_path_buf()))?;
+
+    // `RandomState` is randomly seeded, and hashing a counter keeps it unique within this process
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+
+    Ok(path.with_file_name(format!(
+        ".{}.{}.{:016x}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        hasher.finish()
+    )))
+}
+
+/// A writer which counts the bytes written through it
+struct CountingWriter<W: Write> {
+    inner: W,
+    written: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writes `path` via `write`, replacing any existing file only once everything was written.
+///
+/// The output goes to a temporary file next to `path`, rather than in the OS's temporary directory,
+/// so it can be renamed into place on the same filesystem. It's removed if writing fails.
+fn write_atomically<F>(path: &Path, write: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut CountingWriter<File>) -> Result<(), Error>,
+{
+    let temp_file = temp_path(path)?;
+
+    let mut writer = CountingWriter {
+        // Never share a temporary file, even in the unlikely event its name was already taken
+        inner: File::create_new(&temp_file)?,
+        written: 0,
+    };
+
+    // Syncing surfaces errors the OS would otherwise only report once it flushes the file
+    let result = write(&mut writer).and_then(|_| Ok(writer.inner.sync_all()?));
+
+    if let Err(error) = result {
+        std::fs::remove_file(&temp_file)
+            .unwrap_or_else(|err| warn!("Couldn't remove temporary file: {:?}", err));
+
+        return Err(Error::WriteError(
+            path.to_path_buf(),
+            writer.written,
+            Box::new(error),
+        ));
+    }
+
+    move_into_place(&temp_file, path)?;
+
+    Ok(())
+}
+
+/// Renames `temp_file` to `path`, falling back to copying it when they're on different devices.
+///
+/// That happens despite the temporary file being next to `path` when `path` is itself a mount
+/// point, e.g. a file bind-mounted into a container. The copy isn't atomic, but is synced before
+/// the temporary file is removed.
+fn move_into_place(temp_file: &Path, path: &Path) -> std::io::Result<()> {
+    match std::fs::rename(temp_file, path) {
+        Err(error) if error.kind() == std::io::ErrorKind::CrossesDevices => {
+            debug!("Copying {:?} into place, as {:?}", temp_file, error);
+            copy_into_place(temp_file, path)
+        }
+        result => result,
+    }
+}
+
+/// Copies `temp_file` over `path`, syncing it to disk, then removes `temp_file`
+fn copy_into_place(temp_file: &Path, path: &Path) -> std::io::Result<()> {
+    let mut file = File::create(path)?;
+    std::io::copy(&mut File::open(temp_file)?, &mut file)?;
+    file.sync_all()?;
+
+    std::fs::remove_file(temp_file)
+}
+
+/// `yt-dlp` filter rejecting videos which can't be downloaded without signing in. Videos whose
+/// availability isn't known pass it.
+const MEMBERS_ONLY_FILTER: &str =
+    "availability!=?subscriber_only & availability!=?premium_only & availability!=?needs_auth";
+
+/// Whether `yt-dlp`'s arguments give it cookies or credentials to sign in with
+fn has_auth_args(args: &[String]) -> bool {
+    args.iter().any(|arg| {
+        arg.starts_with("--cookies")
+            || arg.starts_with("--username")
+            || arg.starts_with("--netrc")
+            || arg == "-u"
+    })
+}
+
+/// File extensions of the thumbnails `yt-dlp` downloads, or converts them to
+const THUMBNAIL_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+
+/// Whether a file in the media directory is a standalone thumbnail for a video whose media file
+/// has one of `file_stems`, as `yt-dlp` names them after their media file
+fn is_thumbnail_file(path: &Path, file_stems: &HashSet<String>) -> bool {
+    let is_thumbnail_extension = path.extension().is_some_and(|extension| {
+        THUMBNAIL_EXTENSIONS.contains(&extension.to_string_lossy().to_lowercase().as_str())
+    });
+
+    is_thumbnail_extension
+        && path
+            .file_stem()
+            .is_some_and(|stem| file_stems.contains(stem.to_string_lossy().as_ref()))
+}
+
+/// Whether to filter out members-only videos, which weren't asked for and can't be signed in for
+fn skips_members_only(options: &UpdateOptions) -> bool {
+    !options.include_members_only && !has_auth_args(&options.additional_args)
+}
+
+/// The filters to pass to `yt-dlp`'s `--match-filter`, excluding members-only videos unless they
+/// were asked for, or can be signed in for, as well as videos with too few views or a skipped
+/// duration.
+///
+/// As an entry is kept if it matches any one filter, the exclusions are added to every filter.
+/// `yt-dlp` filters are only ever conjunctions, so they can simply be appended with `&`.
+fn match_filters(options: &UpdateOptions) -> Vec<String> {
+    let mut conditions = vec![];
+
+    if skips_members_only(options) {
+        conditions.push(MEMBERS_ONLY_FILTER.to_string());
+    }
+
+    if let Some(min_views) = options.min_views {
+        // `?` lets videos whose view count isn't known through
+        let unknown = if options.include_unknown_views {
+            "?"
+        } else {
+            ""
+        };
+        conditions.push(format!("view_count >={} {}", unknown, min_views));
+    }
+
+    // `?` lets videos whose duration isn't known through, as it can't be one to skip
+    for duration in options.skip_durations.iter().unique() {
+        conditions.push(format!("duration !=? {}", duration));
+    }
+
+    if conditions.is_empty() {
+        return options.match_filters.clone();
+    }
+
+    let conditions = conditions.join(" & ");
+
+    if options.match_filters.is_empty() {
+        return vec![conditions];
+    }
+
+    options
+        .match_filters
+        .iter()
+        .map(|match_filter| format!("{} & {}", match_filter, conditions))
+        .collect()
+}
+
+/// Whether a video has at least `min_views` views, if set. Videos whose view count isn't known
+/// only pass if `include_unknown_views`.
+fn has_min_views(video: &youtube_dl::SingleVideo, options: &UpdateOptions) -> bool {
+    match (options.min_views, video.view_count) {
+        (None, _) => true,
+        (Some(min_views), Some(view_count)) => view_count >= 0 && view_count as u64 >= min_views,
+        (Some(_), None) => options.include_unknown_views,
+    }
+}
+
+/// Whether a video lasts exactly one of the `skip_durations` seconds
+fn has_skipped_duration(video: &youtube_dl::SingleVideo, options: &UpdateOptions) -> bool {
+    let duration = video.duration.as_ref().and_then(serde_json::Value::as_f64);
+
+    duration.is_some_and(|duration| {
+        options
+            .skip_durations
+            .iter()
+            .any(|skipped| duration == *skipped as f64)
+    })
+}
+
+/// Rejects a `keep` of zero, rather than emptying the feed and deleting every media file
+fn check_keep(keep: Option<usize>) -> Result<(), Error> {
+    match keep {
+        Some(0) => Err(Error::InvalidKeepLimit(0)),
+        _ => Ok(()),
+    }
+}
+
+/// Reads the date from `source` for a video, falling back to the other sources in the default order,
+/// including when a date can't be parsed
+fn video_pub_date(video: &youtube_dl::SingleVideo, source: DateSource) -> Option<DateTime<Utc>> {
+    let parse_date = |date: &String| {
+        NaiveDateTime::parse_from_str(&format!("{}T00:00Z", date), "%Y%m%dT%H:%MZ")
+            .map(|date| Utc.from_utc_datetime(&date))
+            .map_err(|error| warn!("Couldn't parse date {:?} of {}: {}", date, video.id, error))
+            .ok()
+    };
+
+    let read = |source| match source {
+        DateSource::Timestamp => video
+            .timestamp
+            .and_then(|timestamp| DateTime::from_timestamp(timestamp as i64, 0)),
+        DateSource::Upload => video.upload_date.as_ref().and_then(parse_date),
+        DateSource::Release => video.release_date.as_ref().and_then(parse_date),
+    };
+
+    read(source).or_else(|| {
+        [
+            DateSource::Timestamp,
+            DateSource::Upload,
+            DateSource::Release,
+        ]
+        .into_iter()
+        .filter(|fallback| *fallback != source)
+        .find_map(read)
+    })
+}
+
+/// Writes `itunes:explicit` as Apple now expects, accepting the older `yes`/`no`/`clean` values
+fn apple_explicit(explicit: &str) -> String {
+    match explicit.to_lowercase().as_str() {
+        "yes" | "explicit" | "true" => "true".to_string(),
+        _ => "false".to_string(),
+    }
+}
+
+/// Adds or omits the elements `profile` calls for; see `FeedProfile`
+fn profile_feed(rss_channel: &RSSChannel, profile: FeedProfile) -> RSSChannel {
+    let mut rss_channel = rss_channel.clone();
+
+    match profile {
+        FeedProfile::Apple => {
+            if let Some(ref mut itunes_ext) = rss_channel.itunes_ext {
+                itunes_ext.explicit = itunes_ext.explicit.as_deref().map(apple_explicit);
+            }
+
+            for item in rss_channel.items.iter_mut() {
+                if let Some(ref mut itunes_ext) = item.itunes_ext {
+                    itunes_ext.explicit = itunes_ext.explicit.as_deref().map(apple_explicit);
+                }
+            }
+        }
+        FeedProfile::Google => {
+            if rss_channel.image.is_none() {
+                if let Some(image) = rss_channel.itunes_ext().and_then(|ext| ext.image()) {
+                    rss_channel.image = Some(
+                        RSSImageBuilder::default()
+                            .url(image.to_string())
+                            .title(rss_channel.title.clone())
+                            .link(rss_channel.link.clone())
+                            .build(),
+                    );
+                }
+            }
+        }
+        FeedProfile::Strict20 => {
+            rss_channel.itunes_ext = None;
+            rss_channel.dublin_core_ext = None;
+            rss_channel.syndication_ext = None;
+            rss_channel.extensions.clear();
+
+            for item in rss_channel.items.iter_mut() {
+                item.itunes_ext = None;
+                item.dublin_core_ext = None;
+                item.content = None;
+                item.extensions.clear();
+            }
+
+            rss_channel.namespaces.clear();
+        }
+    }
+
+    rss_channel
+}
+
+/// Checks the feed has everything `profile` requires, describing the first thing missing
+fn check_profile(rss_channel: &RSSChannel, profile: FeedProfile) -> Result<(), String> {
+    let require = |present: bool, element: &str| match present {
+        true => Ok(()),
+        false => Err(format!("missing {}", element)),
+    };
+
+    require(!rss_channel.title.is_empty(), "<title>")?;
+    require(!rss_channel.description.is_empty(), "<description>")?;
+
+    match profile {
+        FeedProfile::Apple => {
+            let itunes_ext = rss_channel.itunes_ext().ok_or("missing iTunes elements")?;
+
+            require(rss_channel.language.is_some(), "<language>")?;
+            require(itunes_ext.image.is_some(), "<itunes:image>")?;
+            require(!itunes_ext.categories.is_empty(), "<itunes:category>")?;
+            require(itunes_ext.author.is_some(), "<itunes:author>")?;
+            require(itunes_ext.explicit.is_some(), "<itunes:explicit>")?;
+        }
+        FeedProfile::Google => {
+            require(!rss_channel.link.is_empty(), "<link>")?;
+            require(rss_channel.image.is_some(), "<image>")?;
+        }
+        FeedProfile::Strict20 => {
+            require(!rss_channel.link.is_empty(), "<link>")?;
+        }
+    }
+
+    // Items are named by their GUID, or by their position if they don't have one
+    for (index, item) in rss_channel.items.iter().enumerate() {
+        let name = item.guid().map_or_else(
+            || format!("item {}", index + 1),
+            |guid| format!("item {:?}", guid.value()),
+        );
+
+        match profile {
+            FeedProfile::Strict20 => require(
+                item.title.is_some() || item.description.is_some(),
+                &format!("<title> or <description> on {}", name),
+            )?,
+            FeedProfile::Apple | FeedProfile::Google => {
+                require(item.title.is_some(), &format!("<title> on {}", name))?;
+                require(
+                    item.enclosure.is_some(),
+                    &format!("<enclosure> on {}", name),
+                )?;
+                require(item.guid.is_some(), &format!("<guid> on {}", name))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds an item's `itunes:keywords` from its video's tags and categories
+fn video_keywords(video: &youtube_dl::SingleVideo) -> Option<String> {
+    let keywords = video
+        .tags
+        .iter()
+        .chain(&video.categories)
+        .flatten()
+        .flatten()
+        .map(|keyword| keyword.trim())
+        .filter(|keyword| !keyword.is_empty())
+        .unique_by(|keyword| keyword.to_lowercase())
+        .join(", ");
+
+    Some(keywords).filter(|keywords| !keywords.is_empty())
+}
+
+/// Builds the channel's `itunes:keywords` from the union of its items' keywords, newest first,
+/// dropping duplicates and any keywords past `CHANNEL_KEYWORDS_LIMIT` characters in total
+fn channel_keywords(items: &[RSSItem]) -> Option<String> {
+    let mut keywords = String::new();
+
+    let item_keywords = items
+        .iter()
+        .filter_map(|item| item.itunes_ext()?.keywords())
+        .flat_map(|keywords| keywords.split(','))
+        .map(|keyword| keyword.trim())
+        .filter(|keyword| !keyword.is_empty())
+        .unique_by(|keyword| keyword.to_lowercase());
+
+    for keyword in item_keywords {
+        let separator = if keywords.is_empty() { "" } else { ", " };
+
+        if keywords.chars().count() + separator.len() + keyword.chars().count()
+            > CHANNEL_KEYWORDS_LIMIT
+        {
+            break;
+        }
+
+        keywords.push_str(separator);
+        keywords.push_str(keyword);
+    }
+
+    Some(keywords).filter(|keywords| !keywords.is_empty())
+}
+
+/// Reads a video's (one-based) `playlist_index`, which `yt-dlp` may give as a number or a string,
+/// or leave out entirely
+fn video_playlist_index(video: &youtube_dl::SingleVideo) -> Option<u64> {
+    match video.playlist_index.as_ref()? {
+        serde_json::Value::Number(index) => index.as_u64().or_else(|| {
+            index
+                .as_f64()
+                .filter(|index| index.fract() == 0.0)
+                .map(|index| index as u64)
+        }),
+        serde_json::Value::String(index) => index.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Reads a video's episode number from its `playlist_index`
+fn video_episode_number(video: &youtube_dl::SingleVideo) -> Option<u64> {
+    // Apps treat an episode zero oddly, so leave it unnumbered instead
+    video_playlist_index(video).filter(|index| *index > 0)
+}
+
+/// Reads a video's duration from its metadata, treating a missing or invalid duration as zero
+fn video_duration(video: &youtube_dl::SingleVideo) -> Duration {
+    match &video.duration {
+        Some(value) => {
+            let secs = match value {
+                serde_json::Value::Number(secs) => secs.as_f64().unwrap_or(0.0),
+                _ => 0.0,
+            };
+            Duration::new(secs as u64, 0)
+        }
+        None => Duration::default(),
+    }
+}
+
+/// Checks the given categories against Apple's taxonomy, warning about (or, if `strict`, failing on) unknown ones
+fn check_categories(categories: &[Category], strict: bool) -> Result<(), Error> {
+    for category in categories.iter().filter(|category| !category.is_valid()) {
+        if strict {
+            return Err(Error::CategoryError(category.clone()));
+        }
+
+        warn!("\"{}\" is not an Apple Podcasts category", category);
+    }
+
+    Ok(())
+}
+
+/// Checks every item's enclosure URL is absolute, with a scheme and host, as podcast apps can't
+/// resolve relative ones. Each one which isn't is an error when being strict, or a warning otherwise.
+fn check_enclosure_urls(rss_channel: &RSSChannel, strict: bool) -> Result<(), Error> {
+    for item in &rss_channel.items {
+        let enclosure = match item.enclosure() {
+            Some(enclosure) => enclosure,
+            None => continue,
+        };
+
+        if Url::parse(enclosure.url()).is_ok_and(|url| url.has_host()) {
+            continue;
+        }
+
+        let label = item_id(item)
+            .or_else(|| item.title().map(|title| title.to_string()))
+            .unwrap_or_default();
+
+        if strict {
+            return Err(Error::RelativeEnclosureError(
+                label,
+                enclosure.url().to_string(),
+            ));
+        }
+
+        warn!(
+            "The enclosure URL of {:?} isn't absolute, so podcast apps can't download it: {:?}",
+            label,
+            enclosure.url()
+        );
+    }
+
+    Ok(())
+}
+
+/// Strips a feed down to its required elements, enclosures and artwork, for bandwidth-constrained clients.
+///
+/// Item descriptions are truncated, and the generator, docs, keywords, iTunes summaries and
+/// subtitles, `content:encoded` and Dublin Core elements are all omitted.
+fn minimal_feed(rss_channel: &RSSChannel) -> RSSChannel {
+    let mut rss_channel = rss_channel.clone();
+
+    rss_channel.generator = None;
+    rss_channel.docs = None;
+
+    if let Some(ref mut itunes_ext) = rss_channel.itunes_ext {
+        itunes_ext.keywords = None;
+        itunes_ext.summary = None;
+        itunes_ext.subtitle = None;
+    }
+
+    for item in rss_channel.items.iter_mut() {
+        item.content = None;
+        item.comments = None;
+        item.dublin_core_ext = None;
+
+        if let Some(description) = item.description.take() {
+            item.description = Some(truncate_chars(&description, MINIMAL_DESCRIPTION_LIMIT));
+        }
+
+        if let Some(ref mut itunes_ext) = item.itunes_ext {
+            itunes_ext.keywords = None;
+            itunes_ext.summary = None;
+            itunes_ext.subtitle = None;
+        }
+    }
+
+    rss_channel.dublin_core_ext = None;
+
+    rss_channel
+}
+
+/// Appends `disambiguator`'s suffix to the titles of items which share a title.
+///
+/// Titles already ending in their own suffix (i.e. from a previous update) are compared without
+/// it, and aren't suffixed again, so this is stable across updates. Once a title's no longer
+/// shared, i.e. as the other items left the feed, its suffix is removed again.
+fn disambiguate_titles(items: &mut [RSSItem], disambiguator: TitleDisambiguator) {
+    let titles: Vec<_> = items
+        .iter()
+        .map(|item| {
+            let suffix = disambiguator.suffix(item);
+            let title = item.title().unwrap_or_default();
+
+            let base_title = suffix
+                .as_ref()
+                .and_then(|suffix| title.strip_suffix(suffix.as_str()))
+                .unwrap_or(title)
+                .to_string();
+
+            (base_title, suffix)
+        })
+        .collect();
+
+    let title_counts = titles
+        .iter()
+        .counts_by(|(base_title, _)| base_title.clone());
+
+    for (item, (base_title, suffix)) in items.iter_mut().zip(titles) {
+        if item.title.is_none() {
+            continue;
+        }
+
+        match (suffix, title_counts[&base_title] < 2) {
+            (_, true) => item.set_title(base_title),
+            (Some(suffix), false) => item.set_title(format!("{}{}", base_title, suffix)),
+            (None, false) => {}
+        }
+    }
+}
+
+/// Builds the `itunes:category` element for a category
+fn itunes_category(category: &Category) -> ITunesCategory {
+    ITunesCategoryBuilder::default()
+        .text(category.name.clone())
+        .subcategory(category.subcategory.as_ref().map(|subcategory| {
+            Box::new(
+                ITunesCategoryBuilder::default()
+                    .text(subcategory.clone())
+                    .build(),
+            )
+        }))
+        .build()
+}
+
+/// Picks the thumbnail URL to use as a video's item image, according to `preference`
+fn item_thumbnail(
+    video: &youtube_dl::SingleVideo,
+    preference: Option<ThumbnailPreference>,
+) -> Option<String> {
+    let thumbnails = video
+        .thumbnails
+        .iter()
+        .flatten()
+        .filter(|thumbnail| thumbnail.url.is_some())
+        .collect_vec();
+
+    let area = |thumbnail: &&youtube_dl::Thumbnail| {
+        (thumbnail.width.unwrap_or(0.0) * thumbnail.height.unwrap_or(0.0)) as u64
+    };
+
+    let preferred = match preference {
+        None => None,
+        Some(ThumbnailPreference::Maxres) => thumbnails.into_iter().max_by_key(area),
+        Some(ThumbnailPreference::Square) => thumbnails
+            .into_iter()
+            .filter(|thumbnail| thumbnail.width.is_some() && thumbnail.width == thumbnail.height)
+            .max_by_key(area),
+        Some(ThumbnailPreference::First) => thumbnails.first().copied(),
+        Some(ThumbnailPreference::Index(index)) => thumbnails.get(index).copied(),
+    };
+
+    preferred
+        .and_then(|thumbnail| thumbnail.url.clone())
+        .or_else(|| video.thumbnail.clone())
+}
+
+/// Drops entries which repeat an earlier entry's id, as when a video was added to a playlist
+/// twice, so each video is only processed once
+fn dedup_entries(playlist: &mut youtube_dl::Playlist) {
+    let mut seen_ids = HashSet::new();
+
+    if let Some(ref mut entries) = playlist.entries {
+        entries.retain(|video| {
+            let first = seen_ids.insert(video.id.clone());
+
+            if !first {
+                debug!("Skipping repeat of {:?} in the playlist", video.id);
+            }
+
+            first
+        });
+    }
+}
+
+/// Whether an entry looks like it has no media: it has no duration in its metadata, and there's
+/// no non-empty file on disk for it either (some sources, like audio, don't report a duration)
+fn is_empty_entry(video: &youtube_dl::SingleVideo, item_path: &Path) -> bool {
+    video_duration(video).is_zero()
+        && !std::fs::metadata(item_path).is_ok_and(|metadata| metadata.len() > 0)
+}
+
+/// Whether yt-dlp fetched a video as HLS or DASH segments, which it merges into one file on disk
+fn is_segmented(video: &youtube_dl::SingleVideo) -> bool {
+    use youtube_dl::model::Protocol;
+
+    matches!(
+        video.protocol,
+        Some(
+            Protocol::M3U8
+                | Protocol::M3U8Native
+                | Protocol::HttpDashSegments
+                | Protocol::HttpDashSegmentsHttps
+                | Protocol::HttpDashSegmentsHttpDashSegments
+                | Protocol::M3U8NativeM3U8Native
+                | Protocol::M3U8NativeHttps
+        )
+    )
+}
+
+/// The byte count for a video's enclosure `length`.
+///
+/// Uses yt-dlp's exact `filesize` if known, then its `filesize_approx` estimate, then the size of
+/// the media file on disk, falling back to 0 if none of those give a valid non-negative size.
+/// Segmented (HLS/DASH) downloads use the size on disk first, as their metadata sizes are missing
+/// or only describe one of the merged streams.
+fn enclosure_length(video: &youtube_dl::SingleVideo, item_path: &Path) -> u64 {
+    if is_segmented(video) {
+        if let Ok(metadata) = std::fs::metadata(item_path) {
+            return metadata.len();
+        }
+    }
+
+    if let Some(filesize) = video.filesize.and_then(|size| u64::try_from(size).ok()) {
+        return filesize;
+    }
+
+    if let Some(filesize_approx) = video
+        .filesize_approx
+        .filter(|size| size.is_finite() && *size >= 0.0)
+    {
+        // Float to integer casts saturate, so an enormous estimate becomes `u64::MAX`
+        return filesize_approx as u64;
+    }
+
+    std::fs::metadata(item_path)
+        .map(|metadata| metadata.len())
+        .unwrap_or(0)
+}
+
+/// The `--print-to-file` template `yt-dlp` lists each media file it downloads with, after its
+/// video's id and when it started on the video, for reading back with `read_downloaded_files`
+const DOWNLOADED_FILE_TEMPLATE: &str = "after_move:%(id)s\t%(epoch)s\t%(filepath)s";
+
+/// A media file `yt-dlp` listed with `DOWNLOADED_FILE_TEMPLATE`
+struct DownloadedFile {
+    /// Where the file was downloaded to
+    path: PathBuf,
+
+    /// When `yt-dlp` started on the file's video, as a Unix timestamp in whole seconds
+    started: Option<u64>,
+}
+
+/// Reads the media files `yt-dlp` listed with `DOWNLOADED_FILE_TEMPLATE`, keyed by video id
+fn read_downloaded_files(path: &Path) -> Result<HashMap<String, DownloadedFile>, Error> {
+    let reader = match File::open(path) {
+        Ok(file) => BufReader::new(file),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(error) => return Err(error.into()),
+    };
+
+    let mut downloaded_files = HashMap::new();
+
+    for line in reader.lines() {
+        let line = line?;
+        let mut fields = line.splitn(3, '\t');
+
+        if let (Some(id), Some(started), Some(path)) = (fields.next(), fields.next(), fields.next())
+        {
+            downloaded_files.insert(
+                id.to_string(),
+                DownloadedFile {
+                    path: PathBuf::from(path),
+                    // `yt-dlp` gives `NA` for fields it doesn't know
+                    started: started.parse().ok(),
+                },
+            );
+        }
+    }
+
+    Ok(downloaded_files)
+}
+
+/// Roughly how long `yt-dlp` spent on each of `downloaded_files`, in whole seconds, keyed by video
+/// id, from when it started on each one until it started on the next, or `finished` the run
+fn download_durations(
+    downloaded_files: &HashMap<String, DownloadedFile>,
+    finished: u64,
+) -> HashMap<&str, u64> {
+    let starts = downloaded_files
+        .iter()
+        .filter_map(|(id, file)| Some((file.started?, id.as_str())))
+        .sorted()
+        .collect_vec();
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(index, (started, id))| {
+            let next = starts.get(index + 1).map_or(finished, |(next, _)| *next);
+            (*id, next.saturating_sub(*started))
+        })
+        .collect()
+}
+
+/// The source's URL for a video's media, as used by remote enclosures, with the video's id as its
+/// fragment so the item can be matched up with its video later
+fn remote_enclosure_url(video: &youtube_dl::SingleVideo) -> Result<Url, Error> {
+    let mut url = video
+        .url
+        .as_deref()
+        .ok_or_else(|| Error::RemoteEnclosureError(video.id.clone()))
+        .and_then(|url| Ok(Url::parse(url)?))?;
+
+    url.set_fragment(Some(&video.id));
+
+    Ok(url)
+}
+
+/// Reads a video's title from the given field, falling back to its `title`
+fn video_title(video: &youtube_dl::SingleVideo, source: TitleSource) -> Option<String> {
+    match source {
+        TitleSource::Title => None,
+        TitleSource::Track => video.track.clone(),
+        TitleSource::AltTitle => video.alt_title.clone(),
+    }
+    .or_else(|| video.title.clone())
+}
+
+/// The author of the item for `video`, or of the channel when there's no video, following the
+/// fallbacks described for `UpdateOptions::author`
+fn author(
+    options: &UpdateOptions,
+    playlist: &youtube_dl::Playlist,
+    video: Option<&youtube_dl::SingleVideo>,
+    title: &str,
+) -> String {
+    options
+        .author
+        .clone()
+        .or_else(|| video.and_then(|video| video.uploader.clone()))
+        .or_else(|| video.and_then(|video| video.channel.clone()))
+        .or_else(|| playlist.uploader.clone())
+        .unwrap_or_else(|| title.to_string())
+}
+
+/// Wraps a single video in a synthetic one-entry playlist, named after its uploader
+fn playlist_from_single_video(video: youtube_dl::SingleVideo) -> youtube_dl::Playlist {
+    youtube_dl::Playlist {
+        title: video
+            .uploader
+            .clone()
+            .or_else(|| video.channel.clone())
+            .or_else(|| video.title.clone()),
+        uploader: video.uploader.clone(),
+        uploader_id: video.uploader_id.clone(),
+        uploader_url: video.uploader_url.clone(),
+        webpage_url: video.webpage_url.clone(),
+        id: Some(video.id.clone()),
+        entries: Some(vec![video]),
+        ..Default::default()
+    }
+}
+
+/// Removes namespace declarations (i.e. ones carried over from an existing feed) which nothing uses any more.
+///
+/// The `rss` crate declares the namespaces for the extensions it knows about (iTunes, Dublin Core
+/// and `content`) whenever they're in use, so only namespaces used by custom extension elements,
+/// or the syndication extension, need to be kept.
+fn prune_namespaces(rss_channel: &mut RSSChannel) {
+    let mut used_prefixes: HashSet<String> = rss_channel.extensions.keys().cloned().collect();
+
+    for item in &rss_channel.items {
+        used_prefixes.extend(item.extensions.keys().cloned());
+    }
+
+    if rss_channel.syndication_ext.is_some() {
+        used_prefixes.insert("sy".to_string());
+    }
+
+    if rss_channel.dublin_core_ext.is_some()
+        || rss_channel
+            .items
+            .iter()
+            .any(|item| item.dublin_core_ext.is_some())
+    {
+        used_prefixes.insert("dc".to_string());
+    }
+
+    rss_channel
+        .namespaces
+        .retain(|prefix, _| used_prefixes.contains(prefix));
+}
+
+/// Adds custom elements to a channel's or item's extensions, replacing any existing elements with
+/// the same names rather than adding to them, so applying the same elements again changes nothing
+fn set_custom_elements(extensions: &mut ExtensionMap, elements: &[CustomElement]) {
+    for element in elements {
+        if let Some(named) = extensions.get_mut(&element.prefix) {
+            named.remove(&element.name);
+        }
+    }
+
+    for element in elements {
+        extensions
+            .entry(element.prefix.clone())
+            .or_default()
+            .entry(element.name.clone())
+            .or_default()
+            .push(element.to_extension());
+    }
+}
+
+/// Builds a `podcast:transcript` element pointing at a WebVTT subtitle file
+fn transcript_extension(url: &Url, language: &str) -> Extension {
+    ExtensionBuilder::default()
+        .name(format!("{}:transcript", PODCAST_PREFIX))
+        .attrs(BTreeMap::from([
+            ("url".to_string(), url.to_string()),
+            ("type".to_string(), "text/vtt".to_string()),
+            ("language".to_string(), language.to_string()),
+        ]))
+        .build()
+}
+
+/// Replaces the `playcaster:keep`, `playcaster:limit` and `playcaster:category` elements in
+/// `extensions` with those for `settings`
+fn set_feed_settings(extensions: &mut ExtensionMap, settings: &FeedSettings) {
+    let named = extensions.entry(PLAYCASTER_PREFIX.to_string()).or_default();
+
+    for name in ["keep", "limit", "category"] {
+        named.remove(name);
+    }
+
+    let values = settings
+        .keep
+        .map(|keep| ("keep", keep.to_string()))
+        .into_iter()
+        .chain(
+            settings
+                .download_limit
+                .map(|limit| ("limit", limit.to_string())),
+        )
+        .chain(
+            settings
+                .categories
+                .iter()
+                .map(|category| ("category", category.to_string())),
+        );
+
+    for (name, value) in values {
+        named.entry(name.to_string()).or_default().push(
+            ExtensionBuilder::default()
+                .name(format!("{}:{}", PLAYCASTER_PREFIX, name))
+                .value(value)
+                .build(),
+        );
+    }
+
+    if named.is_empty() {
+        extensions.remove(PLAYCASTER_PREFIX);
+    }
+}
+
+/// Builds a `playcaster:downloaded` element recording when the media file at `path` was
+/// downloaded, from its modification time, if it's on disk
+fn downloaded_extension(path: &Path) -> Option<Extension> {
+    let modified = std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+
+    Some(
+        ExtensionBuilder::default()
+            .name(format!("{}:downloaded", PLAYCASTER_PREFIX))
+            .value(DateTime::<Utc>::from(modified).to_rfc2822())
+            .build(),
+    )
+}
+
+/// The id of the video an item was built from.
+///
+/// This is read from the item's `playcaster:id` element if it has one, as it does when its media
+/// file isn't named after the id (see `UpdateOptions::restrict_filenames`). Otherwise, it's read
+/// from the enclosure's file name, as the GUID may not be the id (i.e. with `guid_from_enclosure`),
+/// falling back to the GUID for items without an enclosure.
+fn item_id(item: &RSSItem) -> Option<String> {
+    if let Some(id) = item
+        .extensions
+        .get(PLAYCASTER_PREFIX)
+        .and_then(|named| named.get("id"))
+        .and_then(|extensions| extensions.first())
+        .and_then(|extension| extension.value())
+    {
+        return Some(id.to_string());
+    }
+
+    item.enclosure()
+        .and_then(|enclosure| Url::parse(enclosure.url()).ok())
+        .and_then(|url| {
+            // Remote enclosures (see `UpdateOptions::remote_enclosures`) carry the id as their fragment
+            if let Some(fragment) = url.fragment() {
+                return Some(fragment.to_string());
+            }
+
+            url.path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .and_then(|file_name| Path::new(file_name).file_stem())
+                .map(|file_stem| file_stem.to_string_lossy().to_string())
+        })
+        .filter(|id| !id.is_empty())
+        .or_else(|| item.guid().map(|guid| guid.value().to_string()))
+}
+
+/// The name of an item's media file, minus its extension, which is read from its enclosure's file
+/// name. That's usually the item's id, but not when `restrict_filenames` had `yt-dlp` name it otherwise.
+fn item_file_stem(item: &RSSItem) -> Option<String> {
+    item.enclosure()
+        .and_then(|enclosure| Url::parse(enclosure.url()).ok())
+        // Remote enclosures aren't named after a file on disk
+        .filter(|url| url.fragment().is_none())
+        .and_then(|url| {
+            url.path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .and_then(|file_name| Path::new(file_name).file_stem())
+                .map(|file_stem| file_stem.to_string_lossy().to_string())
+        })
+        .filter(|file_stem| !file_stem.is_empty())
+        .or_else(|| item_id(item))
+}
+
+/// The key items are deduplicated by: their id if they have one, otherwise their title and link.
+///
+/// Items with none of those (i.e. added to the feed by hand) are never treated as duplicates.
+fn item_dedup_key(item: &RSSItem) -> Option<String> {
+    item_id(item).or_else(|| match (item.title(), item.link()) {
+        (None, None) => None,
+        (title, link) => Some(format!(
+            "{}\n{}",
+            title.unwrap_or_default(),
+            link.unwrap_or_default()
+        )),
+    })
+}
+
+/// Merges a fresh copy of an item into the existing one.
+///
+/// The enclosure and duration describe the media file as it is now, so they're always taken from
+/// the fresh item, e.g. once `probe_duration` has found a duration the metadata lacked. The title,
+/// description and images are kept from the existing item unless `prefer_fresh`, and the
+/// `pub_date` is kept either way. Transcripts are taken from the fresh item if the existing one has
+/// none, as their subtitles may only appear later.
+fn merge_item(mut existing: RSSItem, fresh: &RSSItem, prefer_fresh: bool) -> RSSItem {
+    existing.enclosure.clone_from(&fresh.enclosure);
+
+    if let (Some(existing_ext), Some(fresh_ext)) = (&mut existing.itunes_ext, &fresh.itunes_ext) {
+        existing_ext.duration.clone_from(&fresh_ext.duration);
+    }
+
+    if !existing.extensions.contains_key(PODCAST_PREFIX) {
+        if let Some(transcripts) = fresh.extensions.get(PODCAST_PREFIX) {
+            existing
+                .extensions
+                .insert(PODCAST_PREFIX.to_string(), transcripts.clone());
+        }
+    }
+
+    if !prefer_fresh {
+        return existing;
+    }
+
+    existing.title.clone_from(&fresh.title);
+    existing.description.clone_from(&fresh.description);
+
+    if let (Some(existing_ext), Some(fresh_ext)) = (&mut existing.itunes_ext, &fresh.itunes_ext) {
+        existing_ext.subtitle.clone_from(&fresh_ext.subtitle);
+        existing_ext.summary.clone_from(&fresh_ext.summary);
+        existing_ext.image.clone_from(&fresh_ext.image);
+    }
+
+    existing
+}
+
+/// Compares two sort keys in `direction`, with missing keys last either way
+fn compare_keys<T: Ord>(
+    a: Option<T>,
+    b: Option<T>,
+    direction: OrderDirection,
+) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+
+    match (a, b) {
+        (Some(a), Some(b)) => match direction {
+            OrderDirection::Asc => a.cmp(&b),
+            OrderDirection::Desc => b.cmp(&a),
+        },
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Sorts items by a metadata field, reading views and playlist positions from `entries`. The sort
+/// is stable, so items with equal (or missing) values keep their order.
+fn order_items(
+    items: &mut [RSSItem],
+    order_by: OrderBy,
+    direction: OrderDirection,
+    entries: &[youtube_dl::SingleVideo],
+) {
+    let entries: HashMap<_, _> = entries
+        .iter()
+        .enumerate()
+        .map(|(index, video)| (video.id.as_str(), (index, video)))
+        .collect();
+
+    let entry = |item: &RSSItem| item_id(item).and_then(|id| entries.get(id.as_str()).copied());
+
+    let title = |item: &RSSItem| item.title().map(str::to_lowercase);
+    let duration = |item: &RSSItem| {
+        item.itunes_ext()
+            .and_then(|ext| ext.duration())
+            .and_then(parse_itunes_duration)
+    };
+    let views = |item: &RSSItem| entry(item).and_then(|(_, video)| video.view_count);
+    let index = |item: &RSSItem| entry(item).map(|(index, _)| index);
+
+    match order_by {
+        OrderBy::Date => {
+            items.sort_by(|a, b| compare_keys(item_pub_date(a), item_pub_date(b), direction))
+        }
+        OrderBy::Title => items.sort_by(|a, b| compare_keys(title(a), title(b), direction)),
+        OrderBy::Duration => {
+            items.sort_by(|a, b| compare_keys(duration(a), duration(b), direction))
+        }
+        OrderBy::Views => items.sort_by(|a, b| compare_keys(views(a), views(b), direction)),
+        OrderBy::Index => items.sort_by(|a, b| compare_keys(index(a), index(b), direction)),
+    }
+}
+
+/// Sorts items as `options` order the feed, by its `order_by` field or else its `order`, reading
+/// views and playlist positions from `entries`
+fn sort_items(items: &mut [RSSItem], options: &UpdateOptions, entries: &[youtube_dl::SingleVideo]) {
+    if let Some(order_by) = options.order_by {
+        let direction = options
+            .order_dir
+            .unwrap_or_else(|| order_by.default_direction());
+
+        order_items(items, order_by, direction, entries);
+    } else if options.order == SourceOrder::Date {
+        // Stable sort, so items published at the same time keep their playlist order
+        items.sort_by_key(|item| std::cmp::Reverse(item_pub_date(item)));
+    }
+}
+
+/// Parses an `itunes:duration` (`HH:MM:SS`, `MM:SS` or plain seconds) into seconds
+fn parse_itunes_duration(duration: &str) -> Option<u64> {
+    duration.split(':').try_fold(0, |total, part| {
+        part.trim()
+            .parse::<u64>()
+            .ok()
+            .map(|value| total * 60 + value)
+    })
+}
+
+/// Parses an item's `pub_date`, if it has a valid one
+fn item_pub_date(item: &RSSItem) -> Option<DateTime<FixedOffset>> {
+    item.pub_date()
+        .and_then(|pub_date| DateTime::parse_from_rfc2822(pub_date).ok())
+}
+
+/// Truncates `text` to at most `limit` characters, respecting character boundaries
+fn truncate_chars(text: &str, limit: usize) -> String {
+    match text.char_indices().nth(limit) {
+        Some((index, _)) => text[..index].to_string(),
+        None => text.to_string(),
+    }
+}
+
+/// Checks that an `itunes:image` URL is served over HTTPS, as Apple requires.
+///
+/// Plain `http` URLs are upgraded to `https` if `upgrade` is set, and otherwise left as-is with a warning.
+fn https_image(image: String, upgrade: bool) -> String {
+    match Url::parse(&image) {
+        Ok(mut url) if url.scheme() == "http" => {
+            if upgrade && url.set_scheme("https").is_ok() {
+                debug!("Upgraded image URL to https: {}", url);
+                url.to_string()
+            } else {
+                warn!(
+                    "Image URL is not served over https, and may be rejected by Apple Podcasts: {}",
+                    image
+                );
+                image
+            }
+        }
+        _ => image,
+    }
+}
+
+/// Represents a given RSS channel, which points at a video feed.
+pub struct Channel {
+    /// Path to the input RSS feed
+    pub feed_file: PathBuf,
+
+    /// URL to the playlist with videos to be downloaded
+    pub playlist_url: Url,
+
+    /// The RSS feed
+    pub rss_channel: Option<RSSChannel>,
+
+    /// Directory to store media files in, rather than one named after the feed file next to it
+    pub media_dir: Option<PathBuf>,
+
+    /// Path under the base URL media files are served from, rather than the media directory's name
+    pub url_path_prefix: Option<String>,
+
+    /// The playlist-level metadata from the latest update, for `WriteOptions::channel_info`
+    pub channel_info: Option<serde_json::Value>,
+}
+
+impl Channel {
+    pub fn new_with_reader_and_url<T: BufRead>(
+        feed_file: PathBuf,
+        playlist_url: Url,
+        mut reader: T,
+    ) -> Result<Self, Error> {
+        if feed_file.extension().is_none() {
+            Err(Error::FileExtensionError(feed_file))
+        } else {
+            // Don't pull the URL out of the RSS channel
+
+            // An empty file (i.e. one created with `touch`) is a new feed
+            let rss_channel = if reader.fill_buf()?.is_empty() {
+                None
+            } else {
+                RSSChannel::read_from(reader).ok()
+            };
+
+            Ok(Self {
+                feed_file,
+                playlist_url,
+                rss_channel,
+                media_dir: None,
+                url_path_prefix: None,
+                channel_info: None,
+            })
+        }
+    }
+
+    pub fn new_with_reader<T: BufRead>(feed_file: PathBuf, mut reader: T) -> Result<Self, Error> {
+        if feed_file.extension().is_none() {
+            Err(Error::FileExtensionError(feed_file))
+        } else if reader.fill_buf()?.is_empty() {
+            Err(Error::EmptyFeedError(feed_file))
+        } else {
+            let rss_channel = RSSChannel::read_from(reader)?;
+
+            let playlist_url = Url::parse(rss_channel.link())?;
+
+            Ok(Self {
+                feed_file,
+                playlist_url,
+                rss_channel: Some(rss_channel),
+                media_dir: None,
+                url_path_prefix: None,
+                channel_info: None,
+            })
+        }
+    }
+
+    pub fn new_with_url(feed_file: PathBuf, playlist_url: Url) -> Result<Self, Error> {
+        if feed_file.extension().is_none() {
+            Err(Error::FileExtensionError(feed_file))
+        } else if let Ok(file) = File::open(feed_file.clone()) {
+            let reader = BufReader::new(file);
+            Self::new_with_reader_and_url(feed_file, playlist_url, reader)
+        } else {
+            Ok(Self {
+                feed_file,
+                playlist_url,
+                rss_channel: None,
+                media_dir: None,
+                url_path_prefix: None,
+                channel_info: None,
+            })
+        }
+    }
+
+    pub fn new(feed_file: PathBuf) -> Result<Self, Error> {
+        if feed_file.extension().is_none() {
+            Err(Error::FileExtensionError(feed_file))
+        } else {
+            let file = File::open(feed_file.clone())?;
+            let reader = BufReader::new(file);
+            Self::new_with_reader(feed_file, reader)
+        }
+    }
+
+    /// Validates the feed as it stands, without any write options applied.
+    pub fn validate(&self) -> Result<(), Error> {
+        use rss::validation::Validate;
+
+        self.rss_channel
+            .as_ref()
+            .ok_or_else(|| Error::NoFeedError(self.feed_file.clone()))?
+            .validate()
+            .map_err(|error| Error::FeedValidationError(self.feed_file.clone(), error))
+    }
+
+    /// The RSS feed as it's written with `options`, failing if it doesn't validate (including
+    /// against the profile, if one was given)
+    fn output_channel(&self, options: &WriteOptions) -> Result<Option<Cow<'_, RSSChannel>>, Error> {
+        use rss::validation::Validate;
+
+        let mut rss_channel = match self.rss_channel {
+            Some(ref rss_channel) if options.minimal => Cow::Owned(minimal_feed(rss_channel)),
+            Some(ref rss_channel) => Cow::Borrowed(rss_channel),
+            None => return Ok(None),
+        };
+
+        if let Some(profile) = options.profile {
+            rss_channel = Cow::Owned(profile_feed(&rss_channel, profile));
+        }
+
+        rss_channel
+            .validate()
+            .map_err(|error| Error::FeedValidationError(self.feed_file.clone(), error))?;
+
+        if let Some(profile) = options.profile {
+            check_profile(&rss_channel, profile)
+                .map_err(|problem| Error::ProfileError(self.feed_file.clone(), profile, problem))?;
+        }
+
+        Ok(Some(rss_channel))
+    }
+
+    /// Writes the feed to `writer`, with an XML declaration naming the configured encoding.
+    ///
+    /// Characters which can't be represented in a non-UTF-8 encoding are written as numeric
+    /// character references, which aren't interpreted inside CDATA sections like descriptions.
+    pub fn write_to<W: Write>(&self, writer: W, options: &WriteOptions) -> Result<(), Error> {
+        match self.output_channel(options)? {
+            Some(rss_channel) => self.write_channel_to(writer, &rss_channel, options),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes `rss_channel`, as prepared by `output_channel`, to `writer`; see `write_to`
+    fn write_channel_to<W: Write>(
+        &self,
+        mut writer: W,
+        rss_channel: &RSSChannel,
+        options: &WriteOptions,
+    ) -> Result<(), Error> {
+        let encoding = encoding_rs::Encoding::for_label(options.encoding.as_bytes())
+            .filter(|encoding| encoding.output_encoding() == *encoding)
+            .ok_or_else(|| Error::EncodingError(options.encoding.clone()))?;
+
+        let mut buffer = vec![];
+
+        if options.pretty {
+            rss_channel.pretty_write_to(&mut buffer, b' ', 2)?;
+        } else {
+            rss_channel.write_to(&mut buffer)?;
+        }
+
+        let xml = String::from_utf8_lossy(&buffer);
+
+        // The `rss` crate always declares lowercase `utf-8`, so replace its declaration with our own
+        let body = match xml.strip_prefix("<?xml") {
+            Some(rest) => rest.split_once("?>").map_or(rest, |(_, body)| body),
+            None => &xml,
+        };
+
+        let xml = format!(
+            "<?xml version=\"1.0\" encoding=\"{}\"?>{}",
+            encoding.name(),
+            body
+        );
+
+        let (bytes, _, had_unmappable) = encoding.encode(&xml);
+
+        if had_unmappable {
+            warn!(
+                "Some characters in {:?} can't be represented in {}",
+                self.feed_file,
+                encoding.name()
+            );
+        }
+
+        writer.write_all(&bytes)?;
+
+        Ok(())
+    }
+
+    /// Builds a JSON Feed (version 1.1) with the same contents as the RSS feed
+    pub fn to_json_feed(&self) -> Option<serde_json::Value> {
+        use serde_json::{json, Map, Value};
+
+        let rss_channel = self.rss_channel.as_ref()?;
+
+        let items = rss_channel
+            .items
+            .iter()
+            .map(|item| {
+                let mut json_item = Map::new();
+
+                json_item.insert(
+                    "id".to_string(),
+                    json!(item.guid().map(|guid| guid.value())),
+                );
+                json_item.insert("url".to_string(), json!(item.link()));
+                json_item.insert("title".to_string(), json!(item.title()));
+                json_item.insert(
+                    "content_text".to_string(),
+                    json!(item.description().unwrap_or_default()),
+                );
+
+                if let Some(pub_date) = item_pub_date(item) {
+                    json_item.insert("date_published".to_string(), json!(pub_date.to_rfc3339()));
+                }
+
+                if let Some(itunes_ext) = item.itunes_ext() {
+                    json_item.insert("image".to_string(), json!(itunes_ext.image()));
+
+                    if let Some(author) = itunes_ext.author() {
+                        json_item.insert("authors".to_string(), json!([{ "name": author }]));
+                    }
+                }
+
+                if let Some(enclosure) = item.enclosure() {
+                    let mut attachment = Map::new();
+
+                    attachment.insert("url".to_string(), json!(enclosure.url()));
+                    attachment.insert("mime_type".to_string(), json!(enclosure.mime_type()));
+
+                    if let Ok(length) = enclosure.length().parse::<u64>() {
+                        attachment.insert("size_in_bytes".to_string(), json!(length));
+                    }
+
+                    if let Some(duration) = item
+                        .itunes_ext()
+                        .and_then(|itunes_ext| itunes_ext.duration())
+                        .and_then(parse_itunes_duration)
+                    {
+                        attachment.insert("duration_in_seconds".to_string(), json!(duration));
+                    }
+
+                    json_item.insert("attachments".to_string(), json!([attachment]));
+                }
+
+                json_item.retain(|_, value| !value.is_null());
+
+                Value::Object(json_item)
+            })
+            .collect_vec();
+
+        let mut feed = Map::new();
+
+        feed.insert(
+            "version".to_string(),
+            json!("https://jsonfeed.org/version/1.1"),
+        );
+        feed.insert("title".to_string(), json!(rss_channel.title()));
+        feed.insert("home_page_url".to_string(), json!(rss_channel.link()));
+        feed.insert("description".to_string(), json!(rss_channel.description()));
+        feed.insert(
+            "icon".to_string(),
+            json!(rss_channel
+                .itunes_ext()
+                .and_then(|itunes_ext| itunes_ext.image())),
+        );
+        feed.insert("language".to_string(), json!(rss_channel.language()));
+        feed.insert("items".to_string(), Value::Array(items));
+
+        feed.retain(|_, value| !value.is_null());
+
+        Some(Value::Object(feed))
+    }
+
+    /// The file the feed is written to in the given format
+    pub fn output_file(&self, format: OutputFormat) -> PathBuf {
+        match format {
+            OutputFormat::Rss => self.feed_file.clone(),
+            OutputFormat::Jsonfeed => self.feed_file.with_extension("json"),
+        }
+    }
+
+    /// The formats in `options` whose output file has an extension other than the format's usual
+    /// ones, such as an RSS feed written to a `.json` feed file. Only the RSS feed can be
+    /// mismatched, as the JSON Feed's output file is always given a `json` extension.
+    pub fn mismatched_formats(&self, options: &WriteOptions) -> Vec<OutputFormat> {
+        let mismatched = self
+            .feed_file
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .is_some_and(|extension| !["xml", "rss"].contains(&extension.as_str()));
+
+        match mismatched && options.formats.contains(&OutputFormat::Rss) {
+            true => vec![OutputFormat::Rss],
+            false => vec![],
+        }
+    }
+
+    /// Writes the feed to its output file for each format, replacing any existing contents.
+    /// The content only depends on the format, whatever the file's extension, but a warning is
+    /// logged for any mismatch; see `mismatched_formats`. A `json` feed file can't be written
+    /// alongside its JSON Feed, though, as they'd be the same file.
+    ///
+    /// Each file is written to a temporary file alongside it, then moved into place, so an
+    /// interrupted write never leaves a partially written feed behind. The feed is validated before
+    /// anything's written, so an invalid update leaves every existing file untouched.
+    pub fn write(&self, options: &WriteOptions) -> Result<(), Error> {
+        let _log_context = LogContext::enter(&self.feed_file);
+
+        let both_formats = options.formats.contains(&OutputFormat::Rss)
+            && options.formats.contains(&OutputFormat::Jsonfeed);
+        if both_formats
+            && self
+                .feed_file
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+        {
+            return Err(Error::OutputFileCollisionError(self.feed_file.clone()));
+        }
+
+        // Prepared and validated once, before any file is touched
+        let rss_channel = self.output_channel(options)?;
+
+        for format in self.mismatched_formats(options) {
+            warn!(
+                "Writing the {} feed to {:?}, whose extension doesn't match the format",
+                format,
+                self.output_file(format)
+            );
+        }
+
+        for format in options.formats.iter().unique() {
+            let output_file = match options.preview {
+                true => preview_file(&self.output_file(*format)),
+                false => self.output_file(*format),
+            };
+
+            write_atomically(&output_file, |file| {
+                match format {
+                    OutputFormat::Rss => {
+                        if let Some(ref rss_channel) = rss_channel {
+                            self.write_channel_to(file, rss_channel, options)?;
+                        }
+                    }
+                    OutputFormat::Jsonfeed => {
+                        let json_feed = self.to_json_feed().unwrap_or_default();
+
+                        if options.pretty {
+                            serde_json::to_writer_pretty(file, &json_feed)
+                        } else {
+                            serde_json::to_writer(file, &json_feed)
+                        }
+                        .map_err(std::io::Error::from)?;
+                    }
+                }
+
+                Ok(())
+            })?;
+        }
+
+        if let Some(ref channel_info) = self.channel_info {
+            if options.channel_info && !options.preview {
+                write_atomically(&self.channel_info_file(), |file| {
+                    Ok(serde_json::to_writer_pretty(file, channel_info)
+                        .map_err(std::io::Error::from)?)
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The namespace URI for a custom element's prefix: the last one `options` declares,
+    /// then the existing feed's, then any namespace Playcaster uses itself
+    fn element_namespace(&self, options: &UpdateOptions, prefix: &str) -> Option<String> {
+        options
+            .namespaces
+            .iter()
+            .rev()
+            .find(|(declared_prefix, _)| declared_prefix == prefix)
+            .map(|(_, namespace)| namespace.clone())
+            .or_else(|| {
+                self.rss_channel
+                    .as_ref()
+                    .and_then(|rss_channel| rss_channel.namespaces.get(prefix).cloned())
+            })
+            .or_else(|| (prefix == PODCAST_PREFIX).then(|| PODCAST_NAMESPACE.to_string()))
+    }
+
+    /// Rejects custom channel and item elements whose prefix has no namespace, before anything's downloaded
+    fn check_custom_elements(&self, options: &UpdateOptions) -> Result<(), Error> {
+        let item_elements = options
+            .item_overrides
+            .values()
+            .flat_map(|item_override| &item_override.elements);
+
+        match options
+            .channel_elements
+            .iter()
+            .chain(item_elements)
+            .find(|element| self.element_namespace(options, &element.prefix).is_none())
+        {
+            Some(element) => Err(Error::UndeclaredNamespaceError(element.prefix.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// The directory this channel's media files are stored in, by default named after a slug of
+    /// the feed file's name, so it can be used in enclosure URLs as-is
+    fn media_dir(&self) -> Result<PathBuf, Error> {
+        if let Some(ref media_dir) = self.media_dir {
+            return Ok(media_dir.clone());
+        }
+
+        let parent = self
+            .feed_file
+            .parent()
+            .ok_or_else(|| Error::ParentPathError(self.feed_file.clone()))?;
+
+        let file_stem = self
+            .feed_file
+            .file_stem()
+            .ok_or_else(|| Error::FileStemError(self.feed_file.clone()))?;
+
+        let media_dir = parent.join(slugify(&file_stem.to_string_lossy()));
+        let legacy_media_dir = parent.join(file_stem);
+
+        // Keep using a directory created before names were slugged
+        if media_dir != legacy_media_dir && !media_dir.exists() && legacy_media_dir.is_dir() {
+            debug!("Using existing media directory: {:?}", legacy_media_dir);
+            return Ok(legacy_media_dir);
+        }
+
+        Ok(media_dir)
+    }
+
+    /// The name of the media file for `video`, minus its extension.
+    ///
+    /// This is the video's id, unless `restrict_filenames` had `yt-dlp` name the file otherwise.
+    /// Those names can't be derived from the id, as `yt-dlp` transliterates accented letters and
+    /// so on, so they're read from `downloaded_files` as `yt-dlp` listed them, or from the feed's
+    /// existing item for the video, falling back to the id for videos not downloaded yet.
+    fn media_file_stem(
+        &self,
+        video: &youtube_dl::SingleVideo,
+        options: &UpdateOptions,
+        downloaded_files: &HashMap<String, DownloadedFile>,
+    ) -> String {
+        if !options.restrict_filenames {
+            return video.id.clone();
+        }
+
+        if let Some(file_stem) = downloaded_files
+            .get(&video.id)
+            .and_then(|file| file.path.file_stem())
+        {
+            return file_stem.to_string_lossy().to_string();
+        }
+
+        self.rss_channel
+            .iter()
+            .flat_map(|rss_channel| &rss_channel.items)
+            .find(|item| item_id(item).is_some_and(|id| id == video.id))
+            .and_then(item_file_stem)
+            .unwrap_or_else(|| video.id.clone())
+    }
+
+    /// The file `yt-dlp` lists the media files it downloads in, for `media_file_stem` and each
+    /// download's `elapsed` time. It's removed once read.
+    fn downloaded_files_file(&self) -> PathBuf {
+        self.feed_file.with_extension("downloaded.tsv")
+    }
+
+    /// Path to the media file in the given format with the given name, minus its extension, which
+    /// is usually the id of its item; see `item_file_stem`
+    fn media_path(&self, file_stem: &str, format: MediaFormat) -> Result<PathBuf, Error> {
+        Ok(self
+            .media_dir()?
+            .join(format!("{}.{}", file_stem, format.extension)))
+    }
+
+    /// Paths of the media file and any sidecar files (i.e. subtitles) with the given name, as with `media_path`
+    fn item_paths(&self, file_stem: &str, format: MediaFormat) -> Result<Vec<PathBuf>, Error> {
+        let media_path = self.media_path(file_stem, format)?;
+        let prefix = format!("{}.", file_stem);
+
+        let mut paths = vec![media_path.clone()];
+
+        if let Ok(entries) = std::fs::read_dir(self.media_dir()?) {
+            paths.extend(
+                entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| {
+                        *path != media_path
+                            && path
+                                .file_name()
+                                .is_some_and(|name| name.to_string_lossy().starts_with(&prefix))
+                    })
+                    .sorted(),
+            );
+        }
+
+        Ok(paths)
+    }
+
+    /// The path under the base URL media files are served from.
+    ///
+    /// This is the `url_path_prefix` if set, otherwise the media directory's name, so the URLs
+    /// mirror where the files are stored relative to the directory served at the base URL.
+    pub fn url_path(&self) -> Result<String, Error> {
+        if let Some(ref url_path_prefix) = self.url_path_prefix {
+            return Ok(url_path_prefix.trim_matches('/').to_string());
+        }
+
+        let media_dir = self.media_dir()?;
+
+        Ok(media_dir
+            .file_name()
+            .ok_or_else(|| Error::FileStemError(media_dir.clone()))?
+            .to_string_lossy()
+            .to_string())
+    }
+
+    /// Builds the URL a media file with the given name is served from under `base_url`
+    fn enclosure_url(&self, base_url: &Url, file_name: &str) -> Result<Url, Error> {
+        let url_path = self.url_path()?;
+
+        if url_path.is_empty() {
+            return Ok(base_url.join(file_name)?);
+        }
+
+        Ok(base_url.join(&format!("{}/", url_path))?.join(file_name)?)
+    }
+
+    /// Path to the sidecar file `WriteOptions::channel_info` writes the playlist's metadata to
+    pub fn channel_info_file(&self) -> PathBuf {
+        self.feed_file.with_extension("info.json")
+    }
+
+    /// The playlist-level metadata to write next to the feed, so the feed can be reconstructed
+    /// offline
+    fn channel_info(
+        &self,
+        playlist: &youtube_dl::Playlist,
+        rss_channel: &RSSChannel,
+    ) -> serde_json::Value {
+        serde_json::json!({
+            "id": playlist.id,
+            "title": playlist.title,
+            "description": rss_channel.description(),
+            "uploader": playlist.uploader,
+            "uploader_id": playlist.uploader_id,
+            "uploader_url": playlist.uploader_url,
+            "webpage_url": playlist.webpage_url,
+            "extractor": playlist.extractor,
+            "playlist_url": self.playlist_url,
+        })
+    }
+
+    /// Rewrites every item's enclosure URL to point at `base_url`, without downloading anything.
+    ///
+    /// This is useful after moving the media to a new server; all other item data is preserved.
+    /// Item and channel artwork served from the media directory is moved along with the
+    /// enclosures, but remote enclosures (see `UpdateOptions::remote_enclosures`) and artwork
+    /// hosted elsewhere are left as they are.
+    pub fn rebase(&mut self, base_url: Url) -> Result<(), Error> {
+        let _log_context = LogContext::enter(&self.feed_file);
+
+        let url_path = self.url_path()?;
+
+        // Names the media directory may have been served under before, i.e. before a `url_path_prefix`
+        let media_dir_names = [
+            url_path.rsplit('/').next().unwrap_or_default().to_string(),
+            self.media_dir()?
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+        ];
+
+        let rss_channel = match self.rss_channel.as_mut() {
+            Some(rss_channel) => rss_channel,
+            None => return Ok(()),
+        };
+
+        // Enclosures are always served from the media directory, but artwork only may be
+        let rebase = |url: &str, artwork: bool| -> Result<Option<Url>, Error> {
+            let old_url = match Url::parse(url) {
+                Ok(old_url) => old_url,
+                Err(error) => {
+                    warn!("Not rebasing {:?}, as it isn't a valid URL: {}", url, error);
+                    return Ok(None);
+                }
+            };
+
+            let mut segments = match old_url.path_segments() {
+                Some(segments) => segments.collect_vec(),
+                None => return Ok(None),
+            };
+
+            let file_name = match segments.pop() {
+                Some(file_name) if !file_name.is_empty() => file_name,
+                _ => return Ok(None),
+            };
+
+            let dir_name = segments.last().copied().unwrap_or_default();
+            if artwork
+                && !media_dir_names
+                    .iter()
+                    .any(|name| !name.is_empty() && name == dir_name)
+            {
+                return Ok(None);
+            }
+
+            // Keep any query (i.e. from `enclosure_query`) and fragment on the new URL
+            let mut new_url = match url_path.is_empty() {
+                true => base_url.join(file_name)?,
+                false => base_url.join(&format!("{}/", url_path))?.join(file_name)?,
+            };
+            new_url.set_query(old_url.query());
+            new_url.set_fragment(old_url.fragment());
+
+            Ok(Some(new_url))
+        };
+
+        for item in rss_channel.items.iter_mut() {
+            if let Some(ref mut enclosure) = item.enclosure {
+                // Remote enclosures carry their item's id as their fragment
+                let is_remote =
+                    Url::parse(&enclosure.url).is_ok_and(|url| url.fragment().is_some());
+
+                if !is_remote {
+                    if let Some(new_url) = rebase(&enclosure.url, false)? {
+                        enclosure.set_url(new_url);
+                    }
+                }
+            }
+
+            if let Some(ref mut itunes_ext) = item.itunes_ext {
+                if let Some(new_url) = itunes_ext
+                    .image
+                    .as_deref()
+                    .map(|image| rebase(image, true))
+                    .transpose()?
+                    .flatten()
+                {
+                    itunes_ext.set_image(new_url.to_string());
+                }
+            }
+        }
+
+        if let Some(ref mut itunes_ext) = rss_channel.itunes_ext {
+            if let Some(new_url) = itunes_ext
+                .image
+                .as_deref()
+                .map(|image| rebase(image, true))
+                .transpose()?
+                .flatten()
+            {
+                itunes_ext.set_image(new_url.to_string());
+            }
+        }
+
+        if let Some(ref mut image) = rss_channel.image {
+            if let Some(new_url) = rebase(&image.url, true)? {
+                image.set_url(new_url);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rewrites every item's GUID using `scheme`, preserving the items' order and dates. Returns
+    /// the number of GUIDs changed.
+    ///
+    /// Podcast apps identify episodes by their GUIDs, so subscribers will likely see every changed
+    /// item as a new episode. Items not added by Playcaster (without a media file) are left alone.
+    pub fn migrate_guids(&mut self, scheme: &GuidScheme) -> usize {
+        let rss_channel = match self.rss_channel {
+            Some(ref mut rss_channel) => rss_channel,
+            None => return 0,
+        };
+
+        let mut changed = 0;
+
+        for item in rss_channel.items.iter_mut() {
+            let (id, enclosure_url) = match (item_id(item), item.enclosure()) {
+                (Some(id), Some(enclosure)) => (id, enclosure.url().to_string()),
+                _ => continue,
+            };
+
+            let guid = scheme.guid(&id, &enclosure_url);
+
+            if item.guid.as_ref() != Some(&guid) {
+                item.set_guid(guid);
+                changed += 1;
+            }
+        }
+
+        changed
+    }
+
+    /// Trims the feed to its newest `keep` items, without downloading anything, optionally
+    /// deleting the files of the items removed.
+    ///
+    /// The feed's items are expected to be in order, newest first, as they're written.
+    pub fn prune(&mut self, keep: usize, delete_files: bool) -> Result<UpdateSummary, Error> {
+        let _log_context = LogContext::enter(&self.feed_file);
+
+        check_keep(Some(keep))?;
+
+        let mut summary = UpdateSummary::default();
+
+        let mut items = match self.rss_channel {
+            Some(ref mut rss_channel) => rss_channel.items.split_off(0),
+            None => return Ok(summary),
+        };
+
+        if items.len() > keep {
+            let mut removed_items: Vec<_> = items.drain(keep..).collect();
+            summary.removed = removed_items.len();
+
+            // A feed updated with `no_dedup` may have repeats, whose files a kept item still uses
+            let kept_ids: HashSet<_> = items.iter().filter_map(item_id).collect();
+            removed_items.retain(|item| item_id(item).is_none_or(|id| !kept_ids.contains(&id)));
+
+            if delete_files {
+                self.remove_item_files(&removed_items, &UpdateOptions::default(), &mut summary)?;
+            }
+        }
+
+        summary.total = items.len();
+
+        if let Some(ref mut rss_channel) = self.rss_channel {
+            rss_channel.set_items(items);
+        }
+
+        Ok(summary)
+    }
+
+    /// Merges another feed's items into this one, without any network access or downloads.
+    ///
+    /// Items are deduplicated by their GUID (or, lacking one, as updates deduplicate them),
+    /// keeping this feed's copy, then sorted as `options` order updates. Without a playlist,
+    /// ordering by views or playlist position leaves them as they are. Their enclosures are left
+    /// pointing wherever the other feed's did. Returns the number of items added.
+    pub fn merge_feed(&mut self, other: &RSSChannel, options: &UpdateOptions) -> usize {
+        let rss_channel = match self.rss_channel {
+            Some(ref mut rss_channel) => rss_channel,
+            None => {
+                self.rss_channel = Some(other.clone());
+                return other.items.len();
+            }
+        };
+
+        let merge_key = |item: &RSSItem| {
+            item.guid()
+                .map(|guid| guid.value().to_string())
+                .or_else(|| item_dedup_key(item))
+        };
+
+        let mut seen_keys: HashSet<_> = rss_channel.items.iter().filter_map(merge_key).collect();
+
+        let added_items: Vec<_> = other
+            .items
+            .iter()
+            .filter(|item| match merge_key(item) {
+                Some(key) => seen_keys.insert(key),
+                None => true,
+            })
+            .cloned()
+            .collect();
+        let added = added_items.len();
+
+        let mut items = rss_channel.items.split_off(0);
+        items.extend(added_items);
+
+        sort_items(&mut items, options, &[]);
+        rss_channel.set_items(items);
+
+        for (prefix, namespace) in &other.namespaces {
+            rss_channel
+                .namespaces
+                .entry(prefix.clone())
+                .or_insert_with(|| namespace.clone());
+        }
+
+        added
+    }
+
+    /// Removes the files of items trimmed from the feed by `keep` (or `keep_files`).
+    ///
+    /// Files are left alone when downloads are skipped, and listed as pending instead when
+    /// deletions need confirming.
+    fn remove_item_files(
+        &self,
+        items: &[RSSItem],
+        options: &UpdateOptions,
+        summary: &mut UpdateSummary,
+    ) -> Result<(), Error> {
+        for item in items {
+            let file_stem = match item_file_stem(item) {
+                Some(file_stem) => file_stem,
+                None => continue,
+            };
+
+            for path in self.item_paths(&file_stem, MediaFormat::of_item(item))? {
+                if options.skip_download || options.remote_enclosures {
+                    debug!("Skipping removal of file: {:?}", path);
+                    continue;
+                }
+
+                if options.confirm_deletes {
+                    info!("Not removing file until confirmed: {:?}", path);
+                    summary.pending_deletions.push(path);
+                    continue;
+                }
+
+                match remove_media_file(&path, options.trash_dir.as_deref()) {
+                    Ok(()) => summary.deleted_files.push(path),
+                    Err(err) => warn!("Couldn't remove file: {:?}", err),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Changes the playlist the channel is updated from.
+    ///
+    /// This takes effect on the next update; until then, nothing in the existing feed (such as its
+    /// link or title) changes. Fails if `url` isn't an http or https URL.
+    pub fn set_playlist_url(&mut self, url: Url) -> Result<(), Error> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(Error::PlaylistUrlError(url));
+        }
+
+        self.playlist_url = url;
+
+        Ok(())
+    }
+
+    /// The default history file for `append_history`, next to the feed file, e.g.
+    /// `mightycarmods.history.jsonl` for `mightycarmods.xml`
+    pub fn history_file(&self) -> PathBuf {
+        self.feed_file.with_extension("history.jsonl")
+    }
+
+    /// Removes the channel's media directory if it's empty, e.g. after its files were removed.
+    ///
+    /// A directory holding anything at all, such as subtitles or other sidecar files, is kept.
+    /// Returns whether the directory was removed.
+    pub fn prune_media_dir(&self) -> Result<bool, Error> {
+        let media_dir = self.media_dir()?;
+
+        let mut entries = match std::fs::read_dir(&media_dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(error) => return Err(error.into()),
+        };
+
+        if entries.next().is_some() {
+            debug!("Keeping non-empty media directory: {:?}", media_dir);
+            return Ok(false);
+        }
+
+        debug!("Removing empty media directory: {:?}", media_dir);
+
+        // `remove_dir` refuses to remove a directory which isn't empty, should a file appear meanwhile
+        std::fs::remove_dir(&media_dir)?;
+
+        Ok(true)
+    }
+
+    /// Lists the files purging the channel would delete, without deleting anything.
+    ///
+    /// Unless `force` is set, this fails with `Error::PurgeUnreferencedError` if the media directory
+    /// holds files the feed doesn't reference, which may have been put there by something else.
+    pub fn purge_plan(&self, force: bool) -> Result<PurgePlan, Error> {
+        let mut feed_files = [OutputFormat::Rss, OutputFormat::Jsonfeed]
+            .iter()
+            .map(|format| self.output_file(*format))
+            .flat_map(|output_file| [preview_file(&output_file), output_file])
+            .chain([self.history_file(), self.channel_info_file()])
+            .filter(|path| path.is_file())
+            .collect_vec();
+        feed_files.sort();
+        feed_files.dedup();
+
+        let media_dir = Some(self.media_dir()?).filter(|media_dir| media_dir.is_dir());
+
+        let mut item_files = HashSet::new();
+        if let Some(ref rss_channel) = self.rss_channel {
+            for item in &rss_channel.items {
+                if let Some(file_stem) = item_file_stem(item) {
+                    item_files.extend(self.item_paths(&file_stem, MediaFormat::of_item(item))?);
+                }
+            }
+        }
+
+        let mut plan = PurgePlan {
+            feed_files,
+            media_dir: media_dir.clone(),
+            ..Default::default()
+        };
+
+        let mut dirs = media_dir.into_iter().collect_vec();
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(&dir)? {
+                let path = entry?.path();
+
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if item_files.contains(&path) {
+                    plan.referenced_files.push(path);
+                } else {
+                    plan.unreferenced_files.push(path);
+                }
+            }
+        }
+
+        plan.referenced_files.sort();
+        plan.unreferenced_files.sort();
+
+        if let (Some(ref media_dir), Some(unreferenced), false) =
+            (&plan.media_dir, plan.unreferenced_files.first(), force)
+        {
+            return Err(Error::PurgeUnreferencedError(
+                media_dir.clone(),
+                plan.unreferenced_files.len(),
+                unreferenced.clone(),
+            ));
+        }
+
+        Ok(plan)
+    }
+
+    /// Retires the channel by deleting its feed file, the files written beside it and its whole
+    /// media directory, as listed by `purge_plan`, which is returned.
+    ///
+    /// Like `purge_plan`, this refuses to delete a media directory holding files the feed doesn't
+    /// reference unless `force` is set, in which case they're deleted too.
+    pub fn purge(&self, force: bool) -> Result<PurgePlan, Error> {
+        let plan = self.purge_plan(force)?;
+
+        if let Some(ref media_dir) = plan.media_dir {
+            debug!("Removing media directory: {:?}", media_dir);
+            std::fs::remove_dir_all(media_dir)?;
+        }
+
+        for feed_file in &plan.feed_files {
+            debug!("Removing feed file: {:?}", feed_file);
+            std::fs::remove_file(feed_file)?;
+        }
+
+        Ok(plan)
+    }
+
+    /// Deletes partial downloads (`.part`, `.part-Frag*` and `.ytdl` files) in the channel's media
+    /// directory last modified more than `max_age` ago, such as ones left behind by a crash, so
+    /// `yt-dlp` starts those downloads afresh. Returns the paths of the deleted files.
+    pub fn clean_stale_partials(&self, max_age: Duration) -> Result<Vec<PathBuf>, Error> {
+        let entries = match std::fs::read_dir(self.media_dir()?) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(error) => return Err(error.into()),
+        };
+
+        let mut deleted = vec![];
+
+        for entry in entries {
+            let path = entry?.path();
+
+            let is_partial = path.file_name().is_some_and(|file_name| {
+                let file_name = file_name.to_string_lossy();
+                file_name.ends_with(".part")
+                    || file_name.ends_with(".ytdl")
+                    || file_name.contains(".part-Frag")
+            });
+
+            let is_stale = || {
+                std::fs::metadata(&path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .and_then(|modified| modified.elapsed().ok())
+                    .is_some_and(|age| age > max_age)
+            };
+
+            if is_partial && path.is_file() && is_stale() {
+                debug!("Removing stale partial download: {:?}", path);
+                std::fs::remove_file(&path)?;
+                deleted.push(path);
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    /// Checks the feed is consistent with the media on disk, without modifying anything.
+    ///
+    /// The feed must validate, and every item must have a well-formed enclosure URL under
+    /// `base_url` whose media file exists. Returns every problem found, if any.
+    pub fn check(&self, base_url: &Url) -> Result<Vec<CheckProblem>, Error> {
+        let _log_context = LogContext::enter(&self.feed_file);
+
+        use rss::validation::Validate;
+
+        let rss_channel = match self.rss_channel {
+            Some(ref rss_channel) => rss_channel,
+            None => return Ok(vec![CheckProblem::NoFeed]),
+        };
+
+        let mut problems = vec![];
+
+        if let Err(error) = rss_channel.validate() {
+            problems.push(CheckProblem::Invalid(error));
+        }
+
+        let media_url = self.enclosure_url(base_url, "")?;
+
+        for item in &rss_channel.items {
+            let label = item_id(item)
+                .or_else(|| item.title().map(|title| title.to_string()))
+                .unwrap_or_default();
+
+            let enclosure = match item.enclosure() {
+                Some(enclosure) => enclosure,
+                None => {
+                    problems.push(CheckProblem::MissingEnclosure(label));
+                    continue;
+                }
+            };
+
+            let enclosure_url = match Url::parse(enclosure.url()) {
+                Ok(enclosure_url) => enclosure_url,
+                Err(error) => {
+                    problems.push(CheckProblem::MalformedEnclosureUrl(
+                        label,
+                        enclosure.url().to_string(),
+                        error,
+                    ));
+                    continue;
+                }
+            };
+
+            if !enclosure_url.as_str().starts_with(media_url.as_str()) {
+                problems.push(CheckProblem::ForeignEnclosureUrl(
+                    label.clone(),
+                    enclosure_url,
+                ));
+            }
+
+            let file_stem = item_file_stem(item).unwrap_or_else(|| label.clone());
+            let path = self.media_path(&file_stem, MediaFormat::of_item(item))?;
+
+            if !path.is_file() {
+                problems.push(CheckProblem::MissingMedia(label, path));
+            }
+        }
+
+        Ok(problems)
+    }
+
+    /// Builds a feed of the `limit` newest items across `channels`, for a "latest episodes" view.
+    ///
+    /// Each item gets a `<source>` element crediting the channel it came from, unless it already
+    /// has one, and items sharing a GUID are only included once. As the feed has no playlist of
+    /// its own, `link` is used as both its playlist URL and its link.
+    pub fn new_combined(feed_file: PathBuf, link: Url, channels: &[Channel], limit: usize) -> Self {
+        let mut items: Vec<_> = channels
+            .iter()
+            .filter_map(|channel| {
+                let rss_channel = channel.rss_channel.as_ref()?;
+
+                let source = RSSSourceBuilder::default()
+                    .url(channel.playlist_url.to_string())
+                    .title(Some(rss_channel.title.clone()))
+                    .build();
+
+                Some(rss_channel.items.iter().map(move |item| {
+                    let mut item = item.clone();
+                    item.source.get_or_insert_with(|| source.clone());
+                    item
+                }))
+            })
+            .flatten()
+            .collect();
+
+        // Newest first; the sort is stable, so items without dates keep their feed's order, last
+        items.sort_by_key(|item| std::cmp::Reverse(item_pub_date(item)));
+
+        let mut guids = HashSet::new();
+
+        items.retain(|item| match item.guid() {
+            Some(guid) => guids.insert(guid.value().to_string()),
+            None => true,
+        });
+
+        items.truncate(limit);
+
+        // The items may use elements from any of the channels' namespaces
+        let namespaces: BTreeMap<_, _> = channels
+            .iter()
+            .filter_map(|channel| channel.rss_channel.as_ref())
+            .flat_map(|rss_channel| rss_channel.namespaces.clone())
+            .collect();
+
+        let title = "Latest episodes".to_string();
+
+        let rss_channel = RSSChannelBuilder::default()
+            .title(title.clone())
+            .link(link.to_string())
+            .description(format!("{} podcast feed for {}", PKG_NAME, title))
+            .generator(Some(format!(
+                "{}/{} ({})",
+                PKG_NAME, PKG_VERSION, PKG_HOMEPAGE
+            )))
+            .namespaces(namespaces)
+            .items(items)
+            .build();
+
+        Self {
+            feed_file,
+            playlist_url: link,
+            rss_channel: Some(rss_channel),
+            media_dir: None,
+            url_path_prefix: None,
+            channel_info: None,
+        }
+    }
+
+    /// Computes statistics about the feed and its media files on disk, without any network access.
+    ///
+    /// A channel with no feed yet has no items, so all its stats are empty.
+    pub fn stats(&self) -> Result<ChannelStats, Error> {
+        let items = match self.rss_channel {
+            Some(ref rss_channel) => &rss_channel.items[..],
+            None => &[],
+        };
+
+        let mut stats = ChannelStats {
+            item_count: items.len(),
+            oldest_pub_date: items.iter().filter_map(item_pub_date).min(),
+            newest_pub_date: items.iter().filter_map(item_pub_date).max(),
+            ..Default::default()
+        };
+
+        for item in items {
+            let metadata = match item_file_stem(item) {
+                Some(file_stem) => {
+                    std::fs::metadata(self.media_path(&file_stem, MediaFormat::of_item(item))?)
+                        .ok()
+                        .filter(|metadata| metadata.is_file())
+                }
+                None => None,
+            };
+
+            match metadata {
+                Some(metadata) => stats.media_bytes += metadata.len(),
+                None => stats.missing_media += 1,
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Reads the settings stored in the feed's channel elements; see `FeedSettings`.
+    ///
+    /// Values which can't be parsed are ignored with a warning. A channel with no feed yet has
+    /// no stored settings.
+    pub fn feed_settings(&self) -> FeedSettings {
+        let named = match self
+            .rss_channel
+            .as_ref()
+            .and_then(|rss_channel| rss_channel.extensions.get(PLAYCASTER_PREFIX))
+        {
+            Some(named) => named,
+            None => return FeedSettings::default(),
+        };
+
+        let values = |name: &str| {
+            named
+                .get(name)
+                .into_iter()
+                .flatten()
+                .filter_map(|extension| extension.value())
+                .map(str::trim)
+                .collect_vec()
+        };
+
+        let number = |name: &str| {
+            values(name)
+                .first()
+                .and_then(|value| match value.parse::<usize>() {
+                    Ok(number) => Some(number),
+                    Err(_) => {
+                        warn!(
+                            "Ignoring the feed's {}:{} of {:?}, as it isn't a number",
+                            PLAYCASTER_PREFIX, name, value
+                        );
+                        None
+                    }
+                })
+        };
+
+        FeedSettings {
+            keep: number("keep"),
+            download_limit: number("limit"),
+            categories: values("category")
+                .into_iter()
+                .filter_map(|value| match value.parse::<Category>() {
+                    Ok(category)
+                        if !category.name.is_empty()
+                            && category.subcategory.as_deref() != Some("") =>
+                    {
+                        Some(category)
+                    }
+                    _ => {
+                        warn!(
+                            "Ignoring the feed's {}:category of {:?}, as it isn't a `Category` or `Category/Subcategory`",
+                            PLAYCASTER_PREFIX, value
+                        );
+                        None
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Whether the channel is paused with a `playcaster:disabled` element in its feed, whose value
+    /// is `true`, so `update_channels` skips it without its playlist needing to be removed
+    pub fn is_disabled(&self) -> bool {
+        self.rss_channel
+            .as_ref()
+            .and_then(|rss_channel| rss_channel.extensions.get(PLAYCASTER_PREFIX))
+            .and_then(|named| named.get("disabled"))
+            .and_then(|extensions| extensions.first())
+            .and_then(|extension| extension.value())
+            .is_some_and(|value| value.trim().eq_ignore_ascii_case("true"))
+    }
+
+    /// Whether the feed already contains an item with the given id
+    pub fn contains(&self, id: &str) -> bool {
+        self.rss_channel.as_ref().is_some_and(|rss_channel| {
+            rss_channel
+                .items
+                .iter()
+                .any(|item| item_id(item).is_some_and(|item_id| item_id == id))
+        })
+    }
+
+    /// Removes the item with the given id from the feed, optionally deleting its media file and
+    /// any sidecar files too (or moving them to `trash_dir`, if given), as updates do.
+    ///
+    /// Returns the files deleted, or `None` if no matching item was found.
+    pub fn remove_item(
+        &mut self,
+        id: &str,
+        delete_files: bool,
+        trash_dir: Option<&Path>,
+    ) -> Result<Option<Vec<PathBuf>>, Error> {
+        let rss_channel = match self.rss_channel {
+            Some(ref mut rss_channel) => rss_channel,
+            None => return Ok(None),
+        };
+
+        let mut removed = None;
+
+        rss_channel.items.retain(|item| {
+            let matches = item_id(item).is_some_and(|item_id| item_id == id);
+
+            if matches {
+                removed = Some((
+                    item_file_stem(item).unwrap_or_else(|| id.to_string()),
+                    MediaFormat::of_item(item),
+                ));
+            }
+
+            !matches
+        });
+
+        let (file_stem, removed_format) = match removed {
+            Some(removed) => removed,
+            None => return Ok(None),
+        };
+
+        let mut deleted_files = vec![];
+
+        if delete_files {
+            for path in self.item_paths(&file_stem, removed_format)? {
+                // The media file may never have been downloaded, or already been removed
+                if !path.exists() {
+                    continue;
+                }
+
+                remove_media_file(&path, trash_dir)?;
+                deleted_files.push(path);
+            }
+        }
+
+        Ok(Some(deleted_files))
+    }
+
+    /// Updates the feed from a playlist's metadata, such as from `read_playlist_json`, without
+    /// running the downloader. Media files are expected to be on disk already.
+    pub fn update_with_playlist(
+        &mut self,
+        base_url: Url,
+        options: &UpdateOptions,
+        playlist: youtube_dl::Playlist,
+    ) -> Result<UpdateSummary, Error> {
+        self.update_with_playlist_and_transform(
+            base_url,
+            options,
+            playlist,
+            &HashMap::new(),
+            |_, item| item,
+        )
+    }
+
+    fn update_with_playlist_and_transform<F>(
+        &mut self,
+        base_url: Url,
+        options: &UpdateOptions,
+        mut playlist: youtube_dl::Playlist,
+        downloaded_files: &HashMap<String, DownloadedFile>,
+        mut transform: F,
+    ) -> Result<UpdateSummary, Error>
+    where
+        F: FnMut(&youtube_dl::SingleVideo, RSSItem) -> RSSItem,
+    {
+        let _log_context = LogContext::enter(&self.feed_file);
+
+        check_keep(options.keep)?;
+        check_keep(options.keep_files)?;
+        self.check_custom_elements(options)?;
+        check_categories(&options.categories, options.strict)?;
+
+        if !options.no_dedup {
+            dedup_entries(&mut playlist);
+        }
+
+        let title = playlist
+            .title
+            .as_ref()
+            .unwrap_or(&self.playlist_url.to_string())
+            .clone();
+
+        let media_dir = self.media_dir()?;
+        let preferred_format = options.media_format();
+        let guid_scheme = options.guid_scheme();
+
+        let mut zero_duration_item_paths = vec![];
+        let mut invalid_enclosures = vec![];
+
+        let mut rss_items: Vec<RSSItem> = match playlist.entries {
+            Some(ref entries) => entries
+                .iter()
+                .filter_map(|video| {
+                    use hhmmss::Hhmmss;
+
+                    if !has_min_views(video, options) {
+                        debug!("Skipping {:?}, as it has too few views", video.id);
+                        return None;
+                    }
+
+                    if has_skipped_duration(video, options) {
+                        debug!("Skipping {:?}, as its duration is one to skip", video.id);
+                        return None;
+                    }
+
+                    let media_format = preferred_format.of_video(video);
+
+                    if media_format != preferred_format {
+                        warn!(
+                            "The preferred format wasn't available for {:?}, so it was downloaded as {}",
+                            video.id, media_format.extension
+                        );
+                    }
+
+                    let file_stem = self.media_file_stem(video, options, downloaded_files);
+
+                    let enclosure_url = match options.remote_enclosures {
+                        true => remote_enclosure_url(video),
+                        false => self.enclosure_url(
+                            &base_url,
+                            &format!("{}.{}", file_stem, media_format.extension),
+                        ),
+                    };
+
+                    let mut enclosure_url = match enclosure_url {
+                        Ok(enclosure_url) => enclosure_url,
+                        Err(error) => {
+                            warn!(
+                                "Skipping {:?}, as its enclosure URL couldn't be built: {}",
+                                video.id, error
+                            );
+                            invalid_enclosures.push(video.id.clone());
+                            return None;
+                        }
+                    };
+
+                    let duration = video_duration(video);
+
+                    let item_path =
+                        media_dir.join(format!("{}.{}", file_stem, media_format.extension));
+
+                    if is_empty_entry(video, &item_path) {
+                        zero_duration_item_paths.push(item_path.clone());
+                    }
+
+                    let episode_number = match options.episode_numbers {
+                        true => video_episode_number(video),
+                        false => None,
+                    };
+
+                    let item_itunes_extension =
+                        ITunesItemExtensionBuilder::default()
+                            .author(author(options, &playlist, Some(video), &title))
+                            .subtitle(video_title(video, options.title_source))
+                            .summary(video.description.as_ref().map(|description| {
+                                truncate_chars(description, ITUNES_SUMMARY_LIMIT)
+                            }))
+                            .image(item_thumbnail(video, options.thumbnail_preference))
+                            .duration(duration.hhmmss())
+                            .keywords(video_keywords(video))
+                            .explicit("No".to_string())
+                            .episode(episode_number.map(|number| number.to_string()))
+                            .build();
+
+                    // Source URLs are often signed, so they're left as they are
+                    if !options.enclosure_query.is_empty() && !options.remote_enclosures {
+                        enclosure_url
+                            .query_pairs_mut()
+                            .extend_pairs(&options.enclosure_query);
+                    }
+
+                    let item_guid = guid_scheme.guid(&video.id, enclosure_url.as_str());
+
+                    let item_enclosure = RSSEnclosureBuilder::default()
+                        .url(enclosure_url)
+                        .length(enclosure_length(video, &item_path).to_string())
+                        .mime_type(
+                            mime_type_for_extension(media_format.extension, &options.mime_map)
+                                .unwrap_or_else(|| "application/octet-stream".to_string()),
+                        )
+                        .build();
+
+                    // video.release_date
+                    // video.upload_date
+
+                    let mut item = RSSItemBuilder::default();
+
+                    // `yt-dlp` names subtitle files after the media file, with the language before the extension
+                    let transcripts: Vec<_> = options
+                        .sub_langs
+                        .iter()
+                        .filter_map(|language| {
+                            let file_name = format!("{}.{}.vtt", file_stem, language);
+
+                            if !media_dir.join(&file_name).exists() {
+                                debug!("No {:?} subtitles for {:?}", language, video.id);
+                                return None;
+                            }
+
+                            self.enclosure_url(&base_url, &file_name)
+                                .ok()
+                                .map(|url| transcript_extension(&url, language))
+                        })
+                        .collect();
+
+                    let mut extensions = ExtensionMap::new();
+
+                    if !transcripts.is_empty() {
+                        extensions.insert(
+                            PODCAST_PREFIX.to_string(),
+                            BTreeMap::from([("transcript".to_string(), transcripts)]),
+                        );
+                    }
+
+                    // Items already in the feed keep the timestamp recorded when they were added
+                    if options.write_downloaded_timestamp && !options.remote_enclosures {
+                        if let Some(downloaded) = downloaded_extension(&item_path) {
+                            extensions
+                                .entry(PLAYCASTER_PREFIX.to_string())
+                                .or_default()
+                                .insert("downloaded".to_string(), vec![downloaded]);
+                        }
+                    }
+
+                    // Record the id for `item_id`, as the media file isn't named after it
+                    if file_stem != video.id && !options.remote_enclosures {
+                        extensions
+                            .entry(PLAYCASTER_PREFIX.to_string())
+                            .or_default()
+                            .insert(
+                                "id".to_string(),
+                                vec![ExtensionBuilder::default()
+                                    .name(format!("{}:id", PLAYCASTER_PREFIX))
+                                    .value(video.id.clone())
+                                    .build()],
+                            );
+                    }
+
+                    item.extensions(extensions);
+
+                    item.guid(item_guid)
+                        .title(video_title(video, options.title_source))
+                        .description(video.description.clone())
+                        .link(video.webpage_url.clone())
+                        .enclosure(item_enclosure)
+                        .itunes_ext(item_itunes_extension);
+
+                    if let Some(pub_date) = video_pub_date(video, options.date_source) {
+                        item.pub_date(pub_date.to_rfc2822());
+                    }
+
+                    if options.with_source {
+                        item.source(
+                            RSSSourceBuilder::default()
+                                .url(self.playlist_url.to_string())
+                                .title(video.playlist_title.clone().or_else(|| Some(title.clone())))
+                                .build(),
+                        );
+                    }
+
+                    if options.per_item_language {
+                        if let Some(ref language) = video.language {
+                            item.dublin_core_ext(DublinCoreExtension {
+                                languages: vec![language.clone()],
+                                ..Default::default()
+                            });
+                        }
+                    }
+
+                    Some(transform(video, item.build()))
+                })
+                .collect(),
+            None => vec![],
+        };
+
+        if !rss_items.is_empty() && zero_duration_item_paths.len() == rss_items.len() {
+            return Err(Error::AllDownloadsEmptyError(self.playlist_url.clone()));
+        } else if !zero_duration_item_paths.is_empty() {
+            warn!("One or more files were not found on disk!\nYour playlist URL might be invalid. {:?}", zero_duration_item_paths);
+        }
+
+        // Retrieve the existing RSS channel, or create a new one
+        let mut rss_channel = self.rss_channel.clone().unwrap_or_else(|| {
+            let description = format!("{} podcast feed for {}", PKG_NAME, title);
+
+            let rss_itunes_category = ITunesCategoryBuilder::default().text("TV & Film").build();
+
+            let rss_itunes_extension = ITunesChannelExtensionBuilder::default()
+                .author(author(options, &playlist, None, &title))
+                .subtitle(title.clone())
+                .summary(description.clone())
+                .explicit("No".to_string())
+                .category(rss_itunes_category)
+                .block("Yes".to_string())
+                .build();
+
+            RSSChannelBuilder::default()
+                .title(title.clone())
+                .description(options.description.clone().unwrap_or(description))
+                .itunes_ext(rss_itunes_extension)
+                .build()
+        });
+
+        if !options.categories.is_empty() {
+            if let Some(ref mut channel_itunes_ext) = rss_channel.itunes_ext {
+                let mut categories = options.categories.iter().unique().collect_vec();
+
+                if options.sort_categories {
+                    categories.sort();
+                }
+
+                channel_itunes_ext
+                    .set_categories(categories.into_iter().map(itunes_category).collect_vec());
+            }
+        }
+
+        set_custom_elements(&mut rss_channel.extensions, &options.channel_elements);
+
+        if options.provisional {
+            rss_channel
+                .extensions
+                .entry(PLAYCASTER_PREFIX.to_string())
+                .or_default()
+                .insert(
+                    "provisional".to_string(),
+                    vec![ExtensionBuilder::default()
+                        .name(format!("{}:provisional", PLAYCASTER_PREFIX))
+                        .value(PROVISIONAL_NOTE.to_string())
+                        .build()],
+                );
+        } else if let Some(named) = rss_channel.extensions.get_mut(PLAYCASTER_PREFIX) {
+            named.remove("provisional");
+
+            if named.is_empty() {
+                rss_channel.extensions.remove(PLAYCASTER_PREFIX);
+            }
+        }
+
+        if let Some(ref feed_settings) = options.feed_settings {
+            set_feed_settings(&mut rss_channel.extensions, feed_settings);
+        }
+
+        if let (Some(ref author), Some(ref mut channel_itunes_ext)) =
+            (&options.author, &mut rss_channel.itunes_ext)
+        {
+            channel_itunes_ext.set_author(author.clone());
+        }
+
+        if options.sync_title && rss_channel.title != title {
+            let old_description = format!("{} podcast feed for {}", PKG_NAME, rss_channel.title);
+            let description = format!("{} podcast feed for {}", PKG_NAME, title);
+
+            // Only replace the description and summary if they were derived from the old title
+            if rss_channel.description == old_description {
+                rss_channel.set_description(description.clone());
+            }
+
+            if let Some(ref mut channel_itunes_ext) = rss_channel.itunes_ext {
+                channel_itunes_ext.set_author(author(options, &playlist, None, &title));
+                channel_itunes_ext.set_subtitle(title.clone());
+
+                if channel_itunes_ext.summary.as_deref() == Some(old_description.as_str()) {
+                    channel_itunes_ext.set_summary(description);
+                }
+            }
+
+            rss_channel.set_title(title.clone());
+        }
+
+        if options.sync_description {
+            if let Some(ref description) = options.description {
+                rss_channel.set_description(description.clone());
+            }
+        }
+
+        let existing_ids: HashSet<_> = rss_channel.items.iter().filter_map(item_id).collect();
+
+        if let Some(max_new) = options.max_new_per_run {
+            let mut new_count = 0;
+
+            rss_items.retain(|item| {
+                if item_id(item).is_some_and(|id| existing_ids.contains(&id)) {
+                    return true;
+                }
+
+                new_count += 1;
+                new_count <= max_new
+            });
+        }
+
+        // Items already in the feed keep their place and dates in it, with the fresh metadata only if
+        // preferred. Their enclosures and durations are refreshed, e.g. as remote enclosures' URLs expire.
+        let existing_items: HashMap<_, _> = rss_channel
+            .items
+            .iter()
+            .filter_map(|item| item_dedup_key(item).map(|key| (key, item)))
+            .collect();
+
+        for item in rss_items.iter_mut() {
+            if let Some(existing_item) =
+                item_dedup_key(item).and_then(|key| existing_items.get(&key))
+            {
+                *item = merge_item(
+                    (*existing_item).clone(),
+                    item,
+                    options.prefer_fresh_metadata,
+                );
+            }
+        }
+
+        let mut summary = UpdateSummary {
+            invalid_enclosures,
+            ..Default::default()
+        };
+
+        let mut unique_items: Vec<_> = if options.no_dedup {
+            // Repeats within the playlist are kept, but existing items are still replaced by their
+            // fresh copies, so they aren't added again on every run
+            let fresh_keys: HashSet<_> = rss_items.iter().filter_map(item_dedup_key).collect();
+
+            rss_channel
+                .items
+                .retain(|item| item_dedup_key(item).is_none_or(|key| !fresh_keys.contains(&key)));
+            rss_items.append(&mut rss_channel.items);
+            rss_items
+        } else {
+            rss_items.append(&mut rss_channel.items);
+
+            let mut seen_keys = HashSet::new();
+
+            rss_items
+                .into_iter()
+                .filter(|item| match item_dedup_key(item) {
+                    Some(key) => seen_keys.insert(key),
+                    None => true,
+                })
+                .collect()
+        };
+
+        sort_items(
+            &mut unique_items,
+            options,
+            playlist.entries.as_deref().unwrap_or_default(),
+        );
+
+        // Their files are only removed once the updated feed is known to be valid, below
+        let mut unkept_items = vec![];
+
+        if let Some(keep_item_count) = options.keep {
+            if unique_items.len() > keep_item_count {
+                let mut removed_items: Vec<_> = unique_items.drain(keep_item_count..).collect();
+                summary.removed = removed_items.len();
+
+                // With `no_dedup`, a removed repeat's files may still be used by a kept item
+                if options.no_dedup {
+                    let kept_ids: HashSet<_> = unique_items.iter().filter_map(item_id).collect();
+                    removed_items
+                        .retain(|item| item_id(item).is_none_or(|id| !kept_ids.contains(&id)));
+                }
+
+                unkept_items.append(&mut removed_items);
+            }
+        }
+
+        if let Some(keep_file_count) = options.keep_files {
+            // With `no_dedup`, a repeat's files may still be used by an earlier item keeping them
+            let kept_ids: HashSet<_> = match options.no_dedup {
+                true => unique_items
+                    .iter()
+                    .take(keep_file_count)
+                    .filter_map(item_id)
+                    .collect(),
+                false => HashSet::new(),
+            };
+
+            // Only items whose media is still on disk, so files removed by earlier runs aren't retried
+            unkept_items.extend(
+                unique_items
+                    .iter()
+                    .skip(keep_file_count)
+                    .filter(|item| item_id(item).is_none_or(|id| !kept_ids.contains(&id)))
+                    .filter(|item| {
+                        item_file_stem(item).is_some_and(|file_stem| {
+                            self.media_path(&file_stem, MediaFormat::of_item(item))
+                                .is_ok_and(|path| path.exists())
+                        })
+                    })
+                    .cloned(),
+            );
+        }
+
+        if let Some(disambiguator) = options.disambiguate_titles {
+            disambiguate_titles(&mut unique_items, disambiguator);
+        }
+
+        for item in unique_items.iter_mut() {
+            if let Some(ref mut item_ext) = item.itunes_ext {
+                if let Some(image) = item_ext.image.take() {
+                    item_ext.image = Some(https_image(image, options.require_https_images));
+                }
+            }
+        }
+
+        if let Some(ref mut channel_itunes_ext) = rss_channel.itunes_ext {
+            for item in &unique_items {
+                if let Some(ref item_ext) = item.itunes_ext {
+                    channel_itunes_ext.image.clone_from(&item_ext.image);
+                    break;
+                }
+            }
+
+            if !options.channel_keywords.is_empty() {
+                channel_itunes_ext.set_keywords(options.channel_keywords.join(", "));
+            } else if let Some(keywords) = channel_keywords(&unique_items) {
+                channel_itunes_ext.set_keywords(keywords);
+            }
+
+            if let Some(episode_count) = options.summary_from_episodes {
+                let episode_titles = unique_items
+                    .iter()
+                    .filter_map(|item| item.title())
+                    .take(episode_count)
+                    .map(|title| format!("• {}", title))
+                    .join("\n");
+
+                channel_itunes_ext.set_summary(truncate_chars(
+                    &format!(
+                        "Latest episodes of {}:\n{}",
+                        rss_channel.title, episode_titles
+                    ),
+                    ITUNES_SUMMARY_LIMIT,
+                ));
+            }
+        }
+
+        self.channel_info = Some(self.channel_info(&playlist, &rss_channel));
+
+        rss_channel.set_link(
+            playlist
+                .webpage_url
+                .unwrap_or_else(|| self.playlist_url.to_string()),
+        );
+        rss_channel.set_generator(format!("{}/{} ({})", PKG_NAME, PKG_VERSION, PKG_HOMEPAGE));
+
+        if let Some(ref docs) = options.docs {
+            rss_channel.set_docs(docs.to_string());
+        }
+
+        // Older readers only show the standard `<image>`, so mirror the artwork into it
+        if let Some(artwork) = rss_channel
+            .itunes_ext
+            .as_ref()
+            .and_then(|itunes_ext| itunes_ext.image())
+        {
+            rss_channel.set_image(
+                RSSImageBuilder::default()
+                    .url(artwork.to_string())
+                    .title(rss_channel.title.clone())
+                    .link(rss_channel.link.clone())
+                    .build(),
+            );
+        }
+
+        summary.added = unique_items
+            .iter()
+            .filter(|item| item_id(item).is_some_and(|id| !existing_ids.contains(&id)))
+            .count();
+        summary.total = unique_items.len();
+
+        // The channel is as fresh as its newest episode
+        rss_channel.set_pub_date(
+            unique_items
+                .iter()
+                .filter(|item| item_pub_date(item).is_some())
+                .max_by_key(|item| item_pub_date(item))
+                .and_then(|item| item.pub_date().map(|pub_date| pub_date.to_string())),
+        );
+
+        if options.no_itunes {
+            rss_channel.itunes_ext = None;
+
+            for item in unique_items.iter_mut() {
+                item.itunes_ext = None;
+            }
+        }
+
+        for item in unique_items.iter_mut() {
+            if let Some(item_override) =
+                item_id(item).and_then(|id| options.item_overrides.get(&id))
+            {
+                set_custom_elements(&mut item.extensions, &item_override.elements);
+            }
+        }
+
+        rss_channel.set_items(unique_items);
+
+        let item_elements = options
+            .item_overrides
+            .values()
+            .flat_map(|item_override| &item_override.elements);
+
+        // Any declared for items which aren't in the feed are pruned below
+        for element in options.channel_elements.iter().chain(item_elements) {
+            if let Some(namespace) = self.element_namespace(options, &element.prefix) {
+                rss_channel
+                    .namespaces
+                    .insert(element.prefix.clone(), namespace);
+            }
+        }
+
+        if rss_channel
+            .items
+            .iter()
+            .any(|item| item.extensions.contains_key(PODCAST_PREFIX))
+        {
+            rss_channel
+                .namespaces
+                .insert(PODCAST_PREFIX.to_string(), PODCAST_NAMESPACE.to_string());
+        }
+
+        if rss_channel.extensions.contains_key(PLAYCASTER_PREFIX)
+            || rss_channel
+                .items
+                .iter()
+                .any(|item| item.extensions.contains_key(PLAYCASTER_PREFIX))
+        {
+            rss_channel.namespaces.insert(
+                PLAYCASTER_PREFIX.to_string(),
+                PLAYCASTER_NAMESPACE.to_string(),
+            );
+        }
+
+        if rss_channel
+            .items
+            .iter()
+            .any(|item| item.dublin_core_ext.is_some())
+        {
+            rss_channel
+                .namespaces
+                .insert("dc".to_string(), dublincore::NAMESPACE.to_string());
+        }
+
+        prune_namespaces(&mut rss_channel);
+
+        check_enclosure_urls(&rss_channel, options.strict)?;
+
+        // An invalid feed isn't written, so the items dropped from it must keep their files for the
+        // feed already on disk
+        if !unkept_items.is_empty() {
+            rss::validation::Validate::validate(&rss_channel)
+                .map_err(|error| Error::FeedValidationError(self.feed_file.clone(), error))?;
+        }
+
+        self.remove_item_files(&unkept_items, options, &mut summary)?;
+
+        if options.prune_empty_dirs && !options.skip_download {
+            self.prune_media_dir()?;
+        }
+
+        self.rss_channel = Some(rss_channel);
+
+        Ok(summary)
+    }
+
+    pub fn update(&mut self, base_url: Url, keep: Option<usize>) -> Result<UpdateSummary, Error> {
+        self.update_with_options(
+            base_url,
+            &UpdateOptions {
+                keep,
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn update_with_args(
+        &mut self,
+        base_url: Url,
+        download_limit: usize,
+        keep: Option<usize>,
+        additional_args: Vec<String>,
+    ) -> Result<UpdateSummary, Error> {
+        self.update_with_options(
+            base_url,
+            &UpdateOptions {
+                download_limit,
+                keep,
+                additional_args,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Builds the list of arguments passed to `yt-dlp` for the given options
+    fn downloader_args(&self, options: &UpdateOptions) -> Result<Vec<String>, Error> {
+        let selector = match options.remote_enclosures {
+            true => options.media_format().single_file_selector(),
+            false => options.media_format().selector,
+        };
+
+        let mut args = vec!["--format".to_string(), selector.to_string()];
+
+        if options.prefer_free_formats {
+            args.push("--prefer-free-formats".to_string());
+        }
+
+        // A limit of zero fetches the whole playlist
+        if options.download_limit > 0 {
+            args.push("--playlist-end".to_string());
+            args.push(options.download_limit.to_string());
+        }
+
+        if let Some(ref format_sort) = options.format_sort {
+            args.push("--format-sort".to_string());
+            args.push(format_sort.clone());
+        }
+
+        if let Some(ref cache_dir) = options.cache_dir {
+            args.push("--cache-dir".to_string());
+            args.push(cache_dir.to_string_lossy().to_string());
+        }
+
+        if options.no_cache_dir {
+            args.push("--no-cache-dir".to_string());
+        }
+
+        if options.embed_chapters {
+            args.push("--embed-chapters".to_string());
+        }
+
+        if options.embed_info_json {
+            args.push("--embed-info-json".to_string());
+        }
+
+        if let Some(link_file) = options.link_file {
+            args.push(link_file.downloader_arg().to_string());
+        }
+
+        if !options.sub_langs.is_empty() {
+            args.push("--write-subs".to_string());
+            args.push("--sub-langs".to_string());
+            args.push(options.sub_langs.join(","));
+            args.push("--convert-subs".to_string());
+            args.push("vtt".to_string());
+        }
+
+        if let Some(ref convert_thumbnails) = options.convert_thumbnails {
+            args.push("--convert-thumbnails".to_string());
+            args.push(convert_thumbnails.clone());
+        }
+
+        if options.embed_thumbnail {
+            args.push("--embed-thumbnail".to_string());
+        }
+
+        if options.restrict_filenames {
+            args.push("--restrict-filenames".to_string());
+        }
+
+        // Restricted names can't be derived from the ids, and downloads' timings aren't otherwise
+        // reported, so have `yt-dlp` list the files it downloads
+        if !options.skip_download && !options.remote_enclosures {
+            args.push("--print-to-file".to_string());
+            args.push(DOWNLOADED_FILE_TEMPLATE.to_string());
+            // The file name is itself an output template
+            args.push(
+                self.downloaded_files_file()
+                    .to_string_lossy()
+                    .replace('%', "%%"),
+            );
+        }
+
+        if options.write_downloaded_timestamp {
+            args.push("--no-mtime".to_string());
+        }
+
+        if !options.skip_download && !options.remote_enclosures {
+            args.push("--no-simulate".to_string());
+        }
+
+        if options.live_from_start {
+            args.push("--live-from-start".to_string());
+        }
+
+        if let Some(ref geo_bypass_country) = options.geo_bypass_country {
+            args.push("--geo-bypass-country".to_string());
+            args.push(geo_bypass_country.clone());
+        }
+
+        if let Some(sleep_interval) = options.sleep_interval {
+            args.push("--sleep-interval".to_string());
+            args.push(sleep_interval.to_string());
+        }
+
+        if let Some(max_sleep_interval) = options.max_sleep_interval {
+            args.push("--max-sleep-interval".to_string());
+            args.push(max_sleep_interval.to_string());
+        }
+
+        if let Some(sleep_requests) = options.sleep_requests {
+            args.push("--sleep-requests".to_string());
+            args.push(sleep_requests.to_string());
+        }
+
+        for match_filter in match_filters(options) {
+            args.push("--match-filter".to_string());
+            args.push(match_filter);
+        }
+
+        args.extend(options.additional_args.iter().cloned());
+
+        // NOTE: Required because `yt-dlp` prints progress to stdout and breaks YoutubeDl when `--no-simulate` is specified
+        args.push("--no-progress".to_string());
+        args.push("--no-overwrites".to_string());
+        args.push("--output".to_string());
+        args.push(
+            self.media_dir()?
+                .join("%(id)s.%(ext)s")
+                .to_string_lossy()
+                .to_string(),
+        );
+
+        Ok(args)
+    }
+
+    pub fn update_with_options(
+        &mut self,
+        base_url: Url,
+        options: &UpdateOptions,
+    ) -> Result<UpdateSummary, Error> {
+        self.update_with_downloader(base_url, options, &YtDlp)
+    }
+
+    /// Builds a one-item feed from the single video at `video_url`, which becomes the channel's link.
+    ///
+    /// The channel's title is taken from the video's uploader, or its title if it has no uploader.
+    pub fn update_single(&mut self, base_url: Url, video_url: Url) -> Result<UpdateSummary, Error> {
+        self.update_single_with_downloader(base_url, video_url, &YtDlp)
+    }
+
+    pub fn update_single_with_downloader<D: Downloader>(
+        &mut self,
+        base_url: Url,
+        video_url: Url,
+        downloader: &D,
+    ) -> Result<UpdateSummary, Error> {
+        self.playlist_url = video_url;
+
+        self.update_with_downloader(
+            base_url,
+            &UpdateOptions {
+                single_ok: true,
+                ..Default::default()
+            },
+            downloader,
+        )
+    }
+
+    /// Runs the downloader for the playlist, returning the playlist it describes
+    fn run_downloader<D: Downloader>(
+        &self,
+        options: &UpdateOptions,
+        downloader: &D,
+    ) -> Result<youtube_dl::Playlist, Error> {
+        self.run_downloader_with_args(options, downloader, self.downloader_args(options)?)
+    }
+
+    /// Runs the downloader as with `run_downloader`, with `args` in place of `downloader_args`
+    fn run_downloader_with_args<D: Downloader>(
+        &self,
+        options: &UpdateOptions,
+        downloader: &D,
+        args: Vec<String>,
+    ) -> Result<youtube_dl::Playlist, Error> {
+        let result = downloader.run(&self.playlist_url, &args)?;
+
+        trace!("{:#?}", result);
+
+        match result {
+            YoutubeDlOutput::Playlist(playlist) => Ok(*playlist),
+            YoutubeDlOutput::SingleVideo(video) if options.single_ok => {
+                Ok(playlist_from_single_video(*video))
+            }
+            YoutubeDlOutput::SingleVideo(_) => {
+                Err(Error::SingleVideoError(self.playlist_url.clone()))
+            }
+        }
+    }
+
+    /// Runs the downloader as with `run_downloader`, but only downloads the first `max_new` of the
+    /// playlist's entries which aren't in the feed yet, leaving the rest for later runs.
+    ///
+    /// The playlist is first listed flat, without extracting each video, to find which entries are
+    /// new, then only the rest are picked for the download with `--playlist-items`.
+    fn run_downloader_capped<D: Downloader>(
+        &self,
+        options: &UpdateOptions,
+        downloader: &D,
+        max_new: usize,
+    ) -> Result<youtube_dl::Playlist, Error> {
+        // Nothing's downloaded either way
+        if options.skip_download {
+            return self.run_downloader(options, downloader);
+        }
+
+        let listing_options = UpdateOptions {
+            skip_download: true,
+            ..options.clone()
+        };
+        let mut listing_args = self.downloader_args(&listing_options)?;
+        listing_args.insert(0, "--flat-playlist".to_string());
+
+        let listing = self.run_downloader_with_args(&listing_options, downloader, listing_args)?;
+
+        let existing_ids: HashSet<_> = self
+            .rss_channel
+            .iter()
+            .flat_map(|rss_channel| &rss_channel.items)
+            .filter_map(item_id)
+            .collect();
+
+        let mut playlist_items = vec![];
+        let mut new_count = 0;
+
+        for (position, video) in listing.entries.iter().flatten().enumerate() {
+            if !existing_ids.contains(&video.id) {
+                new_count += 1;
+
+                if new_count > max_new {
+                    continue;
+                }
+            }
+
+            // Entries the match filters skip are left out of the listing, so its positions only
+            // stand in for the playlist's when `yt-dlp` doesn't give the index itself
+            playlist_items.push(
+                video_playlist_index(video)
+                    .unwrap_or(position as u64 + 1)
+                    .to_string(),
+            );
+        }
+
+        if new_count <= max_new {
+            return self.run_downloader(options, downloader);
+        }
+
+        info!(
+            "Leaving {} new video(s) to be added by later runs",
+            new_count - max_new
+        );
+
+        if playlist_items.is_empty() {
+            return Ok(youtube_dl::Playlist {
+                entries: Some(vec![]),
+                ..listing
+            });
+        }
+
+        // Given first, so additional arguments can still override it
+        let mut args = self.downloader_args(options)?;
+        args.splice(
+            0..0,
+            ["--playlist-items".to_string(), playlist_items.join(",")],
+        );
+
+        self.run_downloader_with_args(options, downloader, args)
+    }
+
+    /// Fetches the playlist's metadata as `yt-dlp` reports it, without downloading any media
+    /// or changing the feed. Useful for debugging extractors.
+    pub fn fetch_metadata(&self, options: &UpdateOptions) -> Result<youtube_dl::Playlist, Error> {
+        self.fetch_metadata_with_downloader(options, &YtDlp)
+    }
+
+    pub fn fetch_metadata_with_downloader<D: Downloader>(
+        &self,
+        options: &UpdateOptions,
+        downloader: &D,
+    ) -> Result<youtube_dl::Playlist, Error> {
+        self.run_downloader(
+            &UpdateOptions {
+                skip_download: true,
+                ..options.clone()
+            },
+            downloader,
+        )
+    }
+
+    pub fn update_with_downloader<D: Downloader>(
+        &mut self,
+        base_url: Url,
+        options: &UpdateOptions,
+        downloader: &D,
+    ) -> Result<UpdateSummary, Error> {
+        self.update_with_downloader_and_transform(base_url, options, downloader, |_, item| item)
+    }
+
+    /// Updates the feed as with `update_with_options`, passing each newly built item through
+    /// `transform` along with the video it was built from, before it's added to the feed.
+    ///
+    /// This allows embedders to inject custom elements or rewrite any of the item's fields.
+    pub fn update_with_transform<F>(
+        &mut self,
+        base_url: Url,
+        options: &UpdateOptions,
+        transform: F,
+    ) -> Result<UpdateSummary, Error>
+    where
+        F: FnMut(&youtube_dl::SingleVideo, RSSItem) -> RSSItem,
+    {
+        self.update_with_downloader_and_transform(base_url, options, &YtDlp, transform)
+    }
+
+    pub fn update_with_downloader_and_transform<D, F>(
+        &mut self,
+        base_url: Url,
+        options: &UpdateOptions,
+        downloader: &D,
+        transform: F,
+    ) -> Result<UpdateSummary, Error>
+    where
+        D: Downloader,
+        F: FnMut(&youtube_dl::SingleVideo, RSSItem) -> RSSItem,
+    {
+        let _log_context = LogContext::enter(&self.feed_file);
+
+        check_keep(options.keep)?;
+        check_keep(options.keep_files)?;
+        self.check_custom_elements(options)?;
+
+        if let (0, Some(keep)) = (options.download_limit, options.keep) {
+            warn!(
+                "Downloading the whole playlist, but only the newest {} items will be kept",
+                keep
+            );
+        }
+
+        if let Some(max_age) = options.clean_stale_partials {
+            let deleted = self.clean_stale_partials(max_age)?;
+
+            if !deleted.is_empty() {
+                info!("Removed {} stale partial download(s)", deleted.len());
+            }
+        }
+
+        if skips_members_only(options) {
+            info!("Skipping any members-only and premium videos, as no cookies or credentials were given");
+        }
+
+        let media_dir = self.media_dir()?;
+
+        // Note which files are already on disk, so we can tell what this run downloaded
+        let existing_files: HashSet<PathBuf> = match std::fs::read_dir(&media_dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .collect(),
+            Err(_) => HashSet::new(),
+        };
+
+        // `yt-dlp` appends to the list, so clear out any left by an interrupted run
+        let downloaded_files_file = self.downloaded_files_file();
+        if downloaded_files_file.exists() {
+            std::fs::remove_file(&downloaded_files_file)?;
+        }
+
+        let started = std::time::Instant::now();
+        let mut playlist = match options.max_new_per_run {
+            Some(max_new) => self.run_downloader_capped(options, downloader, max_new)?,
+            None => self.run_downloader(options, downloader)?,
+        };
+        let elapsed = started.elapsed();
+        let finished = chrono::Utc::now().timestamp().max(0) as u64;
+
+        let downloaded_files = read_downloaded_files(&downloaded_files_file)?;
+        if downloaded_files_file.exists() {
+            std::fs::remove_file(&downloaded_files_file)?;
+        }
+        let download_durations = download_durations(&downloaded_files, finished);
+
+        if !options.no_dedup {
+            dedup_entries(&mut playlist);
+        }
+
+        let mut created_files: Vec<_> = match std::fs::read_dir(&media_dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| !existing_files.contains(path))
+                .sorted()
+                .collect(),
+            Err(_) => vec![],
+        };
+
+        // `yt-dlp` normally removes these itself once they're embedded, but not if embedding fails
+        if options.embed_thumbnail
+            && !options.skip_download
+            && !options
+                .additional_args
+                .iter()
+                .any(|arg| arg == "--write-thumbnail" || arg == "--write-all-thumbnails")
+        {
+            let file_stems: HashSet<_> = playlist
+                .entries
+                .iter()
+                .flatten()
+                .map(|video| self.media_file_stem(video, options, &downloaded_files))
+                .collect();
+
+            created_files.retain(|path| {
+                if !is_thumbnail_file(path, &file_stems) {
+                    return true;
+                }
+
+                debug!("Removing embedded thumbnail file: {:?}", path);
+
+                match std::fs::remove_file(path) {
+                    Ok(()) => false,
+                    Err(err) => {
+                        warn!("Couldn't remove thumbnail file: {:?}", err);
+                        true
+                    }
+                }
+            });
+        }
+
+        if options.probe_duration {
+            for video in playlist.entries.iter_mut().flatten() {
+                let path = self.media_path(
+                    &self.media_file_stem(video, options, &downloaded_files),
+                    options.media_format().of_video(video),
+                )?;
+
+                if !video_duration(video).is_zero() || !path.exists() {
+                    continue;
+                }
+
+                if let Some(duration) = downloader.probe_duration(&path) {
+                    debug!("Probed duration of {:?}: {:?}", path, duration);
+                    video.duration = Some(serde_json::Value::from(duration.as_secs_f64()));
+                }
+            }
+        }
+
+        let mut downloads = vec![];
+        let mut downloaded_ids = HashSet::new();
+
+        // With `no_dedup`, repeats are kept, but they share the first appearance's file
+        for video in playlist
+            .entries
+            .iter()
+            .flatten()
+            .filter(|video| downloaded_ids.insert(video.id.clone()))
+        {
+            let path = self.media_path(
+                &self.media_file_stem(video, options, &downloaded_files),
+                options.media_format().of_video(video),
+            )?;
+
+            let status = if options.skip_download
+                || options.remote_enclosures
+                || is_empty_entry(video, &path)
+            {
+                DownloadStatus::Skipped
+            } else if existing_files.contains(&path) {
+                DownloadStatus::Cached
+            } else if path.exists() {
+                DownloadStatus::Downloaded
+            } else {
+                DownloadStatus::Failed
+            };
+
+            let bytes = match status {
+                DownloadStatus::Downloaded | DownloadStatus::Cached => std::fs::metadata(&path)
+                    .map(|metadata| metadata.len())
+                    .unwrap_or(0),
+                _ => 0,
+            };
+
+            let elapsed = match status {
+                DownloadStatus::Downloaded => download_durations.get(video.id.as_str()).copied(),
+                _ => None,
+            };
+
+            downloads.push(DownloadResult {
+                id: video.id.clone(),
+                status,
+                bytes,
+                path,
+                elapsed,
+            });
+        }
+
+        let mut summary = self.update_with_playlist_and_transform(
+            base_url,
+            options,
+            playlist,
+            &downloaded_files,
+            transform,
+        )?;
+        summary.set_downloads(downloads);
+        summary.set_elapsed(elapsed);
+        summary.created_files = created_files;
+
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    fn get_new_video() -> youtube_dl::SingleVideo {
+        youtube_dl::SingleVideo {
+            abr: Some(129.478),
+            acodec: Some("mp4a.40.2".to_string()),
+            age_limit: Some(0),
+            album: None,
+            album_artist: None,
+            album_type: None,
+            alt_title: None,
+            artist: None,
+            asr: Some(44100.0),
+            automatic_captions: None,
+            average_rating: None,
+            categories: None,
+            channel: Some("Mighty Car Mods".to_string()),
+            channel_id: Some("UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
+            channel_url: Some("https://www.youtube.com/channel/UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
+            chapter: None,
+            chapter_id: None,
+            chapter_number: None,
             chapters: None,
             comment_count: None,
             comments: None,
@@ -492,222 +5765,6536 @@ mod test {
             track_id: None,
             track_number: None,
             upload_date: Some("20220206".to_string()),
-            uploader: Some("Mighty Car Mods".to_string()),
-            uploader_id: Some("mightycarmods".to_string()),
-            uploader_url: Some("http://www.youtube.com/user/mightycarmods".to_string()),
-            url: None,
-            vbr: Some(2282.304),
-            vcodec: Some("avc1.640028".to_string()),
-            view_count: Some(294645),
-            webpage_url: Some("https://www.youtube.com/watch?v=QWkUFkXcx9I".to_string()),
-            width: Some(1920.0),
+            uploader: Some("Mighty Car Mods".to_string()),
+            uploader_id: Some("mightycarmods".to_string()),
+            uploader_url: Some("http://www.youtube.com/user/mightycarmods".to_string()),
+            url: None,
+            vbr: Some(2282.304),
+            vcodec: Some("avc1.640028".to_string()),
+            view_count: Some(294645),
+            webpage_url: Some("https://www.youtube.com/watch?v=QWkUFkXcx9I".to_string()),
+            width: Some(1920.0),
+            ..Default::default()
+        }
+    }
+
+    fn get_duplicate_video() -> youtube_dl::SingleVideo {
+        youtube_dl::SingleVideo {
+            abr: Some(129.478),
+            acodec: Some("mp4a.40.2".to_string()),
+            age_limit: Some(0),
+            album: None,
+            album_artist: None,
+            album_type: None,
+            alt_title: None,
+            artist: None,
+            asr: Some(44100.0),
+            automatic_captions: None,
+            average_rating: None,
+            categories: None,
+            channel: Some("Mighty Car Mods".to_string()),
+            channel_id: Some("UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
+            channel_url: Some("https://www.youtube.com/channel/UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
+            chapter: None,
+            chapter_id: None,
+            chapter_number: None,
+            chapters: None,
+            comment_count: None,
+            comments: None,
+            container: None,
+            creator: None,
+            description: Some("Do you wanna make your car better, more enjoyable for car meets, off-roading, road trips or just day to day commuting? Then this simple modification is for you! \n\nAs the best 4X4 channel in the world, we'll show you exactly what you need to do to get the job done! \n\nYou can use this additional power to run a fridge, audio equipment, charging systems or even a travel oven. \n\nMERCH SHOWN IN THIS EPISODE\n\n►MCM Travel Mug\nhttps://mightycarmods.com/collections/accessories/products/travel-mug\n\n►Enamel Workshop Mug\nhttps://mightycarmods.com/collections/accessories/products/enamel-workshop-mug\n\n►Chopped Ceramic Mug\nhttps://mightycarmods.com/collections/accessories/products/chopped-mug\n\n►Insulated Drink Bottle\nhttps://mightycarmods.com/collections/accessories/products/mcm-drink-bottle\n\n►Fender Covers\nhttps://mightycarmods.com/collections/accessories/products/fender-cover-2-pack\n\n►Microfibre Cloths\nhttps://mightycarmods.com/collections/accessories/products/mighty-car-mods-microfibre-cloth-4-pack\n\n►MCM Ear Muffs\nhttps://mightycarmods.com/collections/accessories/products/ear-muffs\n\n►MCM BOOK [ULTIMATE EDITION] Autographed\nhttps://mightycarmods.com/collections/books/products/copy-of-the-cars-of-mighty-car-mods-ultimate-edition-hardcover-book\n\nMORE MERCH HERE ►http://www.mightycarmods.com/collections/\n\nBe sure to check out the MCM Facebook Page for regular Updates\n►http://www.facebook.com/mightycarmods\n\n✌\u{fe0f} JDM Air Fresheners https://mightycarmods.com/collections/accessories/products/mcm-car-air-freshener \n👕 BRAND NEW EXPLODED TURBO SHIRT\nhttps://mightycarmods.com/collections/clothing/products/exploded-turbo-shirt\nLIMITED EDITION MCM BOOK (SIGNED)\nhttps://mightycarmods.com/collections/books/products/copy-of-the-cars-of-mighty-car-mods-ultimate-edition-hardcover-book\n\n🔔 Hit the bell next to Subscribe so you don't miss a video!\n\nAlso something to note around Mighty Car Mods: we are normal guys and are not trained mechanics. We like to make interesting car mods and show you how we've gone about it, but we can't promise that anything we show you will work for your particular car, or that you won't harm yourself, someone else, your car or your warranty doing it. Please be safe, be responsible and unless you know what you're doing, do not fool around with very serious machinery just because you've seen us make it look so easy. Talk to a qualified mechanic if you are in any doubt. Some of the products featured in this video may be supplied by sponsors. For a list of our current sponsors please go to mightycarmods.com".to_string()),
+            disc_number: None,
+            dislike_count: None,
+            display_id: Some("Wqww1B9wljA".to_string()),
+            downloader_options: None,
+            duration: Some(serde_json::Value::Number(serde_json::Number::from_f64(706.0).unwrap())),
+            end_time: None,
+            episode: None,
+            episode_id: None,
+            episode_number: None,
+            ext: Some("mp4".to_string()),
+            extractor: Some("youtube".to_string()),
+            extractor_key: Some("Youtube".to_string()),
+            filesize: None,
+            filesize_approx: Some(212973334.0),
+            format: Some("137 - 1920x1080 (1080p)+140 - audio only (medium)".to_string()),
+            format_id: Some("137+140".to_string()),
+            format_note: Some("1080p+medium".to_string()),
+            formats: None,
+            fps: Some(25.0),
+            fragment_base_url: None,
+            fragments: None,
+            genre: None,
+            height: Some(1080.0),
+            http_headers: None,
+            id: "QWkUFkXcx9I".to_string(),
+            is_live: Some(false),
+            language: None,
+            language_preference: None,
+            license: None,
+            like_count: Some(16399),
+            location: None,
+            manifest_url: None,
+            no_resume: None,
+            player_url: None,
+            playlist: Some("Mighty Car Mods - Videos".to_string()),
+            playlist_id: Some("UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
+            playlist_index: Some(serde_json::Value::Number(serde_json::Number::from_f64(1.0).unwrap())),
+            playlist_title: Some("Mighty Car Mods - Videos".to_string()),
+            playlist_uploader: Some("Mighty Car Mods".to_string()),
+            playlist_uploader_id: Some("UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
+            preference: None,
+            protocol: Some(youtube_dl::Protocol::HttpsHttps),
+            quality: None,
+            release_date: None,
+            release_year: None,
+            repost_count: None,
+            requested_subtitles: None,
+            resolution: Some("1920x1080".to_string()),
+            season: None,
+            season_id: None,
+            season_number: None,
+            series: None,
+            source_preference: None,
+            start_time: None,
+            stretched_ratio: None,
+            subtitles: None,
+            tags: None,
+            tbr: Some(2411.782),
+            thumbnail: Some("https://i.ytimg.com/vi/QWkUFkXcx9I/maxresdefault.jpg".to_string()),
+            thumbnails: None,
+            timestamp: None,
+            title: Some("Everyone Should do this Simple $10 Car Mod".to_string()),
+            track: None,
+            track_id: None,
+            track_number: None,
+            upload_date: Some("20220206".to_string()),
+            uploader: Some("Mighty Car Mods".to_string()),
+            uploader_id: Some("mightycarmods".to_string()),
+            uploader_url: Some("http://www.youtube.com/user/mightycarmods".to_string()),
+            url: None,
+            vbr: Some(2282.304),
+            vcodec: Some("avc1.640028".to_string()),
+            view_count: Some(294645),
+            webpage_url: Some("https://www.youtube.com/watch?v=QWkUFkXcx9I".to_string()),
+            width: Some(1920.0),
+            ..Default::default()
+        }
+    }
+
+    fn get_playlist(entries: Vec<youtube_dl::SingleVideo>) -> youtube_dl::Playlist {
+        youtube_dl::model::Playlist {
+            entries: Some(entries),
+            extractor: Some("youtube:tab".to_string()),
+            extractor_key: Some("YoutubeTab".to_string()),
+            id: Some("UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
+            title: Some("Mighty Car Mods - Videos".to_string()),
+            uploader: Some("Mighty Car Mods".to_string()),
+            uploader_id: Some("UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
+            uploader_url: Some(
+                "https://www.youtube.com/channel/UCgJRL30YS6XFxq9Ga8W2J3A".to_string(),
+            ),
+            webpage_url: Some("https://www.youtube.com/c/mightycarmods".to_string()),
+            webpage_url_basename: Some("mightycarmods".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn get_new_channel() -> Result<super::Channel, Error> {
+        use url::Url;
+
+        super::Channel::new_with_url(
+            std::path::Path::new("mightycarmods.xml").to_path_buf(),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )
+    }
+
+    fn get_item_ids(rss_channel: &rss::Channel) -> Vec<String> {
+        rss_channel
+            .items
+            .iter()
+            .map(|item| item.guid().unwrap().value().to_string())
+            .collect()
+    }
+
+    fn get_temp_dir(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "{}-test-{}-{}",
+            crate::PKG_NAME,
+            name,
+            std::process::id()
+        ));
+
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+
+        path
+    }
+
+    /// A `Downloader` which returns canned output, recording the arguments it was run with
+    struct MockDownloader {
+        output: youtube_dl::YoutubeDlOutput,
+        files: Vec<std::path::PathBuf>,
+        reported_files: Vec<(String, std::path::PathBuf)>,
+        start_times: Vec<u64>,
+        duration: Option<std::time::Duration>,
+        version: Option<String>,
+        delay: std::time::Duration,
+        invocations: std::cell::RefCell<Vec<Vec<String>>>,
+        urls: std::cell::RefCell<Vec<url::Url>>,
+    }
+
+    impl MockDownloader {
+        fn new(output: youtube_dl::YoutubeDlOutput) -> Self {
+            Self {
+                output,
+                files: vec![],
+                reported_files: vec![],
+                start_times: vec![],
+                duration: None,
+                version: None,
+                delay: std::time::Duration::ZERO,
+                invocations: std::cell::RefCell::new(vec![]),
+                urls: std::cell::RefCell::new(vec![]),
+            }
+        }
+
+        /// Creates the given files when run, as if they were downloaded
+        fn with_files(mut self, files: Vec<std::path::PathBuf>) -> Self {
+            self.files = files;
+            self
+        }
+
+        /// Creates the given files for the videos with the given ids when run, listing them with
+        /// `--print-to-file` if asked to, as `yt-dlp` does
+        fn with_reported_files(mut self, files: Vec<(&str, std::path::PathBuf)>) -> Self {
+            self.reported_files = files
+                .into_iter()
+                .map(|(id, path)| (id.to_string(), path))
+                .collect();
+            self
+        }
+
+        /// Reports the reported files' videos as started at the given Unix timestamps, in order
+        fn with_start_times(mut self, start_times: Vec<u64>) -> Self {
+            self.start_times = start_times;
+            self
+        }
+
+        /// Takes the given time to run, as if downloading
+        fn with_delay(mut self, delay: std::time::Duration) -> Self {
+            self.delay = delay;
+            self
+        }
+
+        /// Reports the given duration when probing files
+        fn with_duration(mut self, duration: std::time::Duration) -> Self {
+            self.duration = Some(duration);
+            self
+        }
+
+        /// Reports the given version
+        fn with_version(mut self, version: &str) -> Self {
+            self.version = Some(version.to_string());
+            self
+        }
+    }
+
+    impl crate::Downloader for MockDownloader {
+        fn run(
+            &self,
+            url: &url::Url,
+            args: &[String],
+        ) -> Result<youtube_dl::YoutubeDlOutput, Error> {
+            self.invocations.borrow_mut().push(args.to_vec());
+            self.urls.borrow_mut().push(url.clone());
+
+            std::thread::sleep(self.delay);
+
+            for file in self
+                .files
+                .iter()
+                .chain(self.reported_files.iter().map(|(_, file)| file))
+            {
+                std::fs::create_dir_all(file.parent().unwrap())?;
+                std::fs::write(file, b"downloaded")?;
+            }
+
+            // Only written when there are files to list, so tests sharing a feed file don't race
+            let print_to_file = args.iter().position(|arg| arg == "--print-to-file");
+            if let (Some(index), false) = (print_to_file, self.reported_files.is_empty()) {
+                let list = self
+                    .reported_files
+                    .iter()
+                    .enumerate()
+                    .map(|(index, (id, file))| {
+                        let started = self.start_times.get(index);
+                        format!(
+                            "{}\t{}\t{}\n",
+                            id,
+                            started.map_or("NA".to_string(), u64::to_string),
+                            file.display()
+                        )
+                    })
+                    .collect::<String>();
+                std::fs::write(args[index + 2].replace("%%", "%"), list)?;
+            }
+
+            Ok(self.output.clone())
+        }
+
+        fn probe_duration(&self, _path: &std::path::Path) -> Option<std::time::Duration> {
+            self.duration
+        }
+
+        fn version(&self) -> Option<String> {
+            self.version.clone()
+        }
+    }
+
+    use crate::Error;
+
+    #[test]
+    fn test_update_new_with_playlist() -> Result<(), Error> {
+        use rss::validation::Validate;
+        use url::Url;
+
+        let playlist = youtube_dl::model::Playlist {
+            entries: Some(vec![get_new_video()]),
+            extractor: Some("youtube:tab".to_string()),
+            extractor_key: Some("YoutubeTab".to_string()),
+            id: Some("UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
+            title: Some("Mighty Car Mods - Videos".to_string()),
+            uploader: Some("Mighty Car Mods".to_string()),
+            uploader_id: Some("UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
+            uploader_url: Some(
+                "https://www.youtube.com/channel/UCgJRL30YS6XFxq9Ga8W2J3A".to_string(),
+            ),
+            webpage_url: Some("https://www.youtube.com/c/mightycarmods".to_string()),
+            webpage_url_basename: Some("mightycarmods".to_string()),
+            ..Default::default()
+        };
+
+        let mut channel = super::Channel::new_with_url(
+            std::path::Path::new("mightycarmods.xml").to_path_buf(),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost").unwrap(),
+            &super::UpdateOptions::default(),
+            playlist,
+        )?;
+        let rss_channel = channel.rss_channel.unwrap();
+        rss_channel.validate().unwrap();
+
+        assert_eq!(rss_channel.items.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_existing_with_playlist_and_truncation() -> Result<(), Error> {
+        use rss::validation::Validate;
+        use std::io::BufReader;
+        use url::Url;
+
+        let playlist = youtube_dl::model::Playlist {
+            entries: Some(vec![get_new_video(), get_duplicate_video()]),
+            extractor: Some("youtube:tab".to_string()),
+            extractor_key: Some("YoutubeTab".to_string()),
+            id: Some("UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
+            title: Some("Mighty Car Mods - Videos".to_string()),
+            uploader: Some("Mighty Car Mods".to_string()),
+            uploader_id: Some("UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
+            uploader_url: Some(
+                "https://www.youtube.com/channel/UCgJRL30YS6XFxq9Ga8W2J3A".to_string(),
+            ),
+            webpage_url: Some("https://www.youtube.com/c/mightycarmods".to_string()),
+            webpage_url_basename: Some("mightycarmods".to_string()),
+            ..Default::default()
+        };
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let reader = BufReader::new(&bytes[0..]);
+
+        let mut channel = super::Channel::new_with_reader(
+            std::path::Path::new("mightycarmods.xml").to_path_buf(),
+            reader,
+        )?;
+
+        let rss_channel = channel.rss_channel.as_ref().unwrap();
+        assert_eq!(rss_channel.items.len(), 1);
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            playlist.clone(),
+        )?;
+        let rss_channel = channel.rss_channel.as_ref().unwrap();
+        rss_channel.validate().unwrap();
+
+        assert_eq!(rss_channel.items.len(), 2);
+
+        assert_eq!(
+            rss_channel.items[0].enclosure.as_ref().unwrap().url,
+            "http://localhost:8080/mightycarmods/QWkUFkXcx9I.mp4"
+        );
+        assert_eq!(
+            rss_channel.items[1].enclosure.as_ref().unwrap().url,
+            "http://localhost:8080/mightycarmods/Wqww1B9wljA.mp4"
+        );
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                keep: Some(1),
+                ..Default::default()
+            },
+            playlist.clone(),
+        )?;
+        let rss_channel = channel.rss_channel.unwrap();
+        rss_channel.validate().unwrap();
+
+        assert_eq!(rss_channel.items.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_docs() -> Result<(), Error> {
+        use url::Url;
+
+        let mut channel = get_new_channel()?;
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![get_new_video()]),
+        )?;
+        assert_eq!(channel.rss_channel.as_ref().unwrap().docs(), None);
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost").unwrap(),
+            &super::UpdateOptions {
+                docs: Some(Url::parse("https://www.rssboard.org/rss-specification").unwrap()),
+                ..Default::default()
+            },
+            get_playlist(vec![get_new_video()]),
+        )?;
+        let rss_channel = channel.rss_channel.unwrap();
+
+        assert_eq!(
+            rss_channel.docs(),
+            Some("https://www.rssboard.org/rss-specification")
+        );
+        assert!(rss_channel
+            .to_string()
+            .contains("<docs>https://www.rssboard.org/rss-specification</docs>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_empty_feed_file() -> Result<(), Error> {
+        use rss::validation::Validate;
+        use url::Url;
+
+        let temp_dir = get_temp_dir("empty-feed");
+        let feed_file = temp_dir.join("mightycarmods.xml");
+        std::fs::File::create(&feed_file)?;
+
+        assert!(matches!(
+            super::Channel::new(feed_file.clone()),
+            Err(Error::EmptyFeedError(_))
+        ));
+
+        let mut channel = super::Channel::new_with_url(
+            feed_file,
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+        assert!(channel.rss_channel.is_none());
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![get_new_video()]),
+        )?;
+        let rss_channel = channel.rss_channel.unwrap();
+        rss_channel.validate().unwrap();
+
+        assert_eq!(rss_channel.title(), "Mighty Car Mods - Videos");
+        assert_eq!(rss_channel.items.len(), 1);
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_downloader_args_format_sort() -> Result<(), Error> {
+        let channel = get_new_channel()?;
+
+        let args = channel.downloader_args(&super::UpdateOptions::default())?;
+        assert!(!args.contains(&"--format-sort".to_string()));
+
+        let args = channel.downloader_args(&super::UpdateOptions {
+            format_sort: Some("vcodec:av01,+size".to_string()),
+            ..Default::default()
+        })?;
+
+        let position = args.iter().position(|arg| arg == "--format-sort").unwrap();
+        assert_eq!(args[position + 1], "vcodec:av01,+size");
+        assert!(args.contains(&"--format".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_from_playlists_file() -> Result<(), Error> {
+        use std::io::BufReader;
+        use url::Url;
+
+        let temp_dir = get_temp_dir("playlists-file");
+
+        let playlists_file = b"# Car channels\nhttps://www.youtube.com/c/mightycarmods/videos\n\nhttps://www.youtube.com/playlist?list=PLDBB2F7BDBD3B3B3B\n";
+        let playlist_urls = super::read_playlists(BufReader::new(&playlists_file[0..]))?;
+        assert_eq!(playlist_urls.len(), 2);
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )));
+
+        for playlist_url in playlist_urls {
+            let mut channel = super::Channel::new_with_url(
+                super::feed_file_for_playlist(&temp_dir, &playlist_url),
+                playlist_url,
+            )?;
+
+            channel.update_with_downloader(
+                Url::parse("http://localhost").unwrap(),
+                &super::UpdateOptions::default(),
+                &downloader,
+            )?;
+
+            let file = std::fs::File::create(&channel.feed_file)?;
+            channel.rss_channel.unwrap().write_to(file)?;
+        }
+
+        assert_eq!(downloader.invocations.borrow().len(), 2);
+
+        for name in ["videos.xml", "PLDBB2F7BDBD3B3B3B.xml"] {
+            let channel = super::Channel::new(temp_dir.join(name))?;
+            assert_eq!(channel.rss_channel.unwrap().items.len(), 1);
+        }
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_http_images() -> Result<(), Error> {
+        use url::Url;
+
+        let mut video = get_new_video();
+        video.thumbnail = Some("http://i.ytimg.com/vi/QWkUFkXcx9I/maxresdefault.jpg".to_string());
+
+        let mut channel = get_new_channel()?;
+        channel.update_with_playlist(
+            Url::parse("http://localhost").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![video.clone()]),
+        )?;
+        let rss_channel = channel.rss_channel.unwrap();
+
+        assert_eq!(
+            rss_channel.items[0].itunes_ext.as_ref().unwrap().image(),
+            Some("http://i.ytimg.com/vi/QWkUFkXcx9I/maxresdefault.jpg")
+        );
+
+        let mut channel = get_new_channel()?;
+        channel.update_with_playlist(
+            Url::parse("http://localhost").unwrap(),
+            &super::UpdateOptions {
+                require_https_images: true,
+                ..Default::default()
+            },
+            get_playlist(vec![video]),
+        )?;
+        let rss_channel = channel.rss_channel.unwrap();
+
+        assert_eq!(
+            rss_channel.items[0].itunes_ext.as_ref().unwrap().image(),
+            Some("https://i.ytimg.com/vi/QWkUFkXcx9I/maxresdefault.jpg")
+        );
+        assert_eq!(
+            rss_channel.itunes_ext.as_ref().unwrap().image(),
+            Some("https://i.ytimg.com/vi/QWkUFkXcx9I/maxresdefault.jpg")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_truncates_itunes_summary() -> Result<(), Error> {
+        use rss::validation::Validate;
+        use url::Url;
+
+        let mut video = get_new_video();
+        video.description = Some("🚗".repeat(5000));
+
+        let mut channel = get_new_channel()?;
+        channel.update_with_playlist(
+            Url::parse("http://localhost").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![video]),
+        )?;
+        let rss_channel = channel.rss_channel.unwrap();
+        rss_channel.validate().unwrap();
+
+        let item = &rss_channel.items[0];
+        assert_eq!(item.description().unwrap().chars().count(), 5000);
+        assert_eq!(
+            item.itunes_ext
+                .as_ref()
+                .unwrap()
+                .summary()
+                .unwrap()
+                .chars()
+                .count(),
+            4000
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_source_order() -> Result<(), Error> {
+        use url::Url;
+
+        let mut older_video = get_new_video();
+        older_video.id = "Wqww1B9wljA".to_string();
+        older_video.upload_date = Some("20220118".to_string());
+
+        let playlist = get_playlist(vec![older_video, get_new_video()]);
+
+        let mut channel = get_new_channel()?;
+        channel.update_with_playlist(
+            Url::parse("http://localhost").unwrap(),
+            &super::UpdateOptions::default(),
+            playlist.clone(),
+        )?;
+        let ids = get_item_ids(channel.rss_channel.as_ref().unwrap());
+        assert_eq!(ids, vec!["QWkUFkXcx9I", "Wqww1B9wljA"]);
+
+        let mut channel = get_new_channel()?;
+        channel.update_with_playlist(
+            Url::parse("http://localhost").unwrap(),
+            &super::UpdateOptions {
+                order: super::SourceOrder::Playlist,
+                ..Default::default()
+            },
+            playlist,
+        )?;
+        let ids = get_item_ids(channel.rss_channel.as_ref().unwrap());
+        assert_eq!(ids, vec!["Wqww1B9wljA", "QWkUFkXcx9I"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rebase() -> Result<(), Error> {
+        use std::io::BufReader;
+        use url::Url;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let reader = BufReader::new(&bytes[0..]);
+
+        let mut channel = super::Channel::new_with_reader(
+            std::path::Path::new("mightycarmods.xml").to_path_buf(),
+            reader,
+        )?;
+
+        {
+            let rss_channel = channel.rss_channel.as_mut().unwrap();
+
+            // Artwork served alongside the media, with a query to keep
+            rss_channel.items[0]
+                .itunes_ext
+                .as_mut()
+                .unwrap()
+                .set_image("http://localhost:8080/mightycarmods/Wqww1B9wljA.jpg?v=2".to_string());
+
+            // A remote enclosure, and one which isn't a valid URL at all
+            let mut remote_item = rss_channel.items[0].clone();
+            remote_item.enclosure.as_mut().unwrap().set_url(
+                "https://rr1.googlevideo.com/videoplayback?expire=1#QWkUFkXcx9I".to_string(),
+            );
+            let mut invalid_item = rss_channel.items[0].clone();
+            invalid_item
+                .enclosure
+                .as_mut()
+                .unwrap()
+                .set_url("not a url".to_string());
+            rss_channel.items.push(remote_item);
+            rss_channel.items.push(invalid_item);
+        }
+
+        let original_items = channel.rss_channel.as_ref().unwrap().items.clone();
+        let original_channel_image = channel.rss_channel.as_ref().unwrap().image.clone();
+
+        channel.rebase(Url::parse("https://cdn.example/podcasts/").unwrap())?;
+        let rss_channel = channel.rss_channel.unwrap();
+
+        assert_eq!(rss_channel.items.len(), original_items.len());
+        for (item, original_item) in rss_channel.items.iter().zip(original_items.iter()) {
+            assert_eq!(
+                item.enclosure.as_ref().unwrap().length,
+                original_item.enclosure.as_ref().unwrap().length
+            );
+            assert_eq!(item.title, original_item.title);
+            assert_eq!(item.guid, original_item.guid);
+        }
+        assert_eq!(
+            rss_channel.items[0].enclosure.as_ref().unwrap().url,
+            "https://cdn.example/podcasts/mightycarmods/Wqww1B9wljA.mp4"
+        );
+        assert_eq!(
+            rss_channel.items[0].itunes_ext.as_ref().unwrap().image(),
+            Some("https://cdn.example/podcasts/mightycarmods/Wqww1B9wljA.jpg?v=2")
+        );
+
+        // Remote enclosures, invalid URLs and artwork hosted elsewhere aren't moved
+        assert_eq!(rss_channel.items[1].enclosure, original_items[1].enclosure);
+        assert_eq!(rss_channel.items[2].enclosure, original_items[2].enclosure);
+        assert_eq!(
+            rss_channel.itunes_ext.as_ref().unwrap().image(),
+            Some("https://i.ytimg.com/vi/mJNcx4G_TTA/maxresdefault.jpg")
+        );
+        assert_eq!(rss_channel.image, original_channel_image);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_cache_dir() -> Result<(), Error> {
+        use url::Url;
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )));
+
+        let mut channel = get_new_channel()?;
+        channel.update_with_downloader(
+            Url::parse("http://localhost").unwrap(),
+            &super::UpdateOptions {
+                cache_dir: Some(std::path::Path::new("/var/cache/yt-dlp").to_path_buf()),
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+        channel.update_with_downloader(
+            Url::parse("http://localhost").unwrap(),
+            &super::UpdateOptions {
+                no_cache_dir: true,
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+
+        let invocations = downloader.invocations.borrow();
+
+        let position = invocations[0]
+            .iter()
+            .position(|arg| arg == "--cache-dir")
+            .unwrap();
+        assert_eq!(invocations[0][position + 1], "/var/cache/yt-dlp");
+        assert!(!invocations[0].contains(&"--no-cache-dir".to_string()));
+
+        assert!(invocations[1].contains(&"--no-cache-dir".to_string()));
+        assert!(!invocations[1].contains(&"--cache-dir".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_single_video() -> Result<(), Error> {
+        use rss::validation::Validate;
+        use url::Url;
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::SingleVideo(Box::new(
+            get_new_video(),
+        )));
+
+        let mut channel = get_new_channel()?;
+        assert!(matches!(
+            channel.update_with_downloader(
+                Url::parse("http://localhost").unwrap(),
+                &super::UpdateOptions::default(),
+                &downloader,
+            ),
+            Err(Error::SingleVideoError(_))
+        ));
+        assert!(channel.rss_channel.is_none());
+
+        channel.update_with_downloader(
+            Url::parse("http://localhost").unwrap(),
+            &super::UpdateOptions {
+                single_ok: true,
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+        let rss_channel = channel.rss_channel.unwrap();
+        rss_channel.validate().unwrap();
+
+        assert_eq!(rss_channel.title(), "Mighty Car Mods");
+        assert_eq!(get_item_ids(&rss_channel), vec!["QWkUFkXcx9I"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_skip_download() -> Result<(), Error> {
+        use std::io::BufReader;
+        use url::Url;
+
+        let temp_dir = get_temp_dir("skip-download");
+        let media_dir = temp_dir.join("mightycarmods");
+        std::fs::create_dir_all(&media_dir)?;
+        std::fs::write(media_dir.join("Wqww1B9wljA.mp4"), b"")?;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let mut channel = super::Channel::new_with_reader(
+            temp_dir.join("mightycarmods.xml"),
+            BufReader::new(&bytes[0..]),
+        )?;
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )));
+
+        channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                keep: Some(1),
+                skip_download: true,
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+
+        assert!(!downloader.invocations.borrow()[0].contains(&"--no-simulate".to_string()));
+
+        let rss_channel = channel.rss_channel.as_ref().unwrap();
+        assert_eq!(get_item_ids(rss_channel), vec!["QWkUFkXcx9I"]);
+
+        // The feed is still written
+        channel.write(&super::WriteOptions::default())?;
+        let written = rss::Channel::read_from(BufReader::new(std::fs::File::open(
+            temp_dir.join("mightycarmods.xml"),
+        )?))
+        .unwrap();
+        assert_eq!(get_item_ids(&written), vec!["QWkUFkXcx9I"]);
+
+        // No media files were created or removed
+        let media_files: Vec<_> = std::fs::read_dir(&media_dir)?.collect();
+        assert_eq!(media_files.len(), 1);
+        assert!(media_dir.join("Wqww1B9wljA.mp4").exists());
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_download_results() -> Result<(), Error> {
+        use crate::DownloadStatus;
+        use url::Url;
+
+        let temp_dir = get_temp_dir("download-results");
+        let media_dir = temp_dir.join("mightycarmods");
+        std::fs::create_dir_all(&media_dir)?;
+        std::fs::write(media_dir.join("Wqww1B9wljA.mp4"), b"cached")?;
+
+        let mut cached_video = get_new_video();
+        cached_video.id = "Wqww1B9wljA".to_string();
+
+        let mut skipped_video = get_new_video();
+        skipped_video.id = "UUgJRL30YS6X".to_string();
+        skipped_video.duration = None;
+
+        let mut failed_video = get_new_video();
+        failed_video.id = "mJNcx4G_TTA".to_string();
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![
+                get_new_video(),
+                cached_video,
+                skipped_video,
+                failed_video,
+            ]),
+        )))
+        .with_files(vec![media_dir.join("QWkUFkXcx9I.mp4")]);
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+        let summary = channel.update_with_downloader(
+            Url::parse("http://localhost").unwrap(),
+            &super::UpdateOptions::default(),
+            &downloader,
+        )?;
+
+        let statuses: Vec<_> = summary
+            .downloads
+            .iter()
+            .map(|download| (download.id.as_str(), download.status, download.bytes))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("QWkUFkXcx9I", DownloadStatus::Downloaded, 10),
+                ("Wqww1B9wljA", DownloadStatus::Cached, 6),
+                ("UUgJRL30YS6X", DownloadStatus::Skipped, 0),
+                ("mJNcx4G_TTA", DownloadStatus::Failed, 0),
+            ]
+        );
+        assert_eq!(
+            (
+                summary.downloaded,
+                summary.cached,
+                summary.skipped,
+                summary.failed
+            ),
+            (1, 1, 1, 1)
+        );
+        assert_eq!((summary.added, summary.removed, summary.total), (4, 0, 4));
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_downloader_args_embed_chapters() -> Result<(), Error> {
+        let channel = get_new_channel()?;
+
+        let args = channel.downloader_args(&super::UpdateOptions::default())?;
+        assert!(!args.contains(&"--embed-chapters".to_string()));
+
+        let args = channel.downloader_args(&super::UpdateOptions {
+            embed_chapters: true,
+            ..Default::default()
+        })?;
+        assert!(args.contains(&"--embed-chapters".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_downloader_args_embed_info_json() -> Result<(), Error> {
+        let channel = get_new_channel()?;
+
+        let args = channel.downloader_args(&super::UpdateOptions::default())?;
+        assert!(!args.contains(&"--embed-info-json".to_string()));
+
+        let args = channel.downloader_args(&super::UpdateOptions {
+            embed_info_json: true,
+            ..Default::default()
+        })?;
+        assert!(args.contains(&"--embed-info-json".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_link_file() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("link-file");
+        let media_dir = temp_dir.join("mightycarmods");
+
+        let options = super::UpdateOptions {
+            link_file: Some(super::LinkFileFormat::Url),
+            keep: Some(1),
+            ..Default::default()
+        };
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )))
+        .with_files(vec![
+            media_dir.join("QWkUFkXcx9I.mp4"),
+            media_dir.join("QWkUFkXcx9I.url"),
+        ]);
+
+        channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &options,
+            &downloader,
+        )?;
+
+        assert!(downloader.invocations.borrow()[0].contains(&"--write-url-link".to_string()));
+        assert!(media_dir.join("QWkUFkXcx9I.url").exists());
+
+        // A newer video pushes the first out of the feed, taking its link file with it
+        let newer_video = youtube_dl::SingleVideo {
+            id: "Wqww1B9wljA".to_string(),
+            upload_date: Some("20220301".to_string()),
+            ..get_new_video()
+        };
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![newer_video]),
+        )))
+        .with_files(vec![
+            media_dir.join("Wqww1B9wljA.mp4"),
+            media_dir.join("Wqww1B9wljA.url"),
+        ]);
+
+        let summary = channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &options,
+            &downloader,
+        )?;
+
+        assert_eq!(
+            summary.deleted_files,
+            vec![
+                media_dir.join("QWkUFkXcx9I.mp4"),
+                media_dir.join("QWkUFkXcx9I.url")
+            ]
+        );
+        assert!(!media_dir.join("QWkUFkXcx9I.url").exists());
+        assert!(media_dir.join("Wqww1B9wljA.url").exists());
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_embed_thumbnail() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("embed-thumbnail");
+        let media_dir = temp_dir.join("mightycarmods");
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )))
+        .with_files(vec![
+            media_dir.join("QWkUFkXcx9I.mp4"),
+            media_dir.join("QWkUFkXcx9I.webp"),
+        ]);
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+
+        let summary = channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                embed_thumbnail: true,
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+
+        let args = &downloader.invocations.borrow()[0];
+        assert!(args.contains(&"--embed-thumbnail".to_string()));
+        assert!(!args.contains(&"--write-thumbnail".to_string()));
+
+        assert!(media_dir.join("QWkUFkXcx9I.mp4").exists());
+        assert!(!media_dir.join("QWkUFkXcx9I.webp").exists());
+        assert_eq!(
+            summary.created_files,
+            vec![media_dir.join("QWkUFkXcx9I.mp4")]
+        );
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_downloaded_timestamp() -> Result<(), Error> {
+        use chrono::SubsecRound;
+        use url::Url;
+
+        let temp_dir = get_temp_dir("downloaded-timestamp");
+        let media_dir = temp_dir.join("mightycarmods");
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )))
+        .with_files(vec![media_dir.join("QWkUFkXcx9I.mp4")]);
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+
+        // The element only has whole seconds, and file times can lag the clock slightly
+        let before = chrono::Utc::now().trunc_subsecs(0) - chrono::Duration::seconds(1);
+
+        channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                write_downloaded_timestamp: true,
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+
+        assert!(downloader.invocations.borrow()[0].contains(&"--no-mtime".to_string()));
+
+        let rss_channel = channel.rss_channel.as_ref().unwrap();
+        assert_eq!(
+            rss_channel.namespaces.get("playcaster").map(String::as_str),
+            Some("https://github.com/ticky/playcaster/namespace")
+        );
+
+        let downloaded = rss_channel.items[0].extensions()["playcaster"]["downloaded"][0]
+            .value()
+            .unwrap();
+        let downloaded = chrono::DateTime::parse_from_rfc2822(downloaded).unwrap();
+        assert!(downloaded >= before && downloaded <= chrono::Utc::now());
+
+        // Distinct from when the video was published
+        assert_ne!(
+            rss_channel.items[0].pub_date(),
+            Some(downloaded.to_rfc2822().as_str())
+        );
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_restrict_filenames() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("restrict-filenames");
+        let media_dir = temp_dir.join("mightycarmods");
+
+        // `yt-dlp` transliterates the accented letter, so the name can't be derived from the id
+        let video = youtube_dl::SingleVideo {
+            id: "Épisode 5: Rotary".to_string(),
+            upload_date: Some("20220101".to_string()),
+            ..get_new_video()
+        };
+        let restricted_file = media_dir.join("Episode_5_-_Rotary.mp4");
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![video.clone()]),
+        )))
+        .with_reported_files(vec![("Épisode 5: Rotary", restricted_file.clone())]);
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+
+        let options = super::UpdateOptions {
+            restrict_filenames: true,
+            ..Default::default()
+        };
+
+        let summary = channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &options,
+            &downloader,
+        )?;
+
+        assert!(downloader.invocations.borrow()[0].contains(&"--restrict-filenames".to_string()));
+        assert_eq!(summary.downloads[0].path, restricted_file);
+        assert_eq!(
+            summary.downloads[0].status,
+            super::DownloadStatus::Downloaded
+        );
+        // The list `yt-dlp` wrote was cleaned up
+        assert!(!channel.downloaded_files_file().exists());
+
+        let item = &channel.rss_channel.as_ref().unwrap().items[0];
+        assert_eq!(
+            item.enclosure().unwrap().url(),
+            "http://localhost:8080/mightycarmods/Episode_5_-_Rotary.mp4"
+        );
+        assert_eq!(
+            item.extensions["playcaster"]["id"][0].value(),
+            Some("Épisode 5: Rotary")
+        );
+        assert!(channel.contains("Épisode 5: Rotary"));
+
+        // Later runs find the file from the feed, without `yt-dlp` listing it again
+        let cached_downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(
+            Box::new(get_playlist(vec![get_new_video(), video])),
+        ))
+        .with_files(vec![media_dir.join("QWkUFkXcx9I.mp4")]);
+
+        let summary = channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                keep: Some(1),
+                ..options
+            },
+            &cached_downloader,
+        )?;
+
+        assert_eq!(summary.downloads[1].path, restricted_file);
+        assert_eq!(summary.downloads[1].status, super::DownloadStatus::Cached);
+        assert_eq!((summary.added, summary.removed), (1, 1));
+
+        // And `keep` deletes the restricted file along with its item
+        assert_eq!(
+            get_item_ids(channel.rss_channel.as_ref().unwrap()),
+            vec!["QWkUFkXcx9I"]
+        );
+        assert!(!restricted_file.exists());
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_source() -> Result<(), Error> {
+        use url::Url;
+
+        let mut channel = get_new_channel()?;
+
+        let video = youtube_dl::SingleVideo {
+            playlist_title: Some("Rotary builds".to_string()),
+            ..get_new_video()
+        };
+
+        let other_video = youtube_dl::SingleVideo {
+            id: "Wqww1B9wljA".to_string(),
+            ..get_new_video()
+        };
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                with_source: true,
+                ..Default::default()
+            },
+            get_playlist(vec![video, other_video]),
+        )?;
+
+        let items = &channel.rss_channel.as_ref().unwrap().items;
+
+        let source = items[0].source().unwrap();
+        assert_eq!(source.url(), "https://www.youtube.com/c/mightycarmods");
+        assert_eq!(source.title(), Some("Rotary builds"));
+
+        // Falls back to the playlist's own title
+        let source = items[1].source().unwrap();
+        assert_eq!(source.title(), Some("Mighty Car Mods - Videos"));
+
+        let mut channel = get_new_channel()?;
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &Default::default(),
+            get_playlist(vec![get_new_video()]),
+        )?;
+        assert!(channel.rss_channel.unwrap().items[0].source().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_downloader_args_convert_thumbnails() -> Result<(), Error> {
+        let channel = get_new_channel()?;
+
+        let args = channel.downloader_args(&super::UpdateOptions::default())?;
+        assert!(!args.contains(&"--convert-thumbnails".to_string()));
+
+        let args = channel.downloader_args(&super::UpdateOptions {
+            convert_thumbnails: Some("jpg".to_string()),
+            ..Default::default()
+        })?;
+        let position = args
+            .iter()
+            .position(|arg| arg == "--convert-thumbnails")
+            .unwrap();
+        assert_eq!(args[position + 1], "jpg");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_downloader_args_zero_limit() -> Result<(), Error> {
+        let channel = get_new_channel()?;
+
+        let args = channel.downloader_args(&super::UpdateOptions::default())?;
+        let position = args.iter().position(|arg| arg == "--playlist-end").unwrap();
+        assert_eq!(args[position + 1], "30");
+
+        let args = channel.downloader_args(&super::UpdateOptions {
+            download_limit: 0,
+            ..Default::default()
+        })?;
+        assert!(!args.contains(&"--playlist-end".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_zero_keep() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("zero-keep");
+        let media_dir = temp_dir.join("mightycarmods");
+        std::fs::create_dir_all(&media_dir)?;
+        std::fs::write(media_dir.join("QWkUFkXcx9I.mp4"), b"")?;
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )));
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+        assert!(matches!(
+            channel.update_with_downloader(
+                Url::parse("http://localhost").unwrap(),
+                &super::UpdateOptions {
+                    keep: Some(0),
+                    ..Default::default()
+                },
+                &downloader,
+            ),
+            Err(Error::InvalidKeepLimit(0))
+        ));
+
+        // Nothing was downloaded or deleted
+        assert!(downloader.invocations.borrow().is_empty());
+        assert!(media_dir.join("QWkUFkXcx9I.mp4").exists());
+        assert!(channel.rss_channel.is_none());
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_summary_from_episodes() -> Result<(), Error> {
+        use std::io::BufReader;
+        use url::Url;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let mut channel = super::Channel::new_with_reader(
+            std::path::Path::new("mightycarmods.xml").to_path_buf(),
+            BufReader::new(&bytes[0..]),
+        )?;
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![get_new_video()]),
+        )?;
+        let summary = channel
+            .rss_channel
+            .as_ref()
+            .unwrap()
+            .itunes_ext
+            .as_ref()
+            .unwrap()
+            .summary()
+            .unwrap()
+            .to_string();
+        assert!(summary.starts_with("Mighty Car Mods is an independent automotive series"));
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                summary_from_episodes: Some(1),
+                ..Default::default()
+            },
+            get_playlist(vec![get_new_video()]),
+        )?;
+        let summary = channel
+            .rss_channel
+            .as_ref()
+            .unwrap()
+            .itunes_ext
+            .as_ref()
+            .unwrap()
+            .summary()
+            .unwrap()
+            .to_string();
+
+        assert!(summary.contains("Everyone Should do this Simple $10 Car Mod"));
+        assert!(!summary.contains("Trying to Guess Marty's New Car (Blindfolded!)"));
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                summary_from_episodes: Some(2),
+                ..Default::default()
+            },
+            get_playlist(vec![get_new_video()]),
+        )?;
+        let summary = channel
+            .rss_channel
+            .as_ref()
+            .unwrap()
+            .itunes_ext
+            .as_ref()
+            .unwrap()
+            .summary()
+            .unwrap()
+            .to_string();
+
+        assert!(summary.contains("Everyone Should do this Simple $10 Car Mod"));
+        assert!(summary.contains("Trying to Guess Marty's New Car (Blindfolded!)"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_no_itunes() -> Result<(), Error> {
+        use rss::validation::Validate;
+        use std::io::BufReader;
+        use url::Url;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let mut channel = super::Channel::new_with_reader(
+            std::path::Path::new("mightycarmods.xml").to_path_buf(),
+            BufReader::new(&bytes[0..]),
+        )?;
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![get_new_video()]),
+        )?;
+        let output = channel.rss_channel.as_ref().unwrap().to_string();
+        assert!(output.contains("xmlns:itunes="));
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                no_itunes: true,
+                ..Default::default()
+            },
+            get_playlist(vec![get_new_video()]),
+        )?;
+        let rss_channel = channel.rss_channel.unwrap();
+        rss_channel.validate().unwrap();
+
+        let output = rss_channel.to_string();
+        assert!(!output.contains("xmlns:itunes="));
+        assert!(!output.contains("<itunes:"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_item() -> Result<(), Error> {
+        use std::io::BufReader;
+
+        let temp_dir = get_temp_dir("remove-item");
+        let media_dir = temp_dir.join("mightycarmods");
+        std::fs::create_dir_all(&media_dir)?;
+        let media_file = media_dir.join("Wqww1B9wljA.mp4");
+        std::fs::write(&media_file, b"downloaded")?;
+        let subtitles_file = media_dir.join("Wqww1B9wljA.en.vtt");
+        std::fs::write(&subtitles_file, b"WEBVTT")?;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let read_channel = || {
+            super::Channel::new_with_reader(
+                temp_dir.join("mightycarmods.xml"),
+                BufReader::new(&bytes[0..]),
+            )
+        };
+        let mut channel = read_channel()?;
+
+        assert_eq!(channel.remove_item("not-an-id", true, None)?, None);
+        assert_eq!(channel.rss_channel.as_ref().unwrap().items.len(), 1);
+
+        // The media file and its sidecars go to the trash, if there is one
+        let trash_dir = temp_dir.join("trash");
+        assert_eq!(
+            channel.remove_item("Wqww1B9wljA", true, Some(&trash_dir))?,
+            Some(vec![media_file.clone(), subtitles_file.clone()])
+        );
+        assert!(channel.rss_channel.as_ref().unwrap().items.is_empty());
+        assert!(!media_file.exists() && !subtitles_file.exists());
+        assert!(trash_dir.join("Wqww1B9wljA.mp4").exists());
+        assert!(trash_dir.join("Wqww1B9wljA.en.vtt").exists());
+
+        assert_eq!(channel.remove_item("Wqww1B9wljA", true, None)?, None);
+
+        // Otherwise they're deleted
+        std::fs::write(&media_file, b"downloaded")?;
+        std::fs::write(&subtitles_file, b"WEBVTT")?;
+
+        let mut channel = read_channel()?;
+        assert_eq!(
+            channel.remove_item("Wqww1B9wljA", true, None)?,
+            Some(vec![media_file.clone(), subtitles_file.clone()])
+        );
+        assert!(std::fs::read_dir(&media_dir)?.next().is_none());
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_contains() -> Result<(), Error> {
+        use std::io::BufReader;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let channel = super::Channel::new_with_reader(
+            std::path::Path::new("mightycarmods.xml").to_path_buf(),
+            BufReader::new(&bytes[0..]),
+        )?;
+
+        assert!(channel.contains("Wqww1B9wljA"));
+        assert!(!channel.contains("not-an-id"));
+
+        assert!(!get_new_channel()?.contains("Wqww1B9wljA"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_transform() -> Result<(), Error> {
+        use url::Url;
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )));
+
+        let mut channel = get_new_channel()?;
+        let mut transformed_ids = vec![];
+
+        channel.update_with_downloader_and_transform(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                skip_download: true,
+                ..Default::default()
+            },
+            &downloader,
+            |video, mut item| {
+                transformed_ids.push(video.id.clone());
+                item.set_title(format!(
+                    "{} (transformed)",
+                    item.title().unwrap_or_default()
+                ));
+                item
+            },
+        )?;
+
+        assert_eq!(transformed_ids, vec!["QWkUFkXcx9I"]);
+
+        let rss_channel = channel.rss_channel.unwrap();
+        assert!(!rss_channel.items.is_empty());
+        for item in &rss_channel.items {
+            assert!(item.title().unwrap().ends_with(" (transformed)"));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_enclosure_length() -> Result<(), Error> {
+        let temp_dir = get_temp_dir("enclosure-length");
+        let item_path = temp_dir.join("QWkUFkXcx9I.mp4");
+
+        let mut video = get_new_video();
+        video.filesize = None;
+        video.filesize_approx = Some(f64::NAN);
+        assert_eq!(super::enclosure_length(&video, &item_path), 0);
+
+        std::fs::write(&item_path, b"downloaded")?;
+        assert_eq!(super::enclosure_length(&video, &item_path), 10);
+
+        video.filesize_approx = Some(-1.0);
+        assert_eq!(super::enclosure_length(&video, &item_path), 10);
+
+        video.filesize_approx = Some(1e30);
+        assert_eq!(super::enclosure_length(&video, &item_path), u64::MAX);
+
+        video.filesize = Some(-5);
+        video.filesize_approx = Some(1234.5);
+        assert_eq!(super::enclosure_length(&video, &item_path), 1234);
+
+        video.filesize = Some(4321);
+        assert_eq!(super::enclosure_length(&video, &item_path), 4321);
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_enclosure_length_segmented() -> Result<(), Error> {
+        let temp_dir = get_temp_dir("enclosure-length-segmented");
+        let item_path = temp_dir.join("QWkUFkXcx9I.mp4");
+
+        let mut video = get_new_video();
+        video.protocol = Some(youtube_dl::model::Protocol::HttpDashSegmentsHttps);
+        video.filesize = None;
+        video.filesize_approx = Some(1234.0);
+
+        // Without a merged file there's nothing better than the estimate
+        assert_eq!(super::enclosure_length(&video, &item_path), 1234);
+
+        std::fs::write(&item_path, b"merged dash")?;
+        assert_eq!(super::enclosure_length(&video, &item_path), 11);
+
+        video.filesize = Some(4321);
+        assert_eq!(super::enclosure_length(&video, &item_path), 11);
+
+        video.protocol = Some(youtube_dl::model::Protocol::M3U8Native);
+        video.filesize = None;
+        video.filesize_approx = None;
+        assert_eq!(super::enclosure_length(&video, &item_path), 11);
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_channel_summary_json() -> Result<(), Error> {
+        use url::Url;
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )));
+
+        let mut channel = get_new_channel()?;
+
+        let mut summary = channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                skip_download: true,
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+
+        // Timings vary from run to run
+        summary.elapsed = 0.0;
+
+        let channel_summary = super::ChannelSummary {
+            feed_file: channel.feed_file.clone(),
+            playlist_url: channel.playlist_url.clone(),
+            summary,
+        };
+
+        assert_eq!(
+            serde_json::to_value(vec![channel_summary]).unwrap(),
+            serde_json::json!([{
+                "feed_file": "mightycarmods.xml",
+                "playlist_url": "https://www.youtube.com/c/mightycarmods",
+                "added": 1,
+                "removed": 0,
+                "total": 1,
+                "downloaded": 0,
+                "cached": 0,
+                "skipped": 1,
+                "failed": 0,
+                "downloads": [{
+                    "id": "QWkUFkXcx9I",
+                    "status": "skipped",
+                    "bytes": 0,
+                    "path": "mightycarmods/QWkUFkXcx9I.mp4",
+                    "elapsed": null,
+                }],
+                "pending_deletions": [],
+                "invalid_enclosures": [],
+                "elapsed": 0.0,
+                "avg_speed": 0.0,
+                "created_files": [],
+                "deleted_files": [],
+            }])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_pub_date_from_timestamp() -> Result<(), Error> {
+        use url::Url;
+
+        let mut video = get_new_video();
+        video.upload_date = Some("20220126".to_string());
+        video.timestamp = Some(1643217143.0);
+
+        let mut channel = get_new_channel()?;
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![video]),
+        )?;
+
+        assert_eq!(
+            channel.rss_channel.unwrap().items[0].pub_date(),
+            Some("Wed, 26 Jan 2022 17:12:23 +0000")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_probe_duration() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("probe-duration");
+        let feed_file = temp_dir.join("mightycarmods.xml");
+
+        let mut video = get_new_video();
+        video.duration = None;
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![video]),
+        )))
+        .with_files(vec![temp_dir.join("mightycarmods/QWkUFkXcx9I.mp4")])
+        .with_duration(std::time::Duration::from_secs(754));
+
+        let mut channel = super::Channel::new_with_url(
+            feed_file.clone(),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+
+        // Without probing, the only entry has no duration
+        channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            &downloader,
+        )?;
+        assert_eq!(
+            channel.rss_channel.as_ref().unwrap().items[0]
+                .itunes_ext()
+                .and_then(|itunes_ext| itunes_ext.duration()),
+            Some("00:00:00")
+        );
+
+        let summary = channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                probe_duration: true,
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+
+        assert_eq!(summary.downloads[0].status, super::DownloadStatus::Cached);
+
+        let rss_channel = channel.rss_channel.unwrap();
+        assert_eq!(
+            rss_channel.items[0]
+                .itunes_ext()
+                .and_then(|itunes_ext| itunes_ext.duration()),
+            Some("00:12:34")
+        );
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_to_with_encoding() -> Result<(), Error> {
+        use std::io::BufReader;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let channel = super::Channel::new_with_reader(
+            std::path::Path::new("mightycarmods.xml").to_path_buf(),
+            BufReader::new(&bytes[0..]),
+        )?;
+
+        let mut output = vec![];
+        channel.write_to(&mut output, &super::WriteOptions::default())?;
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss"));
+        assert_eq!(output.matches("<?xml").count(), 1);
+
+        let mut output = vec![];
+        channel.write_to(
+            &mut output,
+            &super::WriteOptions {
+                pretty: false,
+                encoding: "windows-1252".to_string(),
+                ..Default::default()
+            },
+        )?;
+        assert!(output.starts_with(b"<?xml version=\"1.0\" encoding=\"windows-1252\"?><rss"));
+
+        // Characters outside the encoding survive as references in regular text
+        let parsed = rss::Channel::read_from(BufReader::new(&output[..]))?;
+        assert_eq!(
+            parsed.items[0].itunes_ext,
+            channel.rss_channel.as_ref().unwrap().items[0].itunes_ext
+        );
+
+        assert!(matches!(
+            channel.write_to(
+                vec![],
+                &super::WriteOptions {
+                    encoding: "UTF-16".to_string(),
+                    ..Default::default()
+                },
+            ),
+            Err(Error::EncodingError(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_sync_title() -> Result<(), Error> {
+        use url::Url;
+
+        let mut channel = get_new_channel()?;
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![get_new_video()]),
+        )?;
+        let old_title = channel.rss_channel.as_ref().unwrap().title.clone();
+
+        let mut renamed_playlist = get_playlist(vec![get_new_video()]);
+        renamed_playlist.title = Some("Mighty Car Mods (Renamed)".to_string());
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            renamed_playlist.clone(),
+        )?;
+        assert_eq!(channel.rss_channel.as_ref().unwrap().title, old_title);
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                sync_title: true,
+                ..Default::default()
+            },
+            renamed_playlist,
+        )?;
+
+        let rss_channel = channel.rss_channel.unwrap();
+        assert_eq!(rss_channel.title, "Mighty Car Mods (Renamed)");
+        assert_eq!(
+            rss_channel.description,
+            "playcaster podcast feed for Mighty Car Mods (Renamed)"
+        );
+
+        // The author is the playlist's uploader, which didn't change
+        let itunes_ext = rss_channel.itunes_ext.unwrap();
+        assert_eq!(itunes_ext.author(), Some("Mighty Car Mods"));
+        assert_eq!(itunes_ext.subtitle(), Some("Mighty Car Mods (Renamed)"));
+        assert_eq!(
+            itunes_ext.summary(),
+            Some("playcaster podcast feed for Mighty Car Mods (Renamed)")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_channel_pub_date() -> Result<(), Error> {
+        use std::io::BufReader;
+        use url::Url;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let mut channel = super::Channel::new_with_reader(
+            std::path::Path::new("mightycarmods.xml").to_path_buf(),
+            BufReader::new(&bytes[0..]),
+        )?;
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![get_new_video()]),
+        )?;
+
+        let rss_channel = channel.rss_channel.unwrap();
+        let newest_pub_date = rss_channel
+            .items
+            .iter()
+            .filter_map(|item| item.pub_date())
+            .max_by_key(|pub_date| chrono::DateTime::parse_from_rfc2822(pub_date).unwrap())
+            .unwrap();
+        assert_eq!(rss_channel.pub_date(), Some(newest_pub_date));
+
+        let mut channel = get_new_channel()?;
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![]),
+        )?;
+
+        assert_eq!(channel.rss_channel.unwrap().pub_date(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_without_durations_with_files() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("without-durations");
+
+        let mut first_video = get_new_video();
+        first_video.duration = None;
+        let mut second_video = get_new_video();
+        second_video.id = "Wqww1B9wljA".to_string();
+        second_video.duration = None;
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![first_video, second_video]),
+        )))
+        .with_files(vec![
+            temp_dir.join("mightycarmods/QWkUFkXcx9I.mp4"),
+            temp_dir.join("mightycarmods/Wqww1B9wljA.mp4"),
+        ]);
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+
+        let summary = channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            &downloader,
+        )?;
+
+        assert_eq!(summary.total, 2);
+        assert_eq!(summary.downloaded, 2);
+        assert_eq!(summary.skipped, 0);
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_categories() -> Result<(), Error> {
+        use crate::categories::Category;
+        use url::Url;
+
+        let automotive: Category = "Leisure/Automotive".parse().unwrap();
+        assert!(automotive.is_valid());
+        assert!("Technology".parse::<Category>().unwrap().is_valid());
+        assert!(!"Leisure/Knitting".parse::<Category>().unwrap().is_valid());
+
+        let unknown: Category = "Cars".parse().unwrap();
+        assert!(!unknown.is_valid());
+
+        let mut channel = get_new_channel()?;
+
+        // Unknown categories only warn by default
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                categories: vec![automotive.clone(), unknown.clone()],
+                ..Default::default()
+            },
+            get_playlist(vec![get_new_video()]),
+        )?;
+
+        let categories = channel
+            .rss_channel
+            .as_ref()
+            .unwrap()
+            .itunes_ext()
+            .unwrap()
+            .categories();
+        assert_eq!(categories.len(), 2);
+        assert_eq!(categories[0].text(), "Leisure");
+        assert_eq!(
+            categories[0]
+                .subcategory()
+                .map(|subcategory| subcategory.text()),
+            Some("Automotive")
+        );
+        assert_eq!(categories[1].text(), "Cars");
+
+        let result = channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                categories: vec![automotive, unknown.clone()],
+                strict: true,
+                ..Default::default()
+            },
+            get_playlist(vec![get_new_video()]),
+        );
+        assert!(matches!(result, Err(Error::CategoryError(category)) if category == unknown));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_guid_from_enclosure() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("guid-from-enclosure");
+
+        let mut old_video = get_new_video();
+        old_video.id = "Wqww1B9wljA".to_string();
+        old_video.upload_date = Some("20220118".to_string());
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video(), old_video]),
+        )))
+        .with_files(vec![
+            temp_dir.join("mightycarmods/QWkUFkXcx9I.mp4"),
+            temp_dir.join("mightycarmods/Wqww1B9wljA.mp4"),
+        ]);
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+
+        let options = super::UpdateOptions {
+            guid_from_enclosure: true,
+            ..Default::default()
+        };
+
+        channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &options,
+            &downloader,
+        )?;
+
+        for item in &channel.rss_channel.as_ref().unwrap().items {
+            let guid = item.guid().unwrap();
+            assert_eq!(guid.value(), item.enclosure().unwrap().url());
+            assert!(!guid.is_permalink());
+        }
+        assert!(channel.contains("Wqww1B9wljA"));
+
+        // Updating again doesn't duplicate the items, and `keep` still finds the old file
+        let summary = channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                keep: Some(1),
+                ..options
+            },
+            &downloader,
+        )?;
+
+        assert_eq!(summary.added, 0);
+        assert_eq!(summary.removed, 1);
+        assert_eq!(
+            get_item_ids(channel.rss_channel.as_ref().unwrap()),
+            vec!["http://localhost:8080/mightycarmods/QWkUFkXcx9I.mp4"]
+        );
+        assert!(temp_dir.join("mightycarmods/QWkUFkXcx9I.mp4").exists());
+        assert!(!temp_dir.join("mightycarmods/Wqww1B9wljA.mp4").exists());
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_multiple_formats() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("multiple-formats");
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("feed.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![get_new_video()]),
+        )?;
+
+        channel.write(&super::WriteOptions {
+            formats: vec![super::OutputFormat::Rss, super::OutputFormat::Jsonfeed],
+            ..Default::default()
+        })?;
+
+        let rss_channel = rss::Channel::read_from(std::io::BufReader::new(std::fs::File::open(
+            temp_dir.join("feed.xml"),
+        )?))?;
+        let json_feed: serde_json::Value =
+            serde_json::from_reader(std::fs::File::open(temp_dir.join("feed.json"))?).unwrap();
+
+        assert_eq!(json_feed["version"], "https://jsonfeed.org/version/1.1");
+        assert_eq!(json_feed["title"], rss_channel.title());
+
+        let json_items = json_feed["items"].as_array().unwrap();
+        assert_eq!(json_items.len(), rss_channel.items.len());
+
+        for (json_item, item) in json_items.iter().zip(&rss_channel.items) {
+            assert_eq!(json_item["id"], item.guid().unwrap().value());
+            assert_eq!(json_item["title"], item.title().unwrap());
+
+            let attachment = &json_item["attachments"][0];
+            assert_eq!(attachment["url"], item.enclosure().unwrap().url());
+            assert_eq!(attachment["mime_type"], "video/mp4");
+        }
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_channels_with_validation_error() -> Result<(), Error> {
+        use url::Url;
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )));
+
+        let channels = || -> Result<Vec<super::Channel>, Error> {
+            Ok(vec![
+                super::Channel::new_with_url(
+                    "invalid.xml".into(),
+                    Url::parse("https://www.youtube.com/c/invalid").unwrap(),
+                )?,
+                super::Channel::new_with_url(
+                    "valid.xml".into(),
+                    Url::parse("https://www.youtube.com/c/valid").unwrap(),
+                )?,
+            ])
+        };
+
+        let update = |mut channel: super::Channel| {
+            let invalid = channel.feed_file == std::path::Path::new("invalid.xml");
+
+            channel.update_with_downloader_and_transform(
+                Url::parse("http://localhost:8080").unwrap(),
+                &super::UpdateOptions {
+                    skip_download: true,
+                    ..Default::default()
+                },
+                &downloader,
+                |_, mut item| {
+                    if invalid {
+                        item.set_pub_date("not a date".to_string());
+                    }
+                    item
+                },
+            )?;
+
+            channel.validate()?;
+
+            Ok(channel.feed_file)
+        };
+
+        let updated = super::update_channels(channels()?, &super::BatchOptions::default(), update)?;
+        assert_eq!(updated, vec![std::path::PathBuf::from("valid.xml")]);
+
+        let result = super::update_channels(
+            channels()?,
+            &super::BatchOptions {
+                abort_on_validation_error: true,
+                ..Default::default()
+            },
+            update,
+        );
+        assert!(matches!(result, Err(Error::FeedValidationError(..))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_item_thumbnail() {
+        use super::ThumbnailPreference;
+
+        let thumbnail = |url: &str, width: f64, height: f64| youtube_dl::Thumbnail {
+            url: Some(url.to_string()),
+            width: Some(width),
+            height: Some(height),
+            ..Default::default()
+        };
+
+        let mut video = get_new_video();
+        video.thumbnail = Some("https://example.com/default.jpg".to_string());
+        video.thumbnails = Some(vec![
+            thumbnail("https://example.com/small.jpg", 120.0, 90.0),
+            thumbnail("https://example.com/square.jpg", 400.0, 400.0),
+            thumbnail("https://example.com/maxres.jpg", 1280.0, 720.0),
+        ]);
+
+        let item_thumbnail =
+            |preference| super::item_thumbnail(&video, preference).unwrap_or_default();
+
+        assert_eq!(item_thumbnail(None), "https://example.com/default.jpg");
+        assert_eq!(
+            item_thumbnail(Some(ThumbnailPreference::Maxres)),
+            "https://example.com/maxres.jpg"
+        );
+        assert_eq!(
+            item_thumbnail(Some(ThumbnailPreference::Square)),
+            "https://example.com/square.jpg"
+        );
+        assert_eq!(
+            item_thumbnail(Some(ThumbnailPreference::First)),
+            "https://example.com/small.jpg"
+        );
+        assert_eq!(
+            item_thumbnail(Some(ThumbnailPreference::Index(1))),
+            "https://example.com/square.jpg"
+        );
+        assert_eq!(
+            item_thumbnail(Some(ThumbnailPreference::Index(7))),
+            "https://example.com/default.jpg"
+        );
+
+        assert_eq!("index:2".parse(), Ok(ThumbnailPreference::Index(2)));
+        assert!("biggest".parse::<ThumbnailPreference>().is_err());
+    }
+
+    #[test]
+    fn test_update_with_confirm_deletes() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("confirm-deletes");
+        let old_file = temp_dir.join("mightycarmods/Wqww1B9wljA.mp4");
+
+        let mut old_video = get_new_video();
+        old_video.id = "Wqww1B9wljA".to_string();
+        old_video.upload_date = Some("20220118".to_string());
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video(), old_video]),
+        )))
+        .with_files(vec![
+            temp_dir.join("mightycarmods/QWkUFkXcx9I.mp4"),
+            old_file.clone(),
+        ]);
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+
+        let summary = channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                keep: Some(1),
+                confirm_deletes: true,
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+
+        assert_eq!(summary.removed, 1);
+        assert_eq!(summary.pending_deletions, vec![old_file.clone()]);
+        assert!(old_file.exists());
+        assert_eq!(
+            get_item_ids(channel.rss_channel.as_ref().unwrap()),
+            vec!["QWkUFkXcx9I"]
+        );
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_deletion_decision() {
+        use super::DeletionDecision;
+
+        let pending = vec![std::path::PathBuf::from("mightycarmods/Wqww1B9wljA.mp4")];
+
+        assert_eq!(
+            super::deletion_decision(&pending, true, false),
+            DeletionDecision::Delete
+        );
+        assert_eq!(
+            super::deletion_decision(&pending, true, true),
+            DeletionDecision::Delete
+        );
+        assert_eq!(
+            super::deletion_decision(&pending, false, true),
+            DeletionDecision::Ask
+        );
+
+        // Without a terminal to ask at, nothing's deleted unless confirmed up front
+        assert_eq!(
+            super::deletion_decision(&pending, false, false),
+            DeletionDecision::Keep
+        );
+
+        // Nor is there anything to ask about without pending deletions
+        assert_eq!(
+            super::deletion_decision(&[], false, true),
+            DeletionDecision::Keep
+        );
+    }
+
+    #[test]
+    fn test_update_with_enclosure_query() -> Result<(), Error> {
+        use url::Url;
+
+        let mut channel = get_new_channel()?;
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                enclosure_query: vec![
+                    ("utm_source".to_string(), "podcast".to_string()),
+                    ("ref".to_string(), "a b&c".to_string()),
+                ],
+                ..Default::default()
+            },
+            get_playlist(vec![get_new_video()]),
+        )?;
+
+        let rss_channel = channel.rss_channel.unwrap();
+        let enclosure_url = rss_channel.items[0].enclosure().unwrap().url();
+        assert_eq!(
+            enclosure_url,
+            "http://localhost:8080/mightycarmods/QWkUFkXcx9I.mp4?utm_source=podcast&ref=a+b%26c"
+        );
+
+        // The media file is still found from the enclosure
+        assert!(super::Channel {
+            rss_channel: Some(rss_channel),
+            ..get_new_channel()?
+        }
+        .contains("QWkUFkXcx9I"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_guidless_items() -> Result<(), Error> {
+        use std::io::BufReader;
+        use url::Url;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let mut channel = super::Channel::new_with_reader(
+            std::path::Path::new("mightycarmods.xml").to_path_buf(),
+            BufReader::new(&bytes[0..]),
+        )?;
+
+        let guidless_item = |title: &str| {
+            rss::ItemBuilder::default()
+                .title(title.to_string())
+                .pub_date("Mon, 17 Jan 2022 00:00:00 +0000".to_string())
+                .build()
+        };
+
+        let rss_channel = channel.rss_channel.as_mut().unwrap();
+        rss_channel.items.push(guidless_item("Announcement"));
+        rss_channel
+            .items
+            .push(guidless_item("Another announcement"));
+        rss_channel
+            .items
+            .push(guidless_item("Another announcement"));
+        rss_channel.items.push(rss::Item::default());
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                keep: Some(4),
+                ..Default::default()
+            },
+            get_playlist(vec![get_new_video()]),
+        )?;
+
+        // The empty item sorts last, so `keep` removes it, without a file to delete
+        let titles = channel
+            .rss_channel
+            .unwrap()
+            .items
+            .iter()
+            .map(|item| item.title().unwrap_or_default().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            titles,
+            vec![
+                "Everyone Should do this Simple $10 Car Mod",
+                "Trying to Guess Marty's New Car (Blindfolded!)",
+                "Announcement",
+                "Another announcement",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_channel_info() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("write-channel-info");
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![get_new_video()]),
+        )?;
+        assert!(!channel.channel_info_file().exists());
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![get_new_video()]),
+        )?;
+
+        // Only written along with the feed, and only when asked to
+        std::fs::create_dir_all(&temp_dir)?;
+        channel.write(&super::WriteOptions::default())?;
+        assert!(!channel.channel_info_file().exists());
+
+        channel.write(&super::WriteOptions {
+            channel_info: true,
+            ..Default::default()
+        })?;
+
+        assert_eq!(
+            channel.channel_info_file(),
+            temp_dir.join("mightycarmods.info.json")
+        );
+
+        let channel_info: serde_json::Value =
+            serde_json::from_reader(std::fs::File::open(channel.channel_info_file())?).unwrap();
+
+        assert_eq!(channel_info["id"], "UCgJRL30YS6XFxq9Ga8W2J3A");
+        assert_eq!(channel_info["title"], "Mighty Car Mods - Videos");
+        assert_eq!(
+            channel_info["description"],
+            "playcaster podcast feed for Mighty Car Mods - Videos"
+        );
+        assert_eq!(channel_info["uploader"], "Mighty Car Mods");
+        assert_eq!(
+            channel_info["webpage_url"],
+            "https://www.youtube.com/c/mightycarmods"
+        );
+        assert_eq!(
+            channel_info["playlist_url"],
+            "https://www.youtube.com/c/mightycarmods"
+        );
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_prefer_fresh_metadata() -> Result<(), Error> {
+        use std::io::BufReader;
+        use url::Url;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let read_channel = || {
+            super::Channel::new_with_reader(
+                std::path::Path::new("mightycarmods.xml").to_path_buf(),
+                BufReader::new(&bytes[0..]),
+            )
+        };
+
+        let mut updated_video = get_new_video();
+        updated_video.id = "Wqww1B9wljA".to_string();
+        updated_video.title = Some("Guessing Marty's New Car (Blindfolded!)".to_string());
+        updated_video.upload_date = Some("20220201".to_string());
+
+        let item_title_and_date = |channel: super::Channel| {
+            let rss_channel = channel.rss_channel.unwrap();
+            let item = rss_channel
+                .items
+                .iter()
+                .find(|item| item.guid().unwrap().value() == "Wqww1B9wljA")
+                .unwrap();
+
+            (
+                item.title().unwrap().to_string(),
+                item.pub_date().unwrap().to_string(),
+            )
+        };
+
+        let mut channel = read_channel()?;
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![updated_video.clone()]),
+        )?;
+        assert_eq!(
+            item_title_and_date(channel),
+            (
+                "Trying to Guess Marty's New Car (Blindfolded!)".to_string(),
+                "Tue, 18 Jan 2022 03:57:58 +0000".to_string()
+            )
+        );
+
+        let mut channel = read_channel()?;
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                prefer_fresh_metadata: true,
+                ..Default::default()
+            },
+            get_playlist(vec![updated_video]),
+        )?;
+        assert_eq!(
+            item_title_and_date(channel),
+            (
+                "Guessing Marty's New Car (Blindfolded!)".to_string(),
+                "Tue, 18 Jan 2022 03:57:58 +0000".to_string()
+            )
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_channels_with_shutdown() -> Result<(), Error> {
+        use std::sync::atomic::Ordering;
+        use url::Url;
+
+        let temp_dir = get_temp_dir("shutdown");
+
+        let channels = ["first", "second"]
+            .iter()
+            .map(|name| {
+                super::Channel::new_with_url(
+                    temp_dir.join(format!("{}.xml", name)),
+                    Url::parse(&format!("https://www.youtube.com/c/{}", name)).unwrap(),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let options = super::BatchOptions::default();
+
+        let updated = super::update_channels(channels, &options, |mut channel| {
+            channel.update_with_playlist(
+                Url::parse("http://localhost:8080").unwrap(),
+                &super::UpdateOptions::default(),
+                get_playlist(vec![get_new_video()]),
+            )?;
+
+            // As if interrupted while this channel was updating
+            options.shutdown.store(true, Ordering::SeqCst);
+
+            channel.write(&super::WriteOptions::default())?;
+
+            Ok(channel.feed_file)
+        })?;
+
+        assert_eq!(updated, vec![temp_dir.join("first.xml")]);
+        assert!(temp_dir.join("first.xml").exists());
+        assert!(!temp_dir.join("second.xml").exists());
+        assert_eq!(std::fs::read_dir(&temp_dir)?.count(), 1);
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_channels_with_inter_channel_delay() -> Result<(), Error> {
+        use std::time::{Duration, Instant};
+        use url::Url;
+
+        let channels = ["first", "second", "third"]
+            .iter()
+            .map(|name| {
+                super::Channel::new_with_url(
+                    format!("{}.xml", name).into(),
+                    Url::parse(&format!("https://www.youtube.com/c/{}", name)).unwrap(),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let options = super::BatchOptions {
+            inter_channel_delay: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+
+        let started = Instant::now();
+
+        let started_at = super::update_channels(channels, &options, |_| Ok(started.elapsed()))?;
+
+        // No wait before the first channel, then one before each of the others
+        assert!(started_at[0] < Duration::from_millis(200));
+        assert!(started_at[1] - started_at[0] >= Duration::from_millis(200));
+        assert!(started_at[2] - started_at[1] >= Duration::from_millis(200));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_prefer_free_formats() -> Result<(), Error> {
+        use url::Url;
+
+        let mut channel = get_new_channel()?;
+
+        let args = channel.downloader_args(&super::UpdateOptions::default())?;
+        assert!(!args.contains(&"--prefer-free-formats".to_string()));
+
+        let options = super::UpdateOptions {
+            prefer_free_formats: true,
+            ..Default::default()
+        };
+
+        let args = channel.downloader_args(&options)?;
+        assert!(args.contains(&"--prefer-free-formats".to_string()));
+        let position = args.iter().position(|arg| arg == "--format").unwrap();
+        assert_eq!(
+            args[position + 1],
+            "bestvideo[ext=webm][vcodec^=vp9]+bestaudio[ext=webm]/best[ext=webm][vcodec^=vp9]/best[ext=webm]/best"
+        );
+
+        // `yt-dlp` reports the extension of the format it chose
+        let mut video = get_new_video();
+        video.ext = Some("webm".to_string());
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &options,
+            get_playlist(vec![video]),
+        )?;
+
+        let rss_channel = channel.rss_channel.as_ref().unwrap();
+        let enclosure = rss_channel.items[0].enclosure().unwrap();
+        assert_eq!(
+            enclosure.url(),
+            "http://localhost:8080/mightycarmods/QWkUFkXcx9I.webm"
+        );
+        assert_eq!(enclosure.mime_type(), "video/webm");
+
+        // The existing item's file is found by its format
+        assert_eq!(
+            super::MediaFormat::of_item(&rss_channel.items[0]),
+            super::MediaFormat::WEBM
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_invalid_enclosure_url() -> Result<(), Error> {
+        use url::Url;
+
+        let mut channel = get_new_channel()?;
+
+        // Joins as a scheme-relative URL with an invalid host
+        let invalid_video = youtube_dl::SingleVideo {
+            id: "//[invalid".to_string(),
+            ..get_new_video()
+        };
+
+        let summary = channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![invalid_video, get_new_video()]),
+        )?;
+
+        assert_eq!(summary.invalid_enclosures, vec!["//[invalid".to_string()]);
+        assert_eq!((summary.added, summary.total), (1, 1));
+
+        let rss_channel = channel.rss_channel.unwrap();
+        assert_eq!(rss_channel.items[0].guid().unwrap().value(), "QWkUFkXcx9I");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_sub_langs() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("sub-langs");
+        let media_dir = temp_dir.join("mightycarmods");
+        std::fs::create_dir_all(&media_dir)?;
+        std::fs::write(media_dir.join("QWkUFkXcx9I.en.vtt"), b"WEBVTT\n")?;
+        std::fs::write(media_dir.join("QWkUFkXcx9I.es.vtt"), b"WEBVTT\n")?;
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+
+        let options = super::UpdateOptions {
+            sub_langs: vec!["en".to_string(), "es".to_string(), "fr".to_string()],
+            ..Default::default()
+        };
+
+        let args = channel.downloader_args(&options)?;
+        let position = args.iter().position(|arg| arg == "--sub-langs").unwrap();
+        assert_eq!(args[position + 1], "en,es,fr");
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &options,
+            get_playlist(vec![get_new_video()]),
+        )?;
+
+        let rss_channel = channel.rss_channel.unwrap();
+        assert_eq!(
+            rss_channel.namespaces.get("podcast").map(String::as_str),
+            Some("https://podcastindex.org/namespace/1.0")
+        );
+
+        // French has no subtitles, so is skipped
+        let transcripts = &rss_channel.items[0].extensions["podcast"]["transcript"];
+        let transcripts: Vec<_> = transcripts
+            .iter()
+            .map(|transcript| {
+                (
+                    transcript.attrs["language"].as_str(),
+                    transcript.attrs["url"].as_str(),
+                    transcript.attrs["type"].as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            transcripts,
+            vec![
+                (
+                    "en",
+                    "http://localhost:8080/mightycarmods/QWkUFkXcx9I.en.vtt",
+                    "text/vtt"
+                ),
+                (
+                    "es",
+                    "http://localhost:8080/mightycarmods/QWkUFkXcx9I.es.vtt",
+                    "text/vtt"
+                ),
+            ]
+        );
+
+        let mut output = vec![];
+        rss_channel.write_to(&mut output)?;
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("xmlns:podcast=\"https://podcastindex.org/namespace/1.0\""));
+        assert_eq!(output.matches("<podcast:transcript ").count(), 2);
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_prune_empty_dirs() -> Result<(), Error> {
+        use std::io::BufReader;
+        use url::Url;
+
+        let temp_dir = get_temp_dir("prune-empty-dirs");
+        let media_dir = temp_dir.join("mightycarmods");
+        std::fs::create_dir_all(&media_dir)?;
+        std::fs::write(media_dir.join("Wqww1B9wljA.mp4"), b"")?;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let mut channel = super::Channel::new_with_reader(
+            temp_dir.join("mightycarmods.xml"),
+            BufReader::new(&bytes[0..]),
+        )?;
+
+        // Removing the only file empties the directory, so it's removed too
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                keep: Some(1),
+                prune_empty_dirs: true,
+                ..Default::default()
+            },
+            get_playlist(vec![get_new_video()]),
+        )?;
+
+        assert!(!media_dir.exists());
+
+        // A directory still holding sidecar files is kept
+        std::fs::create_dir_all(&media_dir)?;
+        std::fs::write(media_dir.join("QWkUFkXcx9I.en.vtt"), b"WEBVTT\n")?;
+
+        assert!(!channel.prune_media_dir()?);
+        assert!(media_dir.join("QWkUFkXcx9I.en.vtt").exists());
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_media_dir() -> Result<(), Error> {
+        use url::Url;
+
+        let mut channel = super::Channel {
+            media_dir: Some(std::path::PathBuf::from("/srv/media/cars")),
+            ..get_new_channel()?
+        };
+
+        let options = super::UpdateOptions::default();
+
+        let args = channel.downloader_args(&options)?;
+        let position = args.iter().position(|arg| arg == "--output").unwrap();
+        assert_eq!(args[position + 1], "/srv/media/cars/%(id)s.%(ext)s");
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &options,
+            get_playlist(vec![get_new_video()]),
+        )?;
+
+        // The URL follows the media directory, rather than the feed file's name
+        let item = &channel.rss_channel.as_ref().unwrap().items[0];
+        assert_eq!(
+            item.enclosure().unwrap().url(),
+            "http://localhost:8080/cars/QWkUFkXcx9I.mp4"
+        );
+
+        // An explicit prefix overrides it
+        channel.url_path_prefix = Some("/podcasts/cars/".to_string());
+        channel.rebase(Url::parse("http://localhost:8080").unwrap())?;
+
+        let item = &channel.rss_channel.as_ref().unwrap().items[0];
+        assert_eq!(
+            item.enclosure().unwrap().url(),
+            "http://localhost:8080/podcasts/cars/QWkUFkXcx9I.mp4"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_match_filters() -> Result<(), Error> {
+        use url::Url;
+
+        let mut channel = get_new_channel()?;
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )));
+
+        channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                match_filters: vec![
+                    "view_count > 1000 & duration < 1200".to_string(),
+                    "!is_live".to_string(),
+                ],
+                skip_download: true,
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+
+        let invocation = &downloader.invocations.borrow()[0];
+        let match_filters: Vec<_> = invocation
+            .iter()
+            .enumerate()
+            .filter(|(_, arg)| *arg == "--match-filter")
+            .map(|(position, _)| invocation[position + 1].as_str())
+            .collect();
+        assert_eq!(
+            match_filters,
+            vec![
+                format!(
+                    "view_count > 1000 & duration < 1200 & {}",
+                    super::MEMBERS_ONLY_FILTER
+                ),
+                format!("!is_live & {}", super::MEMBERS_ONLY_FILTER),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_relative_enclosure() -> Result<(), Error> {
+        use url::Url;
+
+        let update = |strict| -> Result<super::Channel, Error> {
+            let mut channel = get_new_channel()?;
+
+            channel.update_with_playlist_and_transform(
+                Url::parse("http://localhost:8080").unwrap(),
+                &super::UpdateOptions {
+                    strict,
+                    ..Default::default()
+                },
+                get_playlist(vec![get_new_video()]),
+                &std::collections::HashMap::new(),
+                |_, mut item| {
+                    if let Some(ref mut enclosure) = item.enclosure {
+                        enclosure.set_url("mightycarmods/QWkUFkXcx9I.mp4");
+                    }
+                    item
+                },
+            )?;
+
+            Ok(channel)
+        };
+
+        // Only warned about, so the feed is still updated
+        assert_eq!(update(false)?.rss_channel.unwrap().items.len(), 1);
+
+        let error = update(true).err().unwrap();
+        assert!(matches!(
+            error,
+            Error::RelativeEnclosureError(ref id, ref url)
+                if id == "QWkUFkXcx9I" && url == "mightycarmods/QWkUFkXcx9I.mp4"
+        ));
+        assert_eq!(
+            error.to_string(),
+            "item \"QWkUFkXcx9I\" has an enclosure URL which isn't absolute: \"mightycarmods/QWkUFkXcx9I.mp4\""
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_sort_categories() -> Result<(), Error> {
+        use crate::categories::Category;
+        use url::Url;
+
+        let categories: Vec<Category> = ["Technology", "Leisure/Automotive", "Leisure"]
+            .iter()
+            .map(|category| category.parse().unwrap())
+            .collect();
+
+        let build = |sort_categories| -> Result<Vec<String>, Error> {
+            let mut channel = get_new_channel()?;
+
+            channel.update_with_playlist(
+                Url::parse("http://localhost:8080").unwrap(),
+                &super::UpdateOptions {
+                    categories: categories
+                        .iter()
+                        .chain(categories.first())
+                        .cloned()
+                        .collect(),
+                    sort_categories,
+                    ..Default::default()
+                },
+                get_playlist(vec![get_new_video()]),
+            )?;
+
+            Ok(channel
+                .rss_channel
+                .unwrap()
+                .itunes_ext()
+                .unwrap()
+                .categories()
+                .iter()
+                .map(|category| match category.subcategory() {
+                    Some(subcategory) => format!("{}/{}", category.text(), subcategory.text()),
+                    None => category.text().to_string(),
+                })
+                .collect())
+        };
+
+        // The given order is kept, minus the repeated category
+        let given = build(false)?;
+        assert_eq!(given, vec!["Technology", "Leisure/Automotive", "Leisure"]);
+        assert_eq!(build(false)?, given);
+
+        let sorted = build(true)?;
+        assert_eq!(sorted, vec!["Leisure", "Leisure/Automotive", "Technology"]);
+        assert_eq!(build(true)?, sorted);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_single() -> Result<(), Error> {
+        use rss::validation::Validate;
+        use url::Url;
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::SingleVideo(Box::new(
+            get_new_video(),
+        )));
+
+        let video_url = Url::parse("https://www.youtube.com/watch?v=QWkUFkXcx9I").unwrap();
+
+        let mut channel = get_new_channel()?;
+        let summary = channel.update_single_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            video_url.clone(),
+            &downloader,
+        )?;
+        assert_eq!((summary.added, summary.total), (1, 1));
+
+        assert_eq!(downloader.invocations.borrow().len(), 1);
+        assert_eq!(channel.playlist_url, video_url);
+
+        let rss_channel = channel.rss_channel.unwrap();
+        rss_channel.validate().unwrap();
+
+        assert_eq!(rss_channel.title(), "Mighty Car Mods");
+        assert_eq!(rss_channel.link(), video_url.as_str());
+        assert_eq!(get_item_ids(&rss_channel), vec!["QWkUFkXcx9I"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_downloader_args_geo_bypass_country() -> Result<(), Error> {
+        let channel = get_new_channel()?;
+
+        let args = channel.downloader_args(&super::UpdateOptions::default())?;
+        assert!(!args.contains(&"--geo-bypass-country".to_string()));
+
+        let args = channel.downloader_args(&super::UpdateOptions {
+            geo_bypass_country: Some("AU".to_string()),
+            ..Default::default()
+        })?;
+        let position = args
+            .iter()
+            .position(|arg| arg == "--geo-bypass-country")
+            .unwrap();
+        assert_eq!(args[position + 1], "AU");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_to_minimal() -> Result<(), Error> {
+        use rss::validation::Validate;
+        use std::io::BufReader;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let channel = super::Channel::new_with_reader(
+            std::path::Path::new("mightycarmods.xml").to_path_buf(),
+            BufReader::new(&bytes[0..]),
+        )?;
+
+        let mut full = vec![];
+        channel.write_to(&mut full, &super::WriteOptions::default())?;
+
+        let mut minimal = vec![];
+        channel.write_to(
+            &mut minimal,
+            &super::WriteOptions {
+                minimal: true,
+                ..Default::default()
+            },
+        )?;
+
+        assert!(minimal.len() < full.len());
+
+        let full = rss::Channel::read_from(&full[..])?;
+        let minimal = rss::Channel::read_from(&minimal[..])?;
+        minimal.validate().unwrap();
+
+        assert_eq!(minimal.title(), full.title());
+        assert_eq!(minimal.link(), full.link());
+        assert_eq!(minimal.description(), full.description());
+        assert_eq!(minimal.items.len(), full.items.len());
+
+        for (minimal_item, full_item) in minimal.items.iter().zip(&full.items) {
+            assert_eq!(minimal_item.title(), full_item.title());
+            assert_eq!(minimal_item.guid(), full_item.guid());
+            assert_eq!(minimal_item.enclosure(), full_item.enclosure());
+            assert_eq!(minimal_item.pub_date(), full_item.pub_date());
+            assert!(minimal_item.content().is_none());
+            assert!(
+                minimal_item
+                    .description()
+                    .unwrap_or_default()
+                    .chars()
+                    .count()
+                    <= 280
+            );
+
+            let itunes_ext = minimal_item.itunes_ext().unwrap();
+            assert!(itunes_ext.summary().is_none());
+            assert_eq!(
+                itunes_ext.duration(),
+                full_item.itunes_ext().unwrap().duration()
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_trash_dir() -> Result<(), Error> {
+        use std::io::BufReader;
+        use url::Url;
+
+        let temp_dir = get_temp_dir("trash-dir");
+        let media_dir = temp_dir.join("mightycarmods");
+        let trash_dir = temp_dir.join("trash");
+        std::fs::create_dir_all(&media_dir)?;
+        std::fs::write(media_dir.join("Wqww1B9wljA.mp4"), b"video")?;
+        std::fs::write(media_dir.join("Wqww1B9wljA.en.vtt"), b"WEBVTT\n")?;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let mut channel = super::Channel::new_with_reader(
+            temp_dir.join("mightycarmods.xml"),
+            BufReader::new(&bytes[0..]),
+        )?;
+
+        let summary = channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                keep: Some(1),
+                trash_dir: Some(trash_dir.clone()),
+                ..Default::default()
+            },
+            get_playlist(vec![get_new_video()]),
+        )?;
+        assert!(summary.removed > 0);
+        assert_eq!(
+            get_item_ids(channel.rss_channel.as_ref().unwrap()),
+            vec!["QWkUFkXcx9I"]
+        );
+
+        // The media file and its subtitles were moved, not deleted
+        assert!(!media_dir.join("Wqww1B9wljA.mp4").exists());
+        assert!(!media_dir.join("Wqww1B9wljA.en.vtt").exists());
+        assert_eq!(std::fs::read(trash_dir.join("Wqww1B9wljA.mp4"))?, b"video");
+        assert!(trash_dir.join("Wqww1B9wljA.en.vtt").exists());
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_args_file() -> Result<(), Error> {
+        use url::Url;
+
+        let args_file = "# Extractor tweaks\n--extractor-args\nyoutube:player_client=web\n\n  --match-filter  \n!is_live\n";
+        let args = super::read_args_file(args_file.as_bytes())?;
+        assert_eq!(
+            args,
+            vec![
+                "--extractor-args",
+                "youtube:player_client=web",
+                "--match-filter",
+                "!is_live"
+            ]
+        );
+
+        let mut channel = get_new_channel()?;
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )));
+
+        channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                additional_args: args.clone(),
+                skip_download: true,
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+
+        let invocation = &downloader.invocations.borrow()[0];
+        let position = invocation
+            .iter()
+            .position(|arg| arg == "--extractor-args")
+            .unwrap();
+        assert_eq!(invocation[position..position + args.len()], args[..]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_channel_image() -> Result<(), Error> {
+        use rss::validation::Validate;
+        use url::Url;
+
+        let mut video = get_new_video();
+        video.thumbnail = Some("https://i.ytimg.com/vi/QWkUFkXcx9I/maxresdefault.jpg".to_string());
+
+        let mut channel = get_new_channel()?;
+        channel.update_with_playlist(
+            Url::parse("http://localhost").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![video]),
+        )?;
+        let rss_channel = channel.rss_channel.unwrap();
+        rss_channel.validate().unwrap();
+
+        assert_eq!(
+            rss_channel.itunes_ext.as_ref().unwrap().image(),
+            Some("https://i.ytimg.com/vi/QWkUFkXcx9I/maxresdefault.jpg")
+        );
+
+        let image = rss_channel.image().unwrap();
+        assert_eq!(
+            image.url(),
+            "https://i.ytimg.com/vi/QWkUFkXcx9I/maxresdefault.jpg"
+        );
+        assert_eq!(image.title(), "Mighty Car Mods - Videos");
+        assert_eq!(image.link(), "https://www.youtube.com/c/mightycarmods");
+
+        let output = rss_channel.to_string();
+        assert!(output
+            .contains("<image><url>https://i.ytimg.com/vi/QWkUFkXcx9I/maxresdefault.jpg</url>"));
+        assert!(output.contains(
+            "<itunes:image href=\"https://i.ytimg.com/vi/QWkUFkXcx9I/maxresdefault.jpg\"/>"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_disambiguate_titles() -> Result<(), Error> {
+        use url::Url;
+
+        let rerun = youtube_dl::SingleVideo {
+            id: "rerun123456".to_string(),
+            upload_date: Some("20220213".to_string()),
+            ..get_new_video()
+        };
+        let unique = youtube_dl::SingleVideo {
+            id: "unique12345".to_string(),
+            title: Some("Something Else".to_string()),
+            upload_date: Some("20220101".to_string()),
+            ..get_new_video()
+        };
+
+        let titles = |disambiguator| -> Result<Vec<String>, Error> {
+            let mut channel = get_new_channel()?;
+            let options = super::UpdateOptions {
+                disambiguate_titles: Some(disambiguator),
+                ..Default::default()
+            };
+
+            // Updating twice doesn't suffix the titles again
+            for _ in 0..2 {
+                channel.update_with_playlist(
+                    Url::parse("http://localhost:8080").unwrap(),
+                    &options,
+                    get_playlist(vec![rerun.clone(), get_new_video(), unique.clone()]),
+                )?;
+            }
+
+            Ok(channel
+                .rss_channel
+                .unwrap()
+                .items
+                .iter()
+                .map(|item| item.title().unwrap().to_string())
+                .collect())
+        };
+
+        assert_eq!(
+            titles(super::TitleDisambiguator::Date)?,
+            vec![
+                "Everyone Should do this Simple $10 Car Mod (2022-02-13)",
+                "Everyone Should do this Simple $10 Car Mod (2022-02-06)",
+                "Something Else",
+            ]
+        );
+        assert_eq!(
+            titles(super::TitleDisambiguator::Id)?,
+            vec![
+                "Everyone Should do this Simple $10 Car Mod [rerun123456]",
+                "Everyone Should do this Simple $10 Car Mod [QWkUFkXcx9I]",
+                "Something Else",
+            ]
+        );
+
+        // Once the title's no longer shared, the suffix is removed again
+        let mut channel = get_new_channel()?;
+        for keep in [None, Some(1)] {
+            channel.update_with_playlist(
+                Url::parse("http://localhost:8080").unwrap(),
+                &super::UpdateOptions {
+                    disambiguate_titles: Some(super::TitleDisambiguator::Date),
+                    keep,
+                    ..Default::default()
+                },
+                get_playlist(vec![rerun.clone(), get_new_video()]),
+            )?;
+        }
+
+        let items = &channel.rss_channel.as_ref().unwrap().items;
+        assert_eq!(
+            get_item_ids(channel.rss_channel.as_ref().unwrap()),
+            vec!["rerun123456"]
+        );
+        assert_eq!(
+            items[0].title(),
+            Some("Everyone Should do this Simple $10 Car Mod")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_items() -> Result<(), Error> {
+        use std::io::BufReader;
+        use url::Url;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let mut channel = super::Channel::new_with_reader(
+            std::path::PathBuf::from("mightycarmods.xml"),
+            BufReader::new(&bytes[0..]),
+        )?;
+
+        let old = channel.rss_channel.clone();
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                keep: Some(1),
+                skip_download: true,
+                ..Default::default()
+            },
+            get_playlist(vec![get_new_video()]),
+        )?;
+
+        let new = channel.rss_channel.as_ref().unwrap();
+        let changes = super::diff_items(old.as_ref(), new);
+
+        assert_eq!(
+            changes,
+            vec![
+                super::ItemChange::Added(
+                    "\"Everyone Should do this Simple $10 Car Mod\" [QWkUFkXcx9I]".to_string()
+                ),
+                super::ItemChange::Removed(
+                    "\"Trying to Guess Marty's New Car (Blindfolded!)\" [Wqww1B9wljA]".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            changes[1].to_string(),
+            "- \"Trying to Guess Marty's New Car (Blindfolded!)\" [Wqww1B9wljA]"
+        );
+
+        // Against itself, with a retitled item
+        let mut retitled = new.clone();
+        retitled.items[0].set_title("Renamed".to_string());
+        let changes = super::diff_items(Some(new), &retitled);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            changes[0].to_string(),
+            "~ \"Renamed\" [QWkUFkXcx9I] (title)"
+        );
+
+        assert!(super::diff_items(Some(new), new).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_preview() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("preview");
+        std::fs::create_dir_all(&temp_dir)?;
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )));
+
+        channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                skip_download: true,
+                provisional: true,
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+
+        channel.write(&super::WriteOptions {
+            preview: true,
+            ..Default::default()
+        })?;
+
+        // Only the preview was written, and nothing was downloaded
+        let preview_file = temp_dir.join("mightycarmods.preview.xml");
+        assert_eq!(super::preview_file(&channel.feed_file), preview_file);
+        assert_eq!(std::fs::read_dir(&temp_dir)?.count(), 1);
+
+        let preview =
+            rss::Channel::read_from(std::io::BufReader::new(std::fs::File::open(&preview_file)?))
+                .unwrap();
+        assert_eq!(
+            preview.items[0].enclosure().unwrap().url(),
+            "http://localhost:8080/mightycarmods/QWkUFkXcx9I.mp4"
+        );
+        assert_eq!(
+            preview.extensions["playcaster"]["provisional"][0].value(),
+            Some(super::PROVISIONAL_NOTE)
+        );
+        assert!(preview.namespaces.contains_key("playcaster"));
+
+        // A real update drops the mark
+        channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                skip_download: true,
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+        let rss_channel = channel.rss_channel.unwrap();
+        assert!(!rss_channel.extensions.contains_key("playcaster"));
+        assert!(!rss_channel.namespaces.contains_key("playcaster"));
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check() -> Result<(), Error> {
+        use std::io::BufReader;
+        use url::Url;
+
+        let temp_dir = get_temp_dir("check");
+        let media_dir = temp_dir.join("mightycarmods");
+        std::fs::create_dir_all(&media_dir)?;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let channel = super::Channel::new_with_reader(
+            temp_dir.join("mightycarmods.xml"),
+            BufReader::new(&bytes[0..]),
+        )?;
+
+        let base_url = Url::parse("http://localhost:8080").unwrap();
+
+        let problems = channel.check(&base_url)?;
+        assert_eq!(problems.len(), 1);
+        assert!(matches!(
+            problems[0],
+            super::CheckProblem::MissingMedia(ref id, ref path)
+                if id == "Wqww1B9wljA" && *path == media_dir.join("Wqww1B9wljA.mp4")
+        ));
+        assert_eq!(
+            problems[0].to_string(),
+            format!(
+                "item \"Wqww1B9wljA\" is missing its media file {:?}",
+                media_dir.join("Wqww1B9wljA.mp4")
+            )
+        );
+
+        std::fs::write(media_dir.join("Wqww1B9wljA.mp4"), b"video")?;
+        assert!(channel.check(&base_url)?.is_empty());
+
+        let problems = channel.check(&Url::parse("https://example.com/podcasts/").unwrap())?;
+        assert!(matches!(
+            problems[..],
+            [super::CheckProblem::ForeignEnclosureUrl(..)]
+        ));
+
+        // Nothing was changed
+        assert_eq!(std::fs::read_dir(&temp_dir)?.count(), 1);
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_author() -> Result<(), Error> {
+        use url::Url;
+
+        let authors = |options: &super::UpdateOptions,
+                       video: youtube_dl::SingleVideo,
+                       playlist_uploader: Option<&str>|
+         -> Result<(String, String), Error> {
+            let mut playlist = get_playlist(vec![video]);
+            playlist.uploader = playlist_uploader.map(str::to_string);
+
+            let mut channel = get_new_channel()?;
+            channel.update_with_playlist(
+                Url::parse("http://localhost:8080").unwrap(),
+                options,
+                playlist,
+            )?;
+
+            let rss_channel = channel.rss_channel.unwrap();
+            let channel_author = rss_channel.itunes_ext().unwrap().author().unwrap();
+            let item_author = rss_channel.items[0].itunes_ext().unwrap().author().unwrap();
+
+            Ok((channel_author.to_string(), item_author.to_string()))
+        };
+
+        let video = youtube_dl::SingleVideo {
+            uploader: Some("Marty".to_string()),
+            channel: Some("Moog".to_string()),
+            ..get_new_video()
+        };
+
+        let options = super::UpdateOptions::default();
+
+        assert_eq!(
+            authors(&options, video.clone(), Some("MCM"))?,
+            ("MCM".to_string(), "Marty".to_string())
+        );
+
+        let video = youtube_dl::SingleVideo {
+            uploader: None,
+            ..video
+        };
+        assert_eq!(
+            authors(&options, video.clone(), Some("MCM"))?,
+            ("MCM".to_string(), "Moog".to_string())
+        );
+
+        let video = youtube_dl::SingleVideo {
+            channel: None,
+            ..video
+        };
+        assert_eq!(
+            authors(&options, video.clone(), Some("MCM"))?,
+            ("MCM".to_string(), "MCM".to_string())
+        );
+
+        // The playlist's title is the last resort
+        assert_eq!(
+            authors(&options, video.clone(), None)?,
+            (
+                "Mighty Car Mods - Videos".to_string(),
+                "Mighty Car Mods - Videos".to_string()
+            )
+        );
+
+        let options = super::UpdateOptions {
+            author: Some("Mighty Car Mods".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            authors(&options, get_new_video(), Some("MCM"))?,
+            ("Mighty Car Mods".to_string(), "Mighty Car Mods".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_title_source() -> Result<(), Error> {
+        use url::Url;
+
+        let video = youtube_dl::SingleVideo {
+            track: Some("Rotary Blues".to_string()),
+            artist: Some("Mighty Car Mods".to_string()),
+            ..get_new_video()
+        };
+
+        let title = |video: &youtube_dl::SingleVideo, title_source| -> Result<String, Error> {
+            let mut channel = get_new_channel()?;
+            channel.update_with_playlist(
+                Url::parse("http://localhost:8080").unwrap(),
+                &super::UpdateOptions {
+                    title_source,
+                    ..Default::default()
+                },
+                get_playlist(vec![video.clone()]),
+            )?;
+
+            let item = &channel.rss_channel.as_ref().unwrap().items[0];
+
+            // The iTunes subtitle follows the title
+            assert_eq!(item.itunes_ext().unwrap().subtitle(), item.title());
+
+            Ok(item.title().unwrap().to_string())
+        };
+
+        assert_eq!(title(&video, super::TitleSource::Track)?, "Rotary Blues");
+        assert_eq!(
+            title(&video, super::TitleSource::Title)?,
+            video.title.clone().unwrap()
+        );
+
+        // Videos without the field fall back to their title
+        assert_eq!(
+            title(&video, super::TitleSource::AltTitle)?,
+            video.title.clone().unwrap()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_date_source() -> Result<(), Error> {
+        use url::Url;
+
+        // A premiere, uploaded ahead of its release
+        let video = youtube_dl::SingleVideo {
+            timestamp: Some(1644120000.0),
+            upload_date: Some("20220206".to_string()),
+            release_date: Some("20220210".to_string()),
+            ..get_new_video()
+        };
+
+        let pub_date = |video: &youtube_dl::SingleVideo, date_source| -> Result<String, Error> {
+            let mut channel = get_new_channel()?;
+            channel.update_with_playlist(
+                Url::parse("http://localhost:8080").unwrap(),
+                &super::UpdateOptions {
+                    date_source,
+                    ..Default::default()
+                },
+                get_playlist(vec![video.clone()]),
+            )?;
+
+            Ok(channel.rss_channel.unwrap().items[0]
+                .pub_date()
+                .unwrap()
+                .to_string())
+        };
+
+        assert_eq!(
+            pub_date(&video, super::DateSource::Timestamp)?,
+            "Sun, 6 Feb 2022 04:00:00 +0000"
+        );
+        assert_eq!(
+            pub_date(&video, super::DateSource::Upload)?,
+            "Sun, 6 Feb 2022 00:00:00 +0000"
+        );
+        assert_eq!(
+            pub_date(&video, super::DateSource::Release)?,
+            "Thu, 10 Feb 2022 00:00:00 +0000"
+        );
+
+        // Videos without the chosen date fall back to the others
+        let video = youtube_dl::SingleVideo {
+            release_date: None,
+            ..video
+        };
+        assert_eq!(
+            pub_date(&video, super::DateSource::Release)?,
+            "Sun, 6 Feb 2022 04:00:00 +0000"
+        );
+
+        // As do videos whose chosen date can't be parsed, with a warning
+        let warnings = captured_warnings();
+        let thread = std::thread::current().id();
+
+        let video = youtube_dl::SingleVideo {
+            timestamp: None,
+            release_date: Some("2022-02-10".to_string()),
+            ..video
+        };
+        assert_eq!(
+            pub_date(&video, super::DateSource::Release)?,
+            "Sun, 6 Feb 2022 00:00:00 +0000"
+        );
+        assert!(warnings
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(id, warning)| *id == thread && warning.contains("\"2022-02-10\"")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_mime_map() -> Result<(), Error> {
+        use url::Url;
+
+        let mime_map = vec![
+            ("3GP".to_string(), "video/3gpp".to_string()),
+            ("mp4".to_string(), "video/x-custom".to_string()),
+        ];
+
+        assert_eq!(
+            super::mime_type_for_extension("3gp", &mime_map).as_deref(),
+            Some("video/3gpp")
+        );
+        assert_eq!(super::mime_type_for_extension("3gp", &[]), None);
+        assert_eq!(
+            super::mime_type_for_extension("webm", &mime_map).as_deref(),
+            Some("video/webm")
+        );
+
+        let mut channel = get_new_channel()?;
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                mime_map,
+                ..Default::default()
+            },
+            get_playlist(vec![get_new_video()]),
+        )?;
+
+        let rss_channel = channel.rss_channel.unwrap();
+        assert_eq!(
+            rss_channel.items[0].enclosure().unwrap().mime_type(),
+            "video/x-custom"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_downloader_args_live_from_start() -> Result<(), Error> {
+        let channel = get_new_channel()?;
+
+        let args = channel.downloader_args(&super::UpdateOptions::default())?;
+        assert!(!args.contains(&"--live-from-start".to_string()));
+
+        let args = channel.downloader_args(&super::UpdateOptions {
+            live_from_start: true,
+            ..Default::default()
+        })?;
+        assert!(args.contains(&"--live-from-start".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_timing() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("timing");
+        let media_dir = temp_dir.join("mightycarmods");
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )))
+        .with_files(vec![media_dir.join("QWkUFkXcx9I.mp4")])
+        .with_delay(std::time::Duration::from_millis(50));
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+        let summary = channel.update_with_downloader(
+            Url::parse("http://localhost").unwrap(),
+            &super::UpdateOptions::default(),
+            &downloader,
+        )?;
+
+        assert_eq!(summary.downloaded, 1);
+        assert!(summary.elapsed >= 0.05);
+        assert!(summary.avg_speed > 0.0);
+        assert!(summary.avg_speed <= b"downloaded".len() as f64 / 0.05);
+
+        // Each download's own time runs until `yt-dlp` started on the next, or finished
+        let older_video = youtube_dl::SingleVideo {
+            id: "Wqww1B9wljA".to_string(),
+            upload_date: Some("20220118".to_string()),
+            ..get_new_video()
+        };
+        let now = chrono::Utc::now().timestamp() as u64;
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![older_video, get_new_video()]),
+        )))
+        .with_reported_files(vec![
+            ("Wqww1B9wljA", media_dir.join("Wqww1B9wljA.mp4")),
+            ("QWkUFkXcx9I", media_dir.join("QWkUFkXcx9I.mp4")),
+        ])
+        .with_start_times(vec![now - 10, now - 4]);
+
+        let summary = channel.update_with_downloader(
+            Url::parse("http://localhost").unwrap(),
+            &super::UpdateOptions::default(),
+            &downloader,
+        )?;
+
+        let elapsed: std::collections::HashMap<_, _> = summary
+            .downloads
+            .iter()
+            .map(|download| (download.id.as_str(), download.elapsed))
+            .collect();
+        assert_eq!(elapsed["Wqww1B9wljA"], Some(6));
+
+        // The other was already on disk, so wasn't downloaded this time
+        assert_eq!(elapsed["QWkUFkXcx9I"], None);
+
+        // The last one runs until the run finished, and those with no start time are left out
+        let downloaded_files = [("first", Some(100)), ("last", Some(103)), ("unknown", None)]
+            .into_iter()
+            .map(|(id, started)| {
+                let path = media_dir.join(format!("{}.mp4", id));
+                (id.to_string(), super::DownloadedFile { path, started })
+            })
+            .collect();
+        assert_eq!(
+            super::download_durations(&downloaded_files, 110),
+            std::collections::HashMap::from([("first", 3), ("last", 7)])
+        );
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_channel_keywords() -> Result<(), Error> {
+        use url::Url;
+
+        let tagged = youtube_dl::SingleVideo {
+            tags: Some(vec![Some("cars".to_string()), Some("DIY".to_string())]),
+            categories: Some(vec![Some("Autos & Vehicles".to_string())]),
+            ..get_new_video()
+        };
+        let retagged = youtube_dl::SingleVideo {
+            id: "Wqww1B9wljA".to_string(),
+            upload_date: Some("20220118".to_string()),
+            timestamp: None,
+            tags: Some(vec![
+                Some("Cars".to_string()),
+                Some("blindfolded".to_string()),
+            ]),
+            categories: None,
+            ..get_new_video()
+        };
+
+        let mut channel = get_new_channel()?;
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![tagged.clone(), retagged.clone()]),
+        )?;
+
+        let rss_channel = channel.rss_channel.unwrap();
+        assert_eq!(
+            rss_channel.items[0].itunes_ext().unwrap().keywords(),
+            Some("cars, DIY, Autos & Vehicles")
+        );
+        assert_eq!(
+            rss_channel.itunes_ext().unwrap().keywords(),
+            Some("cars, DIY, Autos & Vehicles, blindfolded")
+        );
+
+        let mut channel = get_new_channel()?;
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                channel_keywords: vec!["mods".to_string(), "Australia".to_string()],
+                ..Default::default()
+            },
+            get_playlist(vec![tagged, retagged]),
+        )?;
+
+        assert_eq!(
+            channel
+                .rss_channel
+                .unwrap()
+                .itunes_ext()
+                .unwrap()
+                .keywords(),
+            Some("mods, Australia")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_slugged_media_dir() -> Result<(), Error> {
+        use url::Url;
+
+        assert_eq!(super::slugify("My Feed (2024)!"), "My-Feed-2024");
+        assert_eq!(
+            super::slugify("UCgJRL30YS6XFxq9Ga8W2J3A"),
+            "UCgJRL30YS6XFxq9Ga8W2J3A"
+        );
+        assert_eq!(super::slugify("../.."), "playcaster");
+
+        let temp_dir = get_temp_dir("slugged-media-dir");
+
+        let playlist_url =
+            Url::parse("https://www.youtube.com/playlist?list=PL%2F..%2Fcar%20mods&index=2")
+                .unwrap();
+        let feed_file = super::feed_file_for_playlist(&temp_dir, &playlist_url);
+        assert_eq!(feed_file, temp_dir.join("PL-car-mods.xml"));
+
+        let mut channel = super::Channel::new_with_url(feed_file, playlist_url)?;
+
+        let args = channel.downloader_args(&super::UpdateOptions::default())?;
+        let position = args.iter().position(|arg| arg == "--output").unwrap();
+        assert_eq!(
+            args[position + 1],
+            temp_dir
+                .join("PL-car-mods")
+                .join("%(id)s.%(ext)s")
+                .to_string_lossy()
+        );
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![get_new_video()]),
+        )?;
+        assert_eq!(
+            channel.rss_channel.as_ref().unwrap().items[0]
+                .enclosure()
+                .unwrap()
+                .url(),
+            "http://localhost:8080/PL-car-mods/QWkUFkXcx9I.mp4"
+        );
+
+        // Feed files named before slugs keep being used, unless they'd be outside the feed directory
+        let legacy_feed_file = temp_dir.join("car mods.xml");
+        std::fs::write(&legacy_feed_file, b"")?;
+        assert_eq!(
+            super::feed_file_for_playlist(
+                &temp_dir,
+                &Url::parse("https://www.youtube.com/playlist?list=car%20mods").unwrap()
+            ),
+            legacy_feed_file
+        );
+        std::fs::create_dir_all(temp_dir.join("PL"))?;
+        assert_eq!(
+            super::feed_file_for_playlist(
+                &temp_dir,
+                &Url::parse("https://www.youtube.com/playlist?list=PL%2F..%2Fcar%20mods").unwrap()
+            ),
+            temp_dir.join("PL-car-mods.xml")
+        );
+
+        // Feeds whose media directory predates slugs keep using it
+        let legacy_dir = temp_dir.join("car mods");
+        std::fs::create_dir_all(&legacy_dir)?;
+
+        let channel = super::Channel::new_with_url(
+            temp_dir.join("car mods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+        let args = channel.downloader_args(&super::UpdateOptions::default())?;
+        assert!(args.contains(
+            &legacy_dir
+                .join("%(id)s.%(ext)s")
+                .to_string_lossy()
+                .to_string()
+        ));
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_failure_preserves_feed() -> Result<(), Error> {
+        use std::io::Write;
+
+        let temp_dir = get_temp_dir("write-failure");
+        std::fs::create_dir_all(&temp_dir)?;
+
+        let feed_file = temp_dir.join("mightycarmods.xml");
+        std::fs::write(&feed_file, b"<rss>original</rss>")?;
+
+        // Simulate the disk filling up partway through writing
+        let result = super::write_atomically(&feed_file, |file| {
+            file.write_all(b"<rss>")?;
+            Err(std::io::Error::other("No space left on device").into())
+        });
+
+        assert!(matches!(
+            result,
+            Err(Error::WriteError(ref path, 5, _)) if *path == feed_file
+        ));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            format!(
+                "couldn't write \"{}\" (failed after 5 bytes); the existing file was left unchanged",
+                feed_file.display()
+            )
+        );
+
+        assert_eq!(std::fs::read(&feed_file)?, b"<rss>original</rss>");
+        assert_eq!(std::fs::read_dir(&temp_dir)?.count(), 1);
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fetch_metadata() -> Result<(), Error> {
+        let channel = get_new_channel()?;
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )));
+
+        let playlist = channel.fetch_metadata_with_downloader(
+            &super::UpdateOptions {
+                skip_download: false,
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+
+        // Metadata is fetched without ever asking yt-dlp to download anything
+        let invocations = downloader.invocations.borrow();
+        assert_eq!(invocations.len(), 1);
+        assert!(!invocations[0].contains(&"--no-simulate".to_string()));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string_pretty(&playlist).unwrap()).unwrap();
+        assert_eq!(json["title"], "Mighty Car Mods - Videos");
+        assert_eq!(json["entries"][0]["id"], "QWkUFkXcx9I");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_per_item_language() -> Result<(), Error> {
+        let mut channel = get_new_channel()?;
+
+        let mut video = get_new_video();
+        video.language = Some("de".to_string());
+
+        let mut unlabelled = get_new_video();
+        unlabelled.id = "Wqww1B9wljA".to_string();
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![video, unlabelled]),
+        )));
+
+        channel.update_with_downloader(
+            url::Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                skip_download: true,
+                per_item_language: true,
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+
+        let rss_channel = channel.rss_channel.as_ref().unwrap();
+        let language = |id: &str| {
+            rss_channel
+                .items
+                .iter()
+                .find(|item| item.guid.as_ref().unwrap().value == id)
+                .and_then(|item| item.dublin_core_ext.as_ref())
+                .map(|ext| ext.languages.clone())
+        };
+
+        assert_eq!(language("QWkUFkXcx9I"), Some(vec!["de".to_string()]));
+        assert_eq!(language("Wqww1B9wljA"), None);
+        assert_eq!(
+            rss_channel.namespaces.get("dc").map(String::as_str),
+            Some(rss::extension::dublincore::NAMESPACE)
+        );
+
+        let mut output = vec![];
+        rss_channel.write_to(&mut output)?;
+        assert!(String::from_utf8(output)
+            .unwrap()
+            .contains("<dc:language>de</dc:language>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune() -> Result<(), Error> {
+        use std::io::BufReader;
+        use url::Url;
+
+        let temp_dir = get_temp_dir("prune");
+        let media_dir = temp_dir.join("mightycarmods");
+        std::fs::create_dir_all(&media_dir)?;
+        std::fs::write(media_dir.join("QWkUFkXcx9I.mp4"), b"new")?;
+        std::fs::write(media_dir.join("Wqww1B9wljA.mp4"), b"old")?;
+        std::fs::write(media_dir.join("Wqww1B9wljA.en.vtt"), b"WEBVTT")?;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let mut channel = super::Channel::new_with_reader(
+            temp_dir.join("mightycarmods.xml"),
+            BufReader::new(&bytes[0..]),
+        )?;
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                skip_download: true,
+                ..Default::default()
+            },
+            get_playlist(vec![get_new_video()]),
+        )?;
+        assert_eq!(
+            get_item_ids(channel.rss_channel.as_ref().unwrap()),
+            vec!["QWkUFkXcx9I", "Wqww1B9wljA"]
+        );
+
+        // Keeping more items than the feed has changes nothing
+        let summary = channel.prune(2, true)?;
+        assert_eq!((summary.removed, summary.total), (0, 2));
+
+        // Without deleting files, only the feed is trimmed
+        let items = channel.rss_channel.as_ref().unwrap().items.clone();
+        let summary = channel.prune(1, false)?;
+        assert_eq!((summary.removed, summary.total), (1, 1));
+        assert!(summary.deleted_files.is_empty());
+        assert_eq!(std::fs::read_dir(&media_dir)?.count(), 3);
+        channel.rss_channel.as_mut().unwrap().set_items(items);
+
+        let summary = channel.prune(1, true)?;
+        assert_eq!((summary.removed, summary.total), (1, 1));
+        assert_eq!(
+            get_item_ids(channel.rss_channel.as_ref().unwrap()),
+            vec!["QWkUFkXcx9I"]
+        );
+
+        // The removed item's media and sidecar files are deleted, the kept item's aren't
+        assert!(media_dir.join("QWkUFkXcx9I.mp4").exists());
+        assert!(!media_dir.join("Wqww1B9wljA.mp4").exists());
+        assert!(!media_dir.join("Wqww1B9wljA.en.vtt").exists());
+
+        assert!(matches!(
+            channel.prune(0, true),
+            Err(Error::InvalidKeepLimit(0))
+        ));
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_to_profile() -> Result<(), Error> {
+        use rss::validation::Validate;
+        use std::io::BufReader;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let mut channel = super::Channel::new_with_reader(
+            std::path::Path::new("mightycarmods.xml").to_path_buf(),
+            BufReader::new(&bytes[0..]),
+        )?;
+
+        let write = |channel: &super::Channel, profile| -> Result<rss::Channel, Error> {
+            let mut output = vec![];
+            channel.write_to(
+                &mut output,
+                &super::WriteOptions {
+                    profile: Some(profile),
+                    ..Default::default()
+                },
+            )?;
+            Ok(rss::Channel::read_from(&output[..])?)
+        };
+
+        let apple = write(&channel, super::FeedProfile::Apple)?;
+        apple.validate().unwrap();
+
+        assert_eq!(apple.language(), Some("en-us"));
+        let itunes_ext = apple.itunes_ext().unwrap();
+        assert!(itunes_ext.image().is_some());
+        assert_eq!(itunes_ext.author(), Some("Mighty Car Mods"));
+        assert_eq!(itunes_ext.categories()[0].text(), "TV & Film");
+        // Apple's older `no` is written as `false`
+        assert_eq!(itunes_ext.explicit(), Some("false"));
+
+        let strict = write(&channel, super::FeedProfile::Strict20)?;
+        strict.validate().unwrap();
+        assert!(strict.namespaces().is_empty());
+        assert!(strict.itunes_ext().is_none());
+        assert!(strict.items.iter().all(|item| item.itunes_ext().is_none()));
+
+        // A feed missing something Apple requires isn't written
+        channel.rss_channel.as_mut().unwrap().language = None;
+        assert!(matches!(
+            write(&channel, super::FeedProfile::Apple),
+            Err(Error::ProfileError(_, super::FeedProfile::Apple, ref problem)) if problem == "missing <language>"
+        ));
+        assert_eq!(
+            write(&channel, super::FeedProfile::Apple)
+                .unwrap_err()
+                .to_string(),
+            "feed \"mightycarmods.xml\" doesn't meet the apple profile: missing <language>"
+        );
+
+        // Items are named the same way whichever element they're missing
+        let problem = |channel: &super::Channel| match write(channel, super::FeedProfile::Google) {
+            Err(Error::ProfileError(_, _, problem)) => problem,
+            result => panic!("{:?}", result.map(|_| ())),
+        };
+
+        let item = &mut channel.rss_channel.as_mut().unwrap().items[0];
+        let guid = item.guid().unwrap().value().to_string();
+        item.title = None;
+        assert_eq!(
+            problem(&channel),
+            format!("missing <title> on item {:?}", guid)
+        );
+
+        let item = &mut channel.rss_channel.as_mut().unwrap().items[0];
+        item.title = Some("Title".to_string());
+        item.guid = None;
+        assert_eq!(problem(&channel), "missing <guid> on item 1");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_atomically_colocates_temp_file() -> Result<(), Error> {
+        use std::io::Write;
+
+        let temp_dir = get_temp_dir("write-colocated");
+        std::fs::create_dir_all(&temp_dir)?;
+
+        let feed_file = temp_dir.join("mightycarmods.xml");
+
+        super::write_atomically(&feed_file, |file| {
+            // While writing, the only file is the temporary one, alongside the destination
+            let entries: Vec<_> = std::fs::read_dir(&temp_dir)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<_, _>>()?;
+            assert_eq!(entries.len(), 1);
+
+            let name = entries[0]
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string();
+            assert!(name.starts_with(&format!(".mightycarmods.xml.{}.", std::process::id())));
+            assert!(name.ends_with(".tmp"));
+
+            Ok(file.write_all(b"<rss>new</rss>")?)
+        })?;
+
+        assert_eq!(std::fs::read(&feed_file)?, b"<rss>new</rss>");
+        assert_eq!(std::fs::read_dir(&temp_dir)?.count(), 1);
+
+        // The fallback for when renaming crosses devices leaves the same result
+        let temp_file = super::temp_path(&feed_file)?;
+        std::fs::write(&temp_file, b"<rss>copied</rss>")?;
+        super::copy_into_place(&temp_file, &feed_file)?;
+
+        assert_eq!(std::fs::read(&feed_file)?, b"<rss>copied</rss>");
+        assert!(!temp_file.exists());
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_atomically_concurrently() -> Result<(), Error> {
+        use std::io::Write;
+
+        let temp_dir = get_temp_dir("write-concurrently");
+        std::fs::create_dir_all(&temp_dir)?;
+
+        assert_ne!(
+            super::temp_path(&temp_dir.join("mightycarmods.xml"))?,
+            super::temp_path(&temp_dir.join("mightycarmods.xml"))?
+        );
+
+        let feed_file = temp_dir.join("mightycarmods.xml");
+        let barrier = std::sync::Barrier::new(2);
+
+        // Two writers of the same file, both partway through at once
+        std::thread::scope(|scope| {
+            let writers: Vec<_> = ["first", "second"]
+                .into_iter()
+                .map(|name| {
+                    let (feed_file, barrier) = (&feed_file, &barrier);
+
+                    scope.spawn(move || {
+                        super::write_atomically(feed_file, |file| {
+                            file.write_all(format!("<rss>{}", name).as_bytes())?;
+                            barrier.wait();
+                            Ok(file.write_all(b"</rss>")?)
+                        })
+                    })
+                })
+                .collect();
+
+            writers
+                .into_iter()
+                .try_for_each(|writer| writer.join().unwrap())
+        })?;
+
+        // Whichever finished last wins, but neither's output is mixed into the other's
+        let written = std::fs::read(&feed_file)?;
+        assert!(
+            written == b"<rss>first</rss>" || written == b"<rss>second</rss>",
+            "{:?}",
+            String::from_utf8_lossy(&written)
+        );
+        assert_eq!(std::fs::read_dir(&temp_dir)?.count(), 1);
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_downloader_args_members_only() -> Result<(), Error> {
+        let channel = get_new_channel()?;
+
+        let match_filters = |options: &super::UpdateOptions| -> Result<Vec<String>, Error> {
+            let args = channel.downloader_args(options)?;
+            Ok(args
+                .iter()
+                .enumerate()
+                .filter(|(_, arg)| *arg == "--match-filter")
+                .map(|(position, _)| args[position + 1].clone())
+                .collect())
+        };
+
+        // Members-only videos are skipped by default, rather than failing to download
+        assert_eq!(
+            match_filters(&super::UpdateOptions::default())?,
+            vec![
+                "availability!=?subscriber_only & availability!=?premium_only & availability!=?needs_auth"
+            ]
+        );
+
+        assert!(match_filters(&super::UpdateOptions {
+            include_members_only: true,
+            ..Default::default()
+        })?
+        .is_empty());
+
+        // Given cookies, they're attempted
+        assert!(match_filters(&super::UpdateOptions {
+            additional_args: vec!["--cookies-from-browser".to_string(), "firefox".to_string()],
+            ..Default::default()
+        })?
+        .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_feed() -> Result<(), Error> {
+        use std::io::BufReader;
+        use url::Url;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let mut channel = super::Channel::new_with_reader(
+            std::path::Path::new("mightycarmods.xml").to_path_buf(),
+            BufReader::new(&bytes[0..]),
+        )?;
+
+        // A second feed with a newer video, and a differently titled copy of the first feed's
+        let mut duplicate = get_new_video();
+        duplicate.id = "Wqww1B9wljA".to_string();
+        duplicate.title = Some("A Duplicate".to_string());
+
+        let mut other = get_new_channel()?;
+        other.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                skip_download: true,
+                ..Default::default()
+            },
+            get_playlist(vec![get_new_video(), duplicate]),
+        )?;
+        let other = other.rss_channel.unwrap();
+        assert_eq!(other.items.len(), 2);
+
+        assert_eq!(channel.merge_feed(&other, &Default::default()), 1);
+
+        let rss_channel = channel.rss_channel.as_ref().unwrap();
+        assert_eq!(
+            get_item_ids(rss_channel),
+            vec!["QWkUFkXcx9I", "Wqww1B9wljA"]
+        );
+        assert_eq!(
+            rss_channel.items[1].title(),
+            Some("Trying to Guess Marty's New Car (Blindfolded!)")
+        );
+
+        // Merging again adds nothing, but sorts as the options order the feed
+        let options = super::UpdateOptions {
+            order_by: Some(super::OrderBy::Title),
+            ..Default::default()
+        };
+        assert_eq!(channel.merge_feed(&other, &options), 0);
+        assert_eq!(
+            get_item_ids(channel.rss_channel.as_ref().unwrap()),
+            vec!["QWkUFkXcx9I", "Wqww1B9wljA"]
+        );
+
+        let options = super::UpdateOptions {
+            order_by: Some(super::OrderBy::Title),
+            order_dir: Some(super::OrderDirection::Desc),
+            ..Default::default()
+        };
+        channel.merge_feed(&other, &options);
+        assert_eq!(
+            get_item_ids(channel.rss_channel.as_ref().unwrap()),
+            vec!["Wqww1B9wljA", "QWkUFkXcx9I"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_downloader_args_sleep() -> Result<(), Error> {
+        let channel = get_new_channel()?;
+
+        let args = channel.downloader_args(&super::UpdateOptions::default())?;
+        assert!(!args.iter().any(|arg| arg.starts_with("--sleep")));
+
+        let args = channel.downloader_args(&super::UpdateOptions {
+            sleep_interval: Some(5.0),
+            max_sleep_interval: Some(30.0),
+            sleep_requests: Some(0.75),
+            ..Default::default()
+        })?;
+
+        for (flag, value) in [
+            ("--sleep-interval", "5"),
+            ("--max-sleep-interval", "30"),
+            ("--sleep-requests", "0.75"),
+        ] {
+            let position = args.iter().position(|arg| arg == flag).unwrap();
+            assert_eq!(args[position + 1], value);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_version_report() {
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![]),
+        )));
+
+        let json = |downloader: &MockDownloader| {
+            serde_json::to_value(super::VersionReport::new(downloader)).unwrap()
+        };
+
+        assert_eq!(
+            json(&downloader),
+            serde_json::json!({
+                "playcaster": super::PKG_VERSION,
+                "yt_dlp": null,
+                "yt_dlp_ok": false,
+            })
+        );
+
+        assert_eq!(
+            json(&downloader.with_version("2024.08.06")),
+            serde_json::json!({
+                "playcaster": super::PKG_VERSION,
+                "yt_dlp": "2024.08.06",
+                "yt_dlp_ok": true,
+            })
+        );
+
+        assert!(super::yt_dlp_version_ok("2022.04.08"));
+        assert!(super::yt_dlp_version_ok("2024.08.06.232908"));
+        assert!(!super::yt_dlp_version_ok("2021.12.27"));
+        assert!(!super::yt_dlp_version_ok("unknown"));
+    }
+
+    #[test]
+    fn test_update_with_order_by_duration() -> Result<(), Error> {
+        use url::Url;
+
+        let video = |id: &str, duration: u64| youtube_dl::SingleVideo {
+            id: id.to_string(),
+            duration: Some(serde_json::json!(duration)),
+            ..get_new_video()
+        };
+
+        let playlist = get_playlist(vec![
+            video("short", 60),
+            video("long", 600),
+            video("medium", 300),
+        ]);
+
+        let order = |order_dir| -> Result<Vec<String>, Error> {
+            let mut channel = get_new_channel()?;
+            channel.update_with_playlist(
+                Url::parse("http://localhost:8080").unwrap(),
+                &super::UpdateOptions {
+                    order_by: Some(super::OrderBy::Duration),
+                    order_dir,
+                    skip_download: true,
+                    ..Default::default()
+                },
+                playlist.clone(),
+            )?;
+            Ok(get_item_ids(channel.rss_channel.as_ref().unwrap()))
+        };
+
+        assert_eq!(order(None)?, vec!["long", "medium", "short"]);
+        assert_eq!(
+            order(Some(super::OrderDirection::Desc))?,
+            vec!["long", "medium", "short"]
+        );
+        assert_eq!(
+            order(Some(super::OrderDirection::Asc))?,
+            vec!["short", "medium", "long"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_episode_numbers() -> Result<(), Error> {
+        use url::Url;
+
+        let video = |id: &str, playlist_index: Option<serde_json::Value>| youtube_dl::SingleVideo {
+            id: id.to_string(),
+            playlist_index,
+            ..get_new_video()
+        };
+
+        let mut channel = get_new_channel()?;
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                episode_numbers: true,
+                order: super::SourceOrder::Playlist,
+                skip_download: true,
+                ..Default::default()
+            },
+            get_playlist(vec![
+                video("first", Some(serde_json::json!(1))),
+                video("unknown", None),
+                video("third", Some(serde_json::json!("3"))),
+                video("zero", Some(serde_json::json!(0))),
+            ]),
+        )?;
+
+        let episodes: Vec<_> = channel
+            .rss_channel
+            .as_ref()
+            .unwrap()
+            .items
+            .iter()
+            .map(|item| item.itunes_ext().unwrap().episode())
+            .collect();
+
+        // Entries without a usable index are left unnumbered, rather than numbered zero
+        assert_eq!(episodes, vec![Some("1"), None, Some("3"), None]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_append_history() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("history");
+        std::fs::create_dir_all(&temp_dir)?;
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+        let history_file = channel.history_file();
+        assert_eq!(history_file, temp_dir.join("mightycarmods.history.jsonl"));
+
+        let mut older = get_new_video();
+        older.id = "Wqww1B9wljA".to_string();
+
+        for entries in [vec![older.clone()], vec![get_new_video(), older]] {
+            let summary = channel.update_with_playlist(
+                Url::parse("http://localhost:8080").unwrap(),
+                &super::UpdateOptions {
+                    skip_download: true,
+                    ..Default::default()
+                },
+                get_playlist(entries),
+            )?;
+
+            super::append_history(
+                &history_file,
+                &super::ChannelSummary {
+                    feed_file: channel.feed_file.clone(),
+                    playlist_url: channel.playlist_url.clone(),
+                    summary,
+                },
+            )?;
+        }
+
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&history_file)?
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+
+        let totals: Vec<_> = lines.iter().map(|line| line["total"].as_u64()).collect();
+        assert_eq!(totals, vec![Some(1), Some(2)]);
+
+        for line in &lines {
+            assert!(
+                chrono::DateTime::parse_from_rfc3339(line["timestamp"].as_str().unwrap()).is_ok()
+            );
+        }
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_playlist_url() -> Result<(), Error> {
+        use std::io::BufReader;
+        use url::Url;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let mut channel = super::Channel::new_with_reader(
+            std::path::Path::new("mightycarmods.xml").to_path_buf(),
+            BufReader::new(&bytes[0..]),
+        )?;
+        let link = channel.rss_channel.as_ref().unwrap().link.clone();
+
+        let new_url = Url::parse("https://www.youtube.com/@mightycarmods/shorts").unwrap();
+        channel.set_playlist_url(new_url.clone())?;
+        assert_eq!(channel.playlist_url, new_url);
+
+        // The feed itself is untouched until the next update
+        assert_eq!(channel.rss_channel.as_ref().unwrap().link, link);
+
+        assert!(matches!(
+            channel.set_playlist_url(Url::parse("file:///etc/passwd").unwrap()),
+            Err(Error::PlaylistUrlError(_))
+        ));
+        assert_eq!(channel.playlist_url, new_url);
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )));
+        channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                skip_download: true,
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+        assert_eq!(*downloader.urls.borrow(), vec![new_url]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_fallback_format() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("fallback-format");
+        let media_dir = temp_dir.join("mightycarmods");
+
+        // Only VP9 was available, so the `/best` fallback downloaded WebM rather than MP4
+        let mut video = get_new_video();
+        video.ext = Some("webm".to_string());
+        video.vcodec = Some("vp9".to_string());
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![video]),
+        )))
+        .with_files(vec![media_dir.join("QWkUFkXcx9I.webm")]);
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+        let summary = channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            &downloader,
+        )?;
+        assert_eq!(summary.downloaded, 1);
+
+        let item = &channel.rss_channel.as_ref().unwrap().items[0];
+        let enclosure = item.enclosure().unwrap();
+        assert_eq!(
+            enclosure.url(),
+            "http://localhost:8080/mightycarmods/QWkUFkXcx9I.webm"
+        );
+        assert_eq!(enclosure.mime_type(), "video/webm");
+        assert_eq!(super::MediaFormat::of_item(item), super::MediaFormat::WEBM);
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_keep_files() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("keep-files");
+        let media_dir = temp_dir.join("mightycarmods");
+        std::fs::create_dir_all(&media_dir)?;
+
+        let videos: Vec<_> = (1..=3)
+            .map(|day| youtube_dl::SingleVideo {
+                id: format!("video{}", day),
+                timestamp: Some(1_640_995_200.0 + day as f64 * 86_400.0),
+                ..get_new_video()
+            })
+            .collect();
+
+        for video in &videos {
+            std::fs::write(media_dir.join(format!("{}.mp4", video.id)), b"media")?;
+        }
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                keep: Some(3),
+                keep_files: Some(1),
+                ..Default::default()
+            },
+            get_playlist(videos),
+        )?;
+
+        // Every item stays in the feed, but only the newest keeps its file
+        assert_eq!(
+            get_item_ids(channel.rss_channel.as_ref().unwrap()),
+            vec!["video3", "video2", "video1"]
+        );
+        let files: Vec<_> = std::fs::read_dir(&media_dir)?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(files, vec!["video3.mp4"]);
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_invalid_feed_preserves_feed() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("write-invalid");
+        std::fs::create_dir_all(&temp_dir)?;
+
+        let feed_file = temp_dir.join("mightycarmods.xml");
+        let json_file = temp_dir.join("mightycarmods.json");
+        std::fs::write(&feed_file, b"")?;
+
+        let mut channel = super::Channel::new_with_url(
+            feed_file.clone(),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                skip_download: true,
+                ..Default::default()
+            },
+            get_playlist(vec![get_new_video()]),
+        )?;
+
+        let options = super::WriteOptions {
+            formats: vec![super::OutputFormat::Rss, super::OutputFormat::Jsonfeed],
+            ..Default::default()
+        };
+        channel.write(&options)?;
+
+        let written = std::fs::read(&feed_file)?;
+        let written_json = std::fs::read(&json_file)?;
+
+        // An update which leaves the feed invalid
+        channel.rss_channel.as_mut().unwrap().items[0].set_pub_date("not a date".to_string());
+
+        assert!(matches!(
+            channel.write(&options),
+            Err(Error::FeedValidationError(ref path, _)) if *path == feed_file
+        ));
+
+        // Neither output was touched, nor was a temporary file left behind
+        assert_eq!(std::fs::read(&feed_file)?, written);
+        assert_eq!(std::fs::read(&json_file)?, written_json);
+        assert_eq!(std::fs::read_dir(&temp_dir)?.count(), 2);
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_created_and_deleted_files() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("created-deleted");
+        let media_dir = temp_dir.join("mightycarmods");
+        std::fs::create_dir_all(&media_dir)?;
+
+        let mut older = get_new_video();
+        older.id = "Wqww1B9wljA".to_string();
+        older.timestamp = Some(1_600_000_000.0);
+        std::fs::write(media_dir.join("Wqww1B9wljA.mp4"), b"old")?;
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![older]),
+        )?;
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )))
+        .with_files(vec![
+            media_dir.join("QWkUFkXcx9I.mp4"),
+            media_dir.join("QWkUFkXcx9I.en.vtt"),
+        ]);
+
+        let summary = channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                keep: Some(1),
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+
+        assert_eq!(
+            summary.created_files,
+            vec![
+                media_dir.join("QWkUFkXcx9I.en.vtt"),
+                media_dir.join("QWkUFkXcx9I.mp4")
+            ]
+        );
+        assert_eq!(
+            summary.deleted_files,
+            vec![media_dir.join("Wqww1B9wljA.mp4")]
+        );
+        assert!(!media_dir.join("Wqww1B9wljA.mp4").exists());
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_min_views() -> Result<(), Error> {
+        use url::Url;
+
+        let video = |id: &str, view_count: Option<i64>| youtube_dl::SingleVideo {
+            id: id.to_string(),
+            view_count,
+            ..get_new_video()
+        };
+
+        let playlist = get_playlist(vec![
+            video("popular", Some(5000)),
+            video("threshold", Some(1000)),
+            video("unpopular", Some(999)),
+            video("unknown", None),
+        ]);
+
+        let update = |include_unknown_views| -> Result<(Vec<String>, Vec<String>), Error> {
+            let mut channel = get_new_channel()?;
+            let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+                playlist.clone(),
+            )));
+
+            channel.update_with_downloader(
+                Url::parse("http://localhost:8080").unwrap(),
+                &super::UpdateOptions {
+                    min_views: Some(1000),
+                    include_unknown_views,
+                    include_members_only: true,
+                    order: super::SourceOrder::Playlist,
+                    skip_download: true,
+                    ..Default::default()
+                },
+                &downloader,
+            )?;
+
+            let invocation = downloader.invocations.borrow()[0].clone();
+            let match_filters = invocation
+                .iter()
+                .enumerate()
+                .filter(|(_, arg)| *arg == "--match-filter")
+                .map(|(position, _)| invocation[position + 1].clone())
+                .collect();
+
+            Ok((
+                get_item_ids(channel.rss_channel.as_ref().unwrap()),
+                match_filters,
+            ))
+        };
+
+        let (ids, match_filters) = update(false)?;
+        assert_eq!(ids, vec!["popular", "threshold"]);
+        assert_eq!(match_filters, vec!["view_count >= 1000"]);
+
+        let (ids, match_filters) = update(true)?;
+        assert_eq!(ids, vec!["popular", "threshold", "unknown"]);
+        assert_eq!(match_filters, vec!["view_count >=? 1000"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_skip_durations() -> Result<(), Error> {
+        use url::Url;
+
+        let video = |id: &str, duration: f64| youtube_dl::SingleVideo {
+            id: id.to_string(),
+            duration: Some(serde_json::Value::from(duration)),
+            ..get_new_video()
+        };
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![
+                video("episode", 706.0),
+                video("placeholder", 43200.0),
+                video("almost", 43200.5),
+                video("stream", 3600.0),
+            ]),
+        )));
+
+        let mut channel = get_new_channel()?;
+        channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                skip_durations: vec![43200, 3600, 43200],
+                include_members_only: true,
+                order: super::SourceOrder::Playlist,
+                skip_download: true,
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+
+        assert_eq!(
+            get_item_ids(channel.rss_channel.as_ref().unwrap()),
+            vec!["episode", "almost"]
+        );
+
+        let invocation = downloader.invocations.borrow()[0].clone();
+        let position = invocation
+            .iter()
+            .position(|arg| arg == "--match-filter")
+            .unwrap();
+        assert_eq!(
+            invocation[position + 1],
+            "duration !=? 43200 & duration !=? 3600"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_guids() -> Result<(), Error> {
+        use std::io::BufReader;
+        use url::Url;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let mut channel = super::Channel::new_with_reader(
+            std::path::Path::new("mightycarmods.xml").to_path_buf(),
+            BufReader::new(&bytes[0..]),
+        )?;
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                skip_download: true,
+                ..Default::default()
+            },
+            get_playlist(vec![get_new_video()]),
+        )?;
+
+        let before = channel.rss_channel.clone().unwrap();
+
+        let scheme: super::GuidScheme = "prefix:mcm-".parse().unwrap();
+        assert_eq!(channel.migrate_guids(&scheme), 2);
+
+        let after = channel.rss_channel.as_ref().unwrap();
+        assert_eq!(after.items.len(), before.items.len());
+
+        for (item, original) in after.items.iter().zip(&before.items) {
+            let guid = item.guid().unwrap();
+            assert_eq!(
+                guid.value(),
+                format!("mcm-{}", original.guid().unwrap().value())
+            );
+            assert!(!guid.is_permalink());
+            assert_eq!(item.pub_date(), original.pub_date());
+            assert_eq!(item.enclosure(), original.enclosure());
+        }
+
+        // Migrating to the same scheme again changes nothing
+        assert_eq!(channel.migrate_guids(&scheme), 0);
+
+        // New items follow the scheme too
+        let mut newer = get_new_video();
+        newer.id = "newer".to_string();
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                guid_scheme: Some(scheme),
+                skip_download: true,
+                ..Default::default()
+            },
+            get_playlist(vec![get_new_video(), newer]),
+        )?;
+        let rss_channel = channel.rss_channel.as_ref().unwrap();
+        assert_eq!(rss_channel.items.len(), 3);
+        assert!(rss_channel.items.iter().all(|item| item
+            .guid()
+            .unwrap()
+            .value()
+            .starts_with("mcm-")));
+
+        assert!("prefix:".parse::<super::GuidScheme>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exit_code() {
+        use url::Url;
+
+        let channel_summary = |added| super::ChannelSummary {
+            feed_file: std::path::PathBuf::from("mightycarmods.xml"),
+            playlist_url: Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+            summary: super::UpdateSummary {
+                added,
+                ..Default::default()
+            },
+        };
+
+        let unchanged = vec![channel_summary(0), channel_summary(0)];
+        assert_eq!(super::exit_code(&unchanged, false), 0);
+        assert_eq!(
+            super::exit_code(&unchanged, true),
+            super::NO_NEW_ITEMS_EXIT_CODE
+        );
+
+        let changed = vec![channel_summary(0), channel_summary(2)];
+        assert_eq!(super::exit_code(&changed, false), 0);
+        assert_eq!(super::exit_code(&changed, true), 0);
+    }
+
+    #[test]
+    fn test_mismatched_formats() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("mismatched-formats");
+        let playlist_url = Url::parse("https://www.youtube.com/c/mightycarmods").unwrap();
+        let both = super::WriteOptions {
+            formats: vec![super::OutputFormat::Rss, super::OutputFormat::Jsonfeed],
+            ..Default::default()
+        };
+
+        for name in ["feed.xml", "feed.RSS"] {
+            let channel = super::Channel::new_with_url(temp_dir.join(name), playlist_url.clone())?;
+            assert_eq!(channel.mismatched_formats(&both), vec![]);
+        }
+
+        let mut channel =
+            super::Channel::new_with_url(temp_dir.join("feed.rss"), playlist_url.clone())?;
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions::default(),
+            get_playlist(vec![get_new_video()]),
+        )?;
+        channel.write(&Default::default())?;
+
+        // The extension doesn't change what's written
+        let rss_channel = rss::Channel::read_from(std::io::BufReader::new(std::fs::File::open(
+            temp_dir.join("feed.rss"),
+        )?))?;
+        assert_eq!(get_item_ids(&rss_channel), vec!["QWkUFkXcx9I"]);
+
+        // An RSS feed written to any other extension is warned about, but still written
+        let warnings = captured_warnings();
+        let thread = std::thread::current().id();
+
+        channel.feed_file = temp_dir.join("feed.txt");
+        assert_eq!(
+            channel.mismatched_formats(&both),
+            vec![super::OutputFormat::Rss]
+        );
+        channel.write(&both)?;
+
+        let feed_warnings: Vec<_> = warnings
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(id, warning)| *id == thread && warning.contains("feed.txt"))
+            .map(|(_, warning)| warning.clone())
+            .collect();
+        assert_eq!(feed_warnings.len(), 1);
+        assert!(feed_warnings[0].contains("Writing the rss feed to"));
+        assert!(temp_dir.join("feed.txt").exists());
+        assert!(temp_dir.join("feed.json").exists());
+
+        // A `json` feed file would be overwritten by its JSON Feed, so writing both is refused
+        std::fs::remove_file(temp_dir.join("feed.json"))?;
+        channel.feed_file = temp_dir.join("feed.json");
+        assert!(matches!(
+            channel.write(&both),
+            Err(Error::OutputFileCollisionError(ref path)) if *path == channel.feed_file
+        ));
+        assert!(!temp_dir.join("feed.json").exists());
+
+        // Though on its own, it's only a mismatch
+        assert_eq!(
+            channel.mismatched_formats(&Default::default()),
+            vec![super::OutputFormat::Rss]
+        );
+        channel.write(&Default::default())?;
+        assert!(
+            rss::Channel::read_from(std::io::BufReader::new(std::fs::File::open(
+                temp_dir.join("feed.json"),
+            )?))
+            .is_ok()
+        );
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_channel_elements() -> Result<(), Error> {
+        use std::str::FromStr;
+        use url::Url;
+
+        assert!(super::CustomElement::from_str("spotify=us").is_err());
+        assert!(super::CustomElement::from_str(":countryOfOrigin=us").is_err());
+
+        let options = super::UpdateOptions {
+            channel_elements: vec![
+                super::CustomElement::from_str("spotify:countryOfOrigin=us").unwrap(),
+                super::CustomElement {
+                    prefix: "podcast".to_string(),
+                    name: "locked".to_string(),
+                    value: Some("yes".to_string()),
+                    attrs: std::collections::BTreeMap::from([(
+                        "owner".to_string(),
+                        "mods@example.com".to_string(),
+                    )]),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let mut channel = get_new_channel()?;
+
+        assert!(matches!(
+            channel.update_with_playlist(
+                Url::parse("http://localhost:8080").unwrap(),
+                &options,
+                get_playlist(vec![get_new_video()]),
+            ),
+            Err(super::Error::UndeclaredNamespaceError(prefix)) if prefix == "spotify"
+        ));
+
+        let options = super::UpdateOptions {
+            namespaces: vec![(
+                "spotify".to_string(),
+                "http://www.spotify.com/ns/rss".to_string(),
+            )],
+            ..options
+        };
+
+        // Updating twice leaves a single copy of each element
+        for _ in 0..2 {
+            channel.update_with_playlist(
+                Url::parse("http://localhost:8080").unwrap(),
+                &options,
+                get_playlist(vec![get_new_video()]),
+            )?;
+        }
+
+        let mut buffer = Vec::new();
+        channel.write_to(&mut buffer, &Default::default())?;
+        let xml = String::from_utf8(buffer).unwrap();
+
+        assert!(xml.contains("xmlns:spotify=\"http://www.spotify.com/ns/rss\""));
+        assert_eq!(
+            xml.matches("<spotify:countryOfOrigin>us</spotify:countryOfOrigin>")
+                .count(),
+            1
+        );
+        assert!(xml.contains("<podcast:locked owner=\"mods@example.com\">yes</podcast:locked>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_item_overrides() -> Result<(), Error> {
+        use url::Url;
+
+        let item_overrides = super::read_item_overrides(
+            r#"{
+                "QWkUFkXcx9I": {
+                    "elements": [{
+                        "prefix": "podcast",
+                        "name": "soundbite",
+                        "value": "The big reveal",
+                        "attrs": {"startTime": "73.0", "duration": "60.0"}
+                    }]
+                }
+            }"#
+            .as_bytes(),
+        )?;
+
+        assert!(super::read_item_overrides(r#"{"QWkUFkXcx9I": {"tags": []}}"#.as_bytes()).is_err());
+
+        let mut channel = get_new_channel()?;
+
+        for _ in 0..2 {
+            channel.update_with_playlist(
+                Url::parse("http://localhost:8080").unwrap(),
+                &super::UpdateOptions {
+                    item_overrides: item_overrides.clone(),
+                    ..Default::default()
+                },
+                get_playlist(vec![get_new_video()]),
+            )?;
+        }
+
+        let rss_channel = channel.rss_channel.as_ref().unwrap();
+        let overridden = rss_channel
+            .items
+            .iter()
+            .find(|item| item.guid().unwrap().value() == "QWkUFkXcx9I")
+            .unwrap();
+
+        let soundbites = &overridden.extensions["podcast"]["soundbite"];
+        assert_eq!(soundbites.len(), 1);
+        assert_eq!(soundbites[0].value(), Some("The big reveal"));
+        assert_eq!(soundbites[0].attrs()["startTime"], "73.0");
+
+        assert!(rss_channel
+            .items
+            .iter()
+            .filter(|item| item.guid().unwrap().value() != "QWkUFkXcx9I")
+            .all(|item| !item.extensions.contains_key("podcast")));
+
+        let mut buffer = Vec::new();
+        channel.write_to(&mut buffer, &Default::default())?;
+        let xml = String::from_utf8(buffer).unwrap();
+
+        assert!(xml.contains("xmlns:podcast=\"https://podcastindex.org/namespace/1.0\""));
+        assert!(xml.contains(
+            "<podcast:soundbite duration=\"60.0\" startTime=\"73.0\">The big reveal</podcast:soundbite>"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_clean_stale_partials() -> Result<(), Error> {
+        use std::time::{Duration, SystemTime};
+        use url::Url;
+
+        let temp_dir = get_temp_dir("clean-stale-partials");
+        let media_dir = temp_dir.join("mightycarmods");
+        std::fs::create_dir_all(&media_dir)?;
+
+        let two_days_ago = SystemTime::now() - Duration::from_secs(2 * 24 * 60 * 60);
+
+        for name in [
+            "QWkUFkXcx9I.mp4.part",
+            "QWkUFkXcx9I.mp4.ytdl",
+            "QWkUFkXcx9I.f137.mp4.part-Frag12",
+            "Wqww1B9wljA.mp4",
+        ] {
+            std::fs::File::create(media_dir.join(name))?.set_modified(two_days_ago)?;
+        }
+
+        // Still being downloaded, perhaps by another run
+        std::fs::write(media_dir.join("Wqww1B9wljA.webm.part"), b"")?;
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )));
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+
+        channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                clean_stale_partials: Some(Duration::from_secs(24 * 60 * 60)),
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+
+        let mut remaining: Vec<_> = std::fs::read_dir(&media_dir)?
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        remaining.sort();
+
+        assert_eq!(remaining, vec!["Wqww1B9wljA.mp4", "Wqww1B9wljA.webm.part"]);
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_max_new_per_run() -> Result<(), Error> {
+        use url::Url;
+
+        let videos = (0..10)
+            .map(|index| youtube_dl::SingleVideo {
+                id: format!("video{}", index),
+                playlist_index: None,
+                ..get_new_video()
+            })
+            .collect();
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(videos),
+        )));
+
+        let mut channel = get_new_channel()?;
+
+        let summary = channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                max_new_per_run: Some(3),
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+
+        assert_eq!(summary.added, 3);
+        assert_eq!(
+            get_item_ids(channel.rss_channel.as_ref().unwrap()),
+            vec!["video0", "video1", "video2"]
+        );
+
+        // The playlist is listed flat first, then only the first three new videos are picked
+        let invocations = downloader.invocations.borrow();
+        assert_eq!(invocations.len(), 2);
+        assert!(invocations[0].contains(&"--flat-playlist".to_string()));
+        assert!(!invocations[0].contains(&"--no-simulate".to_string()));
+
+        let playlist_items = |invocation: &Vec<String>| {
+            invocation
+                .iter()
+                .position(|arg| arg == "--playlist-items")
+                .map(|index| invocation[index + 1].clone())
+        };
+        assert_eq!(playlist_items(&invocations[1]), Some("1,2,3".to_string()));
+        assert!(!invocations[1].iter().any(|arg| arg.contains("id!=")));
+        drop(invocations);
+
+        // Videos already in the feed are picked as well, by their index when `yt-dlp` gives one
+        let videos = (0..10)
+            .map(|index| youtube_dl::SingleVideo {
+                id: format!("video{}", index),
+                playlist_index: Some(serde_json::json!(index + 11)),
+                ..get_new_video()
+            })
+            .collect();
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(videos),
+        )));
+
+        channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                max_new_per_run: Some(2),
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+
+        assert_eq!(
+            playlist_items(&downloader.invocations.borrow()[1]),
+            Some("11,12,13,14,15".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() -> Result<(), Error> {
+        use std::io::BufReader;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let mut channel = super::Channel::new_with_reader(
+            std::path::PathBuf::from("mightycarmods.xml"),
+            BufReader::new(&bytes[0..]),
+        )?;
+
+        channel.validate()?;
+
+        // A channel's link is required
+        channel.rss_channel.as_mut().unwrap().set_link("");
+
+        let error = channel.validate().unwrap_err();
+        assert!(
+            matches!(error, super::Error::FeedValidationError(ref path, _) if path == "mightycarmods.xml")
+        );
+        assert!(error
+            .to_string()
+            .starts_with("feed \"mightycarmods.xml\" is invalid"));
+
+        channel.rss_channel = None;
+        assert!(matches!(
+            channel.validate(),
+            Err(super::Error::NoFeedError(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_default_args() -> Result<(), Error> {
+        use url::Url;
+
+        let mut additional_args =
+            super::split_default_args(" --proxy socks5://127.0.0.1:1080\t--limit-rate 2M ");
+
+        additional_args.push("--limit-rate".to_string());
+        additional_args.push("4M".to_string());
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )));
+
+        get_new_channel()?.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                additional_args,
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+
+        let invocations = downloader.invocations.borrow();
+        let args = &invocations[0];
+        let position = |needle: &str| args.iter().position(|arg| arg == needle).unwrap();
+
+        assert_eq!(args[position("--proxy") + 1], "socks5://127.0.0.1:1080");
+        assert_eq!(args[position("2M") - 1], "--limit-rate");
+        assert!(position("--format") < position("--proxy"));
+        assert!(position("2M") < position("4M"));
+
+        assert!(super::split_default_args(" \t\n").is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_description() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("description");
+        let description_file = temp_dir.join("description.html");
+        std::fs::write(
+            &description_file,
+            "<p>Cars, mods and <strong>mates</strong>.</p>\n<p>New episodes weekly.</p>",
+        )?;
+
+        let description = std::fs::read_to_string(&description_file)?;
+        let options = super::UpdateOptions {
+            description: Some(description.clone()),
+            ..Default::default()
+        };
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &options,
+            get_playlist(vec![get_new_video()]),
+        )?;
+
+        assert_eq!(
+            channel.rss_channel.as_ref().unwrap().description(),
+            description
+        );
+
+        // An existing feed keeps its description unless syncing it
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let mut channel = super::Channel::new_with_reader(
+            temp_dir.join("mightycarmods.xml"),
+            std::io::BufReader::new(&bytes[0..]),
+        )?;
+        let original = channel
+            .rss_channel
+            .as_ref()
+            .unwrap()
+            .description()
+            .to_string();
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &options,
+            get_playlist(vec![get_new_video()]),
+        )?;
+
+        assert_eq!(
+            channel.rss_channel.as_ref().unwrap().description(),
+            original
+        );
+
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                sync_description: true,
+                ..options
+            },
+            get_playlist(vec![get_new_video()]),
+        )?;
+
+        assert_eq!(
+            channel.rss_channel.as_ref().unwrap().description(),
+            description
+        );
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_remote_enclosures() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("remote-enclosures");
+        let options = super::UpdateOptions {
+            remote_enclosures: true,
             ..Default::default()
+        };
+
+        let remote_video = |signature: &str| youtube_dl::SingleVideo {
+            url: Some(format!(
+                "https://media.example.com/videoplayback?sig={}",
+                signature
+            )),
+            filesize: Some(1234),
+            ..get_new_video()
+        };
+
+        // Merged formats have no single URL to point at
+        let merged_video = youtube_dl::SingleVideo {
+            id: "merged".to_string(),
+            url: None,
+            ..get_new_video()
+        };
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![remote_video("first"), merged_video]),
+        )));
+
+        let summary = channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &options,
+            &downloader,
+        )?;
+
+        assert_eq!(summary.invalid_enclosures, vec!["merged"]);
+        assert_eq!(summary.downloaded, 0);
+
+        let args = &downloader.invocations.borrow()[0];
+        assert!(!args.contains(&"--no-simulate".to_string()));
+        assert_eq!(
+            args[1],
+            "best[ext=mp4][vcodec^=avc1]/best[ext=mp4]/best".to_string()
+        );
+
+        let enclosure = |channel: &super::Channel| {
+            channel.rss_channel.as_ref().unwrap().items[0]
+                .enclosure()
+                .cloned()
+                .unwrap()
+        };
+
+        assert_eq!(
+            enclosure(&channel).url(),
+            "https://media.example.com/videoplayback?sig=first#QWkUFkXcx9I"
+        );
+        assert_eq!(enclosure(&channel).length(), "1234");
+        assert!(!temp_dir.join("mightycarmods").exists());
+
+        // The next update refreshes the expiring URL, rather than adding the video again
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &options,
+            get_playlist(vec![remote_video("second")]),
+        )?;
+
+        assert_eq!(
+            get_item_ids(channel.rss_channel.as_ref().unwrap()),
+            vec!["QWkUFkXcx9I"]
+        );
+        assert_eq!(
+            enclosure(&channel).url(),
+            "https://media.example.com/videoplayback?sig=second#QWkUFkXcx9I"
+        );
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_with_repeated_entries() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("repeated-entries");
+        let media_dir = temp_dir.join("mightycarmods");
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video(), get_new_video()]),
+        )))
+        .with_files(vec![media_dir.join("QWkUFkXcx9I.mp4")]);
+
+        for no_dedup in [false, true] {
+            let mut channel = super::Channel::new_with_url(
+                temp_dir.join("mightycarmods.xml"),
+                Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+            )?;
+
+            let summary = channel.update_with_downloader(
+                Url::parse("http://localhost:8080").unwrap(),
+                &super::UpdateOptions {
+                    no_dedup,
+                    ..Default::default()
+                },
+                &downloader,
+            )?;
+
+            let item_count = channel.rss_channel.unwrap().items.len();
+            assert_eq!(item_count, if no_dedup { 2 } else { 1 });
+
+            // Repeats share a file, so it's only counted once either way
+            assert_eq!(summary.downloads.len(), 1);
+            assert_eq!(summary.downloads[0].id, "QWkUFkXcx9I");
         }
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
     }
 
-    fn get_duplicate_video() -> youtube_dl::SingleVideo {
-        youtube_dl::SingleVideo {
-            abr: Some(129.478),
-            acodec: Some("mp4a.40.2".to_string()),
-            age_limit: Some(0),
-            album: None,
-            album_artist: None,
-            album_type: None,
-            alt_title: None,
-            artist: None,
-            asr: Some(44100.0),
-            automatic_captions: None,
-            average_rating: None,
-            categories: None,
-            channel: Some("Mighty Car Mods".to_string()),
-            channel_id: Some("UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
-            channel_url: Some("https://www.youtube.com/channel/UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
-            chapter: None,
-            chapter_id: None,
-            chapter_number: None,
-            chapters: None,
-            comment_count: None,
-            comments: None,
-            container: None,
-            creator: None,
-            description: Some("Do you wanna make your car better, more enjoyable for car meets, off-roading, road trips or just day to day commuting? Then this simple modification is for you! \n\nAs the best 4X4 channel in the world, we'll show you exactly what you need to do to get the job done! \n\nYou can use this additional power to run a fridge, audio equipment, charging systems or even a travel oven. \n\nMERCH SHOWN IN THIS EPISODE\n\n►MCM Travel Mug\nhttps://mightycarmods.com/collections/accessories/products/travel-mug\n\n►Enamel Workshop Mug\nhttps://mightycarmods.com/collections/accessories/products/enamel-workshop-mug\n\n►Chopped Ceramic Mug\nhttps://mightycarmods.com/collections/accessories/products/chopped-mug\n\n►Insulated Drink Bottle\nhttps://mightycarmods.com/collections/accessories/products/mcm-drink-bottle\n\n►Fender Covers\nhttps://mightycarmods.com/collections/accessories/products/fender-cover-2-pack\n\n►Microfibre Cloths\nhttps://mightycarmods.com/collections/accessories/products/mighty-car-mods-microfibre-cloth-4-pack\n\n►MCM Ear Muffs\nhttps://mightycarmods.com/collections/accessories/products/ear-muffs\n\n►MCM BOOK [ULTIMATE EDITION] Autographed\nhttps://mightycarmods.com/collections/books/products/copy-of-the-cars-of-mighty-car-mods-ultimate-edition-hardcover-book\n\nMORE MERCH HERE ►http://www.mightycarmods.com/collections/\n\nBe sure to check out the MCM Facebook Page for regular Updates\n►http://www.facebook.com/mightycarmods\n\n✌\u{fe0f} JDM Air Fresheners https://mightycarmods.com/collections/accessories/products/mcm-car-air-freshener \n👕 BRAND NEW EXPLODED TURBO SHIRT\nhttps://mightycarmods.com/collections/clothing/products/exploded-turbo-shirt\nLIMITED EDITION MCM BOOK (SIGNED)\nhttps://mightycarmods.com/collections/books/products/copy-of-the-cars-of-mighty-car-mods-ultimate-edition-hardcover-book\n\n🔔 Hit the bell next to Subscribe so you don't miss a video!\n\nAlso something to note around Mighty Car Mods: we are normal guys and are not trained mechanics. We like to make interesting car mods and show you how we've gone about it, but we can't promise that anything we show you will work for your particular car, or that you won't harm yourself, someone else, your car or your warranty doing it. Please be safe, be responsible and unless you know what you're doing, do not fool around with very serious machinery just because you've seen us make it look so easy. Talk to a qualified mechanic if you are in any doubt. Some of the products featured in this video may be supplied by sponsors. For a list of our current sponsors please go to mightycarmods.com".to_string()),
-            disc_number: None,
-            dislike_count: None,
-            display_id: Some("Wqww1B9wljA".to_string()),
-            downloader_options: None,
-            duration: Some(serde_json::Value::Number(serde_json::Number::from_f64(706.0).unwrap())),
-            end_time: None,
-            episode: None,
-            episode_id: None,
-            episode_number: None,
-            ext: Some("mp4".to_string()),
-            extractor: Some("youtube".to_string()),
-            extractor_key: Some("Youtube".to_string()),
-            filesize: None,
-            filesize_approx: Some(212973334.0),
-            format: Some("137 - 1920x1080 (1080p)+140 - audio only (medium)".to_string()),
-            format_id: Some("137+140".to_string()),
-            format_note: Some("1080p+medium".to_string()),
-            formats: None,
-            fps: Some(25.0),
-            fragment_base_url: None,
-            fragments: None,
-            genre: None,
-            height: Some(1080.0),
-            http_headers: None,
-            id: "QWkUFkXcx9I".to_string(),
-            is_live: Some(false),
-            language: None,
-            language_preference: None,
-            license: None,
-            like_count: Some(16399),
-            location: None,
-            manifest_url: None,
-            no_resume: None,
-            player_url: None,
-            playlist: Some("Mighty Car Mods - Videos".to_string()),
-            playlist_id: Some("UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
-            playlist_index: Some(serde_json::Value::Number(serde_json::Number::from_f64(1.0).unwrap())),
-            playlist_title: Some("Mighty Car Mods - Videos".to_string()),
-            playlist_uploader: Some("Mighty Car Mods".to_string()),
-            playlist_uploader_id: Some("UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
-            preference: None,
-            protocol: Some(youtube_dl::Protocol::HttpsHttps),
-            quality: None,
-            release_date: None,
-            release_year: None,
-            repost_count: None,
-            requested_subtitles: None,
-            resolution: Some("1920x1080".to_string()),
-            season: None,
-            season_id: None,
-            season_number: None,
-            series: None,
-            source_preference: None,
-            start_time: None,
-            stretched_ratio: None,
-            subtitles: None,
-            tags: None,
-            tbr: Some(2411.782),
-            thumbnail: Some("https://i.ytimg.com/vi/QWkUFkXcx9I/maxresdefault.jpg".to_string()),
-            thumbnails: None,
-            timestamp: None,
-            title: Some("Everyone Should do this Simple $10 Car Mod".to_string()),
-            track: None,
-            track_id: None,
-            track_number: None,
-            upload_date: Some("20220206".to_string()),
-            uploader: Some("Mighty Car Mods".to_string()),
-            uploader_id: Some("mightycarmods".to_string()),
-            uploader_url: Some("http://www.youtube.com/user/mightycarmods".to_string()),
-            url: None,
-            vbr: Some(2282.304),
-            vcodec: Some("avc1.640028".to_string()),
-            view_count: Some(294645),
-            webpage_url: Some("https://www.youtube.com/watch?v=QWkUFkXcx9I".to_string()),
-            width: Some(1920.0),
+    #[test]
+    fn test_update_with_no_dedup() -> Result<(), Error> {
+        use url::Url;
+
+        let playlist = || get_playlist(vec![get_new_video(), get_new_video()]);
+
+        let mut channel = get_new_channel()?;
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &Default::default(),
+            playlist(),
+        )?;
+        assert_eq!(channel.rss_channel.as_ref().unwrap().items.len(), 1);
+
+        let options = super::UpdateOptions {
+            no_dedup: true,
             ..Default::default()
+        };
+
+        let mut channel = get_new_channel()?;
+
+        // Updating again doesn't add the repeats again
+        for _ in 0..2 {
+            channel.update_with_playlist(
+                Url::parse("http://localhost:8080").unwrap(),
+                &options,
+                playlist(),
+            )?;
+
+            assert_eq!(
+                get_item_ids(channel.rss_channel.as_ref().unwrap()),
+                vec!["QWkUFkXcx9I", "QWkUFkXcx9I"]
+            );
+        }
+
+        // `keep` still applies, without removing the files the kept repeat needs
+        let temp_dir = get_temp_dir("no-dedup");
+        let media_dir = temp_dir.join("mightycarmods");
+        std::fs::create_dir_all(&media_dir)?;
+        std::fs::write(media_dir.join("QWkUFkXcx9I.mp4"), b"downloaded")?;
+
+        channel.feed_file = temp_dir.join("mightycarmods.xml");
+        let summary = channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                keep: Some(1),
+                ..options.clone()
+            },
+            playlist(),
+        )?;
+
+        assert_eq!(summary.removed, 1);
+        assert_eq!(channel.rss_channel.as_ref().unwrap().items.len(), 1);
+        assert!(media_dir.join("QWkUFkXcx9I.mp4").exists());
+
+        // As does `keep_files`, and pruning the repeats afterwards
+        let summary = channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                keep_files: Some(1),
+                ..options
+            },
+            playlist(),
+        )?;
+
+        assert!(summary.deleted_files.is_empty());
+        assert_eq!(channel.rss_channel.as_ref().unwrap().items.len(), 2);
+        assert!(media_dir.join("QWkUFkXcx9I.mp4").exists());
+
+        let summary = channel.prune(1, true)?;
+        assert_eq!(summary.removed, 1);
+        assert!(summary.deleted_files.is_empty());
+        assert!(media_dir.join("QWkUFkXcx9I.mp4").exists());
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_playlist_json() -> Result<(), Error> {
+        use url::Url;
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.json");
+        let playlist = super::read_playlist_json(&bytes[..])?;
+
+        assert_eq!(playlist.title.as_deref(), Some("Mighty Car Mods"));
+
+        let mut channel = get_new_channel()?;
+        channel.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &Default::default(),
+            playlist,
+        )?;
+
+        let rss_channel = channel.rss_channel.as_ref().unwrap();
+        assert_eq!(rss_channel.title(), "Mighty Car Mods");
+        assert_eq!(
+            get_item_ids(rss_channel),
+            vec!["QWkUFkXcx9I", "Wqww1B9wljA"]
+        );
+        assert_eq!(
+            rss_channel.items[0].enclosure().unwrap().url(),
+            "http://localhost:8080/mightycarmods/QWkUFkXcx9I.mp4"
+        );
+        assert_eq!(rss_channel.items[0].enclosure().unwrap().length(), "4567");
+
+        // A single video's output becomes a one-entry playlist
+        let video = br#"{"id": "QWkUFkXcx9I", "title": "Building a Turbo Kit", "uploader": "Mighty Car Mods"}"#;
+        let playlist = super::read_playlist_json(&video[..])?;
+        assert_eq!(playlist.entries.unwrap()[0].id, "QWkUFkXcx9I");
+
+        assert!(super::read_playlist_json(&b"not json"[..]).is_err());
+
+        Ok(())
+    }
+
+    /// Warnings logged by each thread, along with the `log_context` they were logged in
+    fn captured_warnings() -> &'static std::sync::Mutex<Vec<(std::thread::ThreadId, String)>> {
+        use std::sync::{Mutex, OnceLock};
+
+        struct CaptureLogger;
+
+        impl log::Log for CaptureLogger {
+            fn enabled(&self, metadata: &log::Metadata) -> bool {
+                metadata.level() <= log::Level::Warn
+            }
+
+            fn log(&self, record: &log::Record) {
+                if self.enabled(record.metadata()) {
+                    captured_warnings().lock().unwrap().push((
+                        std::thread::current().id(),
+                        format!(
+                            "[{}] {}",
+                            super::log_context().unwrap_or_default(),
+                            record.args()
+                        ),
+                    ));
+                }
+            }
+
+            fn flush(&self) {}
         }
+
+        static WARNINGS: OnceLock<Mutex<Vec<(std::thread::ThreadId, String)>>> = OnceLock::new();
+
+        WARNINGS.get_or_init(|| {
+            log::set_logger(&CaptureLogger).unwrap();
+            log::set_max_level(log::LevelFilter::Warn);
+            Mutex::new(vec![])
+        })
     }
 
-    use crate::Error;
+    #[test]
+    fn test_log_context() -> Result<(), Error> {
+        use url::Url;
+
+        let warnings = captured_warnings();
+
+        // The preferred format being unavailable is warned about
+        let video = youtube_dl::SingleVideo {
+            ext: Some("webm".to_string()),
+            ..get_new_video()
+        };
+
+        assert_eq!(super::log_context(), None);
+
+        get_new_channel()?.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &Default::default(),
+            get_playlist(vec![video]),
+        )?;
+
+        assert_eq!(super::log_context(), None);
+
+        let thread = std::thread::current().id();
+        let warnings: Vec<_> = warnings
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(id, _)| *id == thread)
+            .map(|(_, warning)| warning.clone())
+            .collect();
+
+        assert!(warnings.iter().any(|warning| warning
+            .starts_with("[mightycarmods.xml] The preferred format wasn't available")));
+
+        // Contexts nest, restoring the outer one afterwards
+        super::with_log_context(std::path::Path::new("outer.xml"), || {
+            super::with_log_context(std::path::Path::new("inner.xml"), || {
+                assert_eq!(super::log_context().as_deref(), Some("inner.xml"));
+            });
+            assert_eq!(super::log_context().as_deref(), Some("outer.xml"));
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats() -> Result<(), Error> {
+        use std::io::BufReader;
+
+        let temp_dir = get_temp_dir("stats");
+        let media_dir = temp_dir.join("mightycarmods");
+        std::fs::create_dir_all(&media_dir)?;
+
+        assert_eq!(get_new_channel()?.stats()?, super::ChannelStats::default());
+
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let mut channel = super::Channel::new_with_reader(
+            temp_dir.join("mightycarmods.xml"),
+            BufReader::new(&bytes[0..]),
+        )?;
+
+        channel.rss_channel.as_mut().unwrap().items.push(
+            rss::ItemBuilder::default()
+                .guid(
+                    rss::GuidBuilder::default()
+                        .value("anXmCnLlsps".to_string())
+                        .build(),
+                )
+                .pub_date("Sat, 01 Jan 2022 00:00:00 +0000".to_string())
+                .enclosure(
+                    rss::EnclosureBuilder::default()
+                        .url("http://localhost:8080/mightycarmods/anXmCnLlsps.mp4".to_string())
+                        .mime_type("video/mp4".to_string())
+                        .build(),
+                )
+                .build(),
+        );
+
+        std::fs::write(media_dir.join("Wqww1B9wljA.mp4"), b"video")?;
+
+        let stats = channel.stats()?;
+        assert_eq!(stats.item_count, 2);
+        assert_eq!(stats.media_bytes, 5);
+        assert_eq!(stats.missing_media, 1);
+        assert_eq!(
+            stats.oldest_pub_date.map(|pub_date| pub_date.to_rfc2822()),
+            Some("Sat, 1 Jan 2022 00:00:00 +0000".to_string())
+        );
+        assert_eq!(
+            stats.newest_pub_date.map(|pub_date| pub_date.to_rfc2822()),
+            Some("Tue, 18 Jan 2022 03:57:58 +0000".to_string())
+        );
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
 
     #[test]
-    fn test_update_new_with_playlist() -> Result<(), Error> {
+    fn test_new_combined() -> Result<(), Error> {
         use rss::validation::Validate;
+        use std::io::BufReader;
         use url::Url;
 
-        let playlist = youtube_dl::model::Playlist {
-            entries: Some(vec![get_new_video()]),
-            extractor: Some("youtube:tab".to_string()),
-            extractor_key: Some("YoutubeTab".to_string()),
-            id: Some("UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
-            title: Some("Mighty Car Mods - Videos".to_string()),
-            uploader: Some("Mighty Car Mods".to_string()),
-            uploader_id: Some("UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
-            uploader_url: Some(
-                "https://www.youtube.com/channel/UCgJRL30YS6XFxq9Ga8W2J3A".to_string(),
-            ),
-            webpage_url: Some("https://www.youtube.com/c/mightycarmods".to_string()),
-            webpage_url_basename: Some("mightycarmods".to_string()),
-            ..Default::default()
+        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
+        let existing = super::Channel::new_with_reader(
+            std::path::PathBuf::from("mightycarmods.xml"),
+            BufReader::new(&bytes[0..]),
+        )?;
+
+        let video = |id: &str, upload_date: &str| youtube_dl::SingleVideo {
+            id: id.to_string(),
+            upload_date: Some(upload_date.to_string()),
+            ..get_new_video()
         };
 
-        let mut channel = super::Channel::new_with_url(
-            std::path::Path::new("mightycarmods.xml").to_path_buf(),
-            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        let mut other = super::Channel::new_with_url(
+            std::path::PathBuf::from("other.xml"),
+            Url::parse("https://www.youtube.com/c/other").unwrap(),
+        )?;
+        other.update_with_playlist(
+            Url::parse("http://localhost:8080").unwrap(),
+            &Default::default(),
+            get_playlist(vec![
+                video("QWkUFkXcx9I", "20220206"),
+                // Also in the existing feed, where it's newer
+                video("Wqww1B9wljA", "20220101"),
+                video("anXmCnLlsps", "20211201"),
+            ]),
         )?;
 
-        channel.update_with_playlist(Url::parse("http://localhost").unwrap(), None, playlist)?;
-        let rss_channel = channel.rss_channel.unwrap();
-        rss_channel.validate().unwrap();
+        let channels = [existing, other];
 
-        assert_eq!(rss_channel.items.len(), 1);
+        let combined = |limit| {
+            super::Channel::new_combined(
+                std::path::PathBuf::from("latest.xml"),
+                Url::parse("http://localhost:8080").unwrap(),
+                &channels,
+                limit,
+            )
+        };
+
+        let channel = combined(10);
+        let rss_channel = channel.rss_channel.as_ref().unwrap();
+        assert!(rss_channel.validate().is_ok());
+        assert_eq!(
+            get_item_ids(rss_channel),
+            vec!["QWkUFkXcx9I", "Wqww1B9wljA", "anXmCnLlsps"]
+        );
+
+        let sources: Vec<_> = rss_channel
+            .items
+            .iter()
+            .map(|item| item.source().map(|source| source.url().to_string()))
+            .collect();
+        assert_eq!(
+            sources,
+            vec![
+                Some("https://www.youtube.com/c/other".to_string()),
+                Some("https://www.youtube.com/channel/UCgJRL30YS6XFxq9Ga8W2J3A/videos".to_string()),
+                Some("https://www.youtube.com/c/other".to_string()),
+            ]
+        );
+        assert_eq!(
+            rss_channel.items[1].source().unwrap().title(),
+            Some("Mighty Car Mods")
+        );
+
+        let channel = combined(2);
+        assert_eq!(
+            get_item_ids(channel.rss_channel.as_ref().unwrap()),
+            vec!["QWkUFkXcx9I", "Wqww1B9wljA"]
+        );
 
         Ok(())
     }
 
     #[test]
-    fn test_update_existing_with_playlist_and_truncation() -> Result<(), Error> {
-        use rss::validation::Validate;
-        use std::io::BufReader;
+    fn test_update_with_feed_settings() -> Result<(), Error> {
+        use super::categories::Category;
+        use super::FeedSettings;
         use url::Url;
 
-        let playlist = youtube_dl::model::Playlist {
-            entries: Some(vec![get_new_video(), get_duplicate_video()]),
-            extractor: Some("youtube:tab".to_string()),
-            extractor_key: Some("YoutubeTab".to_string()),
-            id: Some("UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
-            title: Some("Mighty Car Mods - Videos".to_string()),
-            uploader: Some("Mighty Car Mods".to_string()),
-            uploader_id: Some("UCgJRL30YS6XFxq9Ga8W2J3A".to_string()),
-            uploader_url: Some(
-                "https://www.youtube.com/channel/UCgJRL30YS6XFxq9Ga8W2J3A".to_string(),
-            ),
-            webpage_url: Some("https://www.youtube.com/c/mightycarmods".to_string()),
-            webpage_url_basename: Some("mightycarmods".to_string()),
+        let temp_dir = get_temp_dir("feed_settings");
+        std::fs::create_dir_all(&temp_dir)?;
+        let feed_file = temp_dir.join("mightycarmods.xml");
+
+        // Store the settings in a feed
+        let mut channel = super::Channel::new_with_url(
+            feed_file.clone(),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+        let mut options = super::UpdateOptions {
+            skip_download: true,
             ..Default::default()
         };
+        FeedSettings {
+            keep: Some(1),
+            download_limit: Some(5),
+            categories: vec!["Leisure/Automotive".parse().unwrap()],
+        }
+        .apply_to(&mut options);
 
-        let bytes = include_bytes!("../fixtures/mightycarmods.rss");
-        let reader = BufReader::new(&bytes[0..]);
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )));
 
-        let mut channel = super::Channel::new_with_reader(
-            std::path::Path::new("mightycarmods.xml").to_path_buf(),
-            reader,
+        channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &options,
+            &downloader,
         )?;
+        channel.write(&Default::default())?;
 
-        let rss_channel = channel.rss_channel.as_ref().unwrap();
-        assert_eq!(rss_channel.items.len(), 1);
+        // Loading it reads them back
+        let mut channel = super::Channel::new(feed_file)?;
+        let stored = channel.feed_settings();
+        assert_eq!(
+            stored,
+            FeedSettings {
+                keep: Some(1),
+                download_limit: Some(5),
+                categories: vec![Category {
+                    name: "Leisure".to_string(),
+                    subcategory: Some("Automotive".to_string()),
+                }],
+            }
+        );
 
-        channel.update_with_playlist(
+        // Settings given explicitly take precedence over stored ones
+        let settings = FeedSettings {
+            download_limit: Some(10),
+            ..Default::default()
+        }
+        .or(stored);
+        assert_eq!(settings.keep, Some(1));
+        assert_eq!(settings.download_limit, Some(10));
+
+        // And they drive the update
+        let mut options = super::UpdateOptions {
+            skip_download: true,
+            ..Default::default()
+        };
+        settings.apply_to(&mut options);
+        assert_eq!(options.download_limit, 10);
+
+        let mut second_video = get_new_video();
+        second_video.id = "dQw4w9WgXcQ".to_string();
+        second_video.upload_date = Some("20220213".to_string());
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![second_video, get_new_video()]),
+        )));
+
+        channel.update_with_downloader(
             Url::parse("http://localhost:8080").unwrap(),
-            None,
-            playlist.clone(),
+            &options,
+            &downloader,
         )?;
+
+        let invocation = &downloader.invocations.borrow()[0];
+        let limit = invocation
+            .iter()
+            .position(|arg| arg == "--playlist-end")
+            .map(|index| invocation[index + 1].as_str());
+        assert_eq!(limit, Some("10"));
+
         let rss_channel = channel.rss_channel.as_ref().unwrap();
-        rss_channel.validate().unwrap();
+        assert_eq!(get_item_ids(rss_channel), vec!["dQw4w9WgXcQ"]);
+        assert_eq!(
+            rss_channel.itunes_ext.as_ref().unwrap().categories()[0].text(),
+            "Leisure"
+        );
+        assert_eq!(
+            rss_channel.extensions["playcaster"]["limit"][0].value(),
+            Some("10")
+        );
+        assert!(rss_channel.namespaces.contains_key("playcaster"));
 
-        assert_eq!(rss_channel.items.len(), 2);
+        // Stored categories which can't be read are ignored with a warning
+        let warnings = captured_warnings();
+        let thread = std::thread::current().id();
+
+        channel
+            .rss_channel
+            .as_mut()
+            .unwrap()
+            .extensions
+            .get_mut("playcaster")
+            .and_then(|named| named.get_mut("category"))
+            .unwrap()[0]
+            .set_value("Leisure/".to_string());
+        assert_eq!(channel.feed_settings().categories, vec![]);
+        assert!(warnings
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(id, warning)| *id == thread
+                && warning.contains("playcaster:category of \"Leisure/\"")));
+
+        // Applying no settings at all clears the stored ones
+        let mut options = super::UpdateOptions {
+            skip_download: true,
+            ..Default::default()
+        };
+        FeedSettings::default().apply_to(&mut options);
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )));
+        channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &options,
+            &downloader,
+        )?;
+
+        assert_eq!(channel.feed_settings(), FeedSettings::default());
+        let extensions = &channel.rss_channel.as_ref().unwrap().extensions;
+        for name in ["keep", "limit", "category"] {
+            assert!(extensions
+                .get("playcaster")
+                .is_none_or(|named| !named.contains_key(name)));
+        }
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_channels_skips_disabled() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("disabled");
+        std::fs::create_dir_all(&temp_dir)?;
+
+        let disabled_feed = String::from_utf8(include_bytes!("../fixtures/mightycarmods.rss").to_vec())
+            .unwrap()
+            .replacen(
+                "<rss version=\"2.0\"",
+                "<rss version=\"2.0\" xmlns:playcaster=\"https://github.com/ticky/playcaster/namespace\"",
+                1,
+            )
+            .replacen(
+                "<channel>",
+                "<channel>\n    <playcaster:disabled>true</playcaster:disabled>",
+                1,
+            );
+        std::fs::write(temp_dir.join("paused.xml"), disabled_feed)?;
+
+        let channels = ["paused", "enabled"]
+            .iter()
+            .map(|name| {
+                super::Channel::new_with_url(
+                    temp_dir.join(format!("{}.xml", name)),
+                    Url::parse(&format!("https://www.youtube.com/c/{}", name)).unwrap(),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        assert!(channels[0].is_disabled());
+        assert!(!channels[1].is_disabled());
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )));
+
+        let updated =
+            super::update_channels(channels, &super::BatchOptions::default(), |mut channel| {
+                channel.update_with_downloader(
+                    Url::parse("http://localhost:8080").unwrap(),
+                    &super::UpdateOptions {
+                        skip_download: true,
+                        ..Default::default()
+                    },
+                    &downloader,
+                )?;
+                Ok(channel.feed_file)
+            })?;
+
+        assert_eq!(updated, vec![temp_dir.join("enabled.xml")]);
+        assert_eq!(downloader.invocations.borrow().len(), 1);
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
 
+    #[test]
+    fn test_check_media_dirs() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("media_dir_collision");
+
+        let channel = |name: &str| {
+            super::Channel::new_with_url(
+                temp_dir.join(name),
+                Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+            )
+        };
+
+        super::check_media_dirs(&[channel("a.xml")?, channel("b.xml")?])?;
+
+        // Feeds sharing a stem would share a media directory
+        match super::check_media_dirs(&[channel("a.xml")?, channel("a.rss")?]) {
+            Err(Error::MediaDirCollisionError(first, second, media_dir)) => {
+                assert_eq!(first, temp_dir.join("a.xml"));
+                assert_eq!(second, temp_dir.join("a.rss"));
+                assert_eq!(media_dir, temp_dir.join("a"));
+            }
+            result => panic!("expected a media directory collision, got {:?}", result),
+        }
+
+        // Unless one of them is given its own
+        let mut separate = channel("a.rss")?;
+        separate.media_dir = Some(temp_dir.join("a-rss"));
+        super::check_media_dirs(&[channel("a.xml")?, separate])?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_sibling_media_dirs() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("sibling-media-dir-collision");
+
+        let channel = |name: &str| {
+            super::Channel::new_with_url(
+                temp_dir.join(name),
+                Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+            )
+        };
+
+        // Nothing beside the feed yet
+        super::check_sibling_media_dirs(&channel("a.rss")?)?;
+
+        // A feed updated on its own is checked against the feed files beside it, but not its own
+        // JSON Feed
+        std::fs::create_dir_all(&temp_dir)?;
+        std::fs::write(temp_dir.join("a.xml"), b"")?;
+        std::fs::write(temp_dir.join("a.json"), b"")?;
+        std::fs::write(temp_dir.join("b.xml"), b"")?;
+
+        super::check_sibling_media_dirs(&channel("b.xml")?)?;
+
+        match super::check_sibling_media_dirs(&channel("a.rss")?) {
+            Err(Error::MediaDirCollisionError(first, second, media_dir)) => {
+                assert_eq!(first, temp_dir.join("a.xml"));
+                assert_eq!(second, temp_dir.join("a.rss"));
+                assert_eq!(media_dir, temp_dir.join("a"));
+            }
+            result => panic!("expected a media directory collision, got {:?}", result),
+        }
+
+        let mut separate = channel("a.rss")?;
+        separate.media_dir = Some(temp_dir.join("a-rss"));
+        super::check_sibling_media_dirs(&separate)?;
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_purge() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("purge");
+        std::fs::create_dir_all(&temp_dir)?;
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+
+        let downloader = MockDownloader::new(youtube_dl::YoutubeDlOutput::Playlist(Box::new(
+            get_playlist(vec![get_new_video()]),
+        )));
+
+        channel.update_with_downloader(
+            Url::parse("http://localhost:8080").unwrap(),
+            &super::UpdateOptions {
+                skip_download: true,
+                ..Default::default()
+            },
+            &downloader,
+        )?;
+        channel.write(&Default::default())?;
+
+        let media_dir = temp_dir.join("mightycarmods");
+        std::fs::create_dir_all(&media_dir)?;
+        std::fs::write(media_dir.join("QWkUFkXcx9I.mp4"), "video")?;
+        std::fs::write(media_dir.join("QWkUFkXcx9I.en.vtt"), "WEBVTT")?;
+        std::fs::write(channel.history_file(), "{}")?;
+        let stray_file = media_dir.join("notes.txt");
+        std::fs::write(&stray_file, "not from the feed")?;
+
+        // Files the feed doesn't reference stop the purge
+        match channel.purge(false) {
+            Err(Error::PurgeUnreferencedError(dir, count, path)) => {
+                assert_eq!(dir, media_dir);
+                assert_eq!(count, 1);
+                assert_eq!(path, stray_file);
+            }
+            result => panic!(
+                "expected unreferenced files to stop the purge, got {:?}",
+                result
+            ),
+        }
+        assert!(channel.feed_file.exists());
+        assert!(stray_file.exists());
+
+        // Unless forced
+        let plan = channel.purge_plan(true)?;
         assert_eq!(
-            rss_channel.items[0].enclosure.as_ref().unwrap().url,
-            "http://localhost:8080/mightycarmods/QWkUFkXcx9I.mp4"
+            plan.feed_files,
+            vec![channel.history_file(), channel.feed_file.clone()]
         );
+        assert_eq!(plan.media_dir, Some(media_dir.clone()));
         assert_eq!(
-            rss_channel.items[1].enclosure.as_ref().unwrap().url,
-            "http://localhost:8080/mightycarmods/Wqww1B9wljA.mp4"
+            plan.referenced_files,
+            vec![
+                media_dir.join("QWkUFkXcx9I.en.vtt"),
+                media_dir.join("QWkUFkXcx9I.mp4"),
+            ]
         );
+        assert_eq!(plan.unreferenced_files, vec![stray_file.clone()]);
 
-        channel.update_with_playlist(
+        std::fs::remove_file(&stray_file)?;
+        channel.purge(false)?;
+
+        assert!(!channel.feed_file.exists());
+        assert!(!channel.history_file().exists());
+        assert!(!media_dir.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_invalid_feed_keeps_files() -> Result<(), Error> {
+        use url::Url;
+
+        let temp_dir = get_temp_dir("invalid-keep");
+        let media_dir = temp_dir.join("mightycarmods");
+        std::fs::create_dir_all(&media_dir)?;
+
+        let videos: Vec<_> = (1..=2)
+            .map(|day| youtube_dl::SingleVideo {
+                id: format!("video{}", day),
+                timestamp: Some(1_640_995_200.0 + day as f64 * 86_400.0),
+                ..get_new_video()
+            })
+            .collect();
+
+        for video in &videos {
+            std::fs::write(media_dir.join(format!("{}.mp4", video.id)), b"media")?;
+        }
+
+        let mut channel = super::Channel::new_with_url(
+            temp_dir.join("mightycarmods.xml"),
+            Url::parse("https://www.youtube.com/c/mightycarmods").unwrap(),
+        )?;
+        let result = channel.update_with_playlist_and_transform(
             Url::parse("http://localhost:8080").unwrap(),
-            Some(1),
-            playlist.clone(),
+            &super::UpdateOptions {
+                keep: Some(1),
+                ..Default::default()
+            },
+            get_playlist(videos),
+            &std::collections::HashMap::new(),
+            |_, mut item| {
+                item.set_link("not a url".to_string());
+                item
+            },
+        );
+
+        // The feed isn't updated, and so the item it would have dropped keeps its file
+        assert!(matches!(result, Err(Error::FeedValidationError(..))));
+        assert!(channel.rss_channel.is_none());
+        assert!(media_dir.join("video1.mp4").exists());
+        assert!(media_dir.join("video2.mp4").exists());
+
+        std::fs::remove_dir_all(temp_dir)?;
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_update_channels_with_interrupted_downloader() -> Result<(), Error> {
+        use url::Url;
+
+        let channels = ["first", "second"]
+            .iter()
+            .map(|name| {
+                super::Channel::new_with_url(
+                    format!("{}.xml", name).into(),
+                    Url::parse(&format!("https://www.youtube.com/c/{}", name)).unwrap(),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let options = super::BatchOptions::default();
+        super::install_shutdown_handler(options.shutdown.clone());
+
+        let mut started = vec![];
+        let updated: Vec<()> = super::update_channels(channels, &options, |channel| {
+            started.push(channel.feed_file);
+
+            // Ctrl-C reaches `yt-dlp` as well as Playcaster, so its run fails
+            unsafe {
+                libc::raise(libc::SIGINT);
+            }
+            Err(Error::IoError(std::io::Error::other(
+                "yt-dlp was interrupted",
+            )))
+        })?;
+
+        // Which stops the batch, rather than failing it
+        assert!(updated.is_empty());
+        assert_eq!(started, vec![std::path::PathBuf::from("first.xml")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_and_write_channels_with_write_queue() -> Result<(), Error> {
+        use std::io::BufReader;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::time::Duration;
+        use url::Url;
+
+        let temp_dir = get_temp_dir("write-queue");
+        std::fs::create_dir_all(&temp_dir)?;
+
+        let names = ["first", "second", "third"];
+        let channels = || {
+            names
+                .iter()
+                .map(|name| {
+                    super::Channel::new_with_url(
+                        temp_dir.join(format!("{}.xml", name)),
+                        Url::parse(&format!("https://www.youtube.com/c/{}", name)).unwrap(),
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        let update = |mut channel: super::Channel| -> Result<super::Channel, Error> {
+            let name = channel.feed_file.file_stem().unwrap().to_string_lossy();
+            let video = youtube_dl::SingleVideo {
+                id: format!("{}-video", name),
+                ..get_new_video()
+            };
+
+            channel.update_with_playlist(
+                Url::parse("http://localhost:8080").unwrap(),
+                &super::UpdateOptions::default(),
+                get_playlist(vec![video]),
+            )?;
+
+            Ok(channel)
+        };
+
+        let options = super::BatchOptions {
+            write_queue: Some(0),
+            ..Default::default()
+        };
+
+        // The first feed is only written once the second channel has started updating
+        let (started, starts) = std::sync::mpsc::channel();
+        let starts = std::sync::Mutex::new(starts);
+        let overlapped = AtomicBool::new(false);
+
+        let written = super::update_and_write_channels(
+            channels()?,
+            &options,
+            |channel| {
+                started.send(channel.feed_file.clone()).unwrap();
+                update(channel)
+            },
+            |channel| {
+                if channel.feed_file == temp_dir.join("first.xml") {
+                    let starts = starts.lock().unwrap();
+                    starts.recv_timeout(Duration::from_secs(5)).unwrap();
+                    overlapped.store(
+                        starts
+                            .recv_timeout(Duration::from_secs(5))
+                            .is_ok_and(|feed_file| feed_file == temp_dir.join("second.xml")),
+                        Ordering::SeqCst,
+                    );
+                }
+
+                channel.write(&super::WriteOptions::default())?;
+                Ok(channel.feed_file)
+            },
         )?;
-        let rss_channel = channel.rss_channel.unwrap();
-        rss_channel.validate().unwrap();
 
-        assert_eq!(rss_channel.items.len(), 1);
+        assert!(overlapped.load(Ordering::SeqCst));
+
+        // Every feed is written, in order, with its own items
+        assert_eq!(
+            written,
+            names
+                .iter()
+                .map(|name| temp_dir.join(format!("{}.xml", name)))
+                .collect::<Vec<_>>()
+        );
+
+        for name in names {
+            let rss_channel = rss::Channel::read_from(BufReader::new(std::fs::File::open(
+                temp_dir.join(format!("{}.xml", name)),
+            )?))?;
+            assert_eq!(get_item_ids(&rss_channel), vec![format!("{}-video", name)]);
+        }
+
+        // A feed failing validation as it's written is skipped, as when updating
+        let written =
+            super::update_and_write_channels(
+                channels()?,
+                &options,
+                update,
+                |channel| match channel.feed_file == temp_dir.join("second.xml") {
+                    true => Err(Error::FeedValidationError(
+                        channel.feed_file,
+                        rss::validation::ValidationError::Validation("invalid".to_string()),
+                    )),
+                    false => Ok(channel.feed_file),
+                },
+            )?;
+        assert_eq!(
+            written,
+            vec![temp_dir.join("first.xml"), temp_dir.join("third.xml")]
+        );
+
+        // Any other failure stops the batch
+        let result = super::update_and_write_channels(channels()?, &options, update, |channel| {
+            Err::<(), _>(Error::NoFeedError(channel.feed_file))
+        });
+        assert!(matches!(result, Err(Error::NoFeedError(_))));
+
+        std::fs::remove_dir_all(temp_dir)?;
 
         Ok(())
     }
@@ -1,18 +1,29 @@
 #[macro_use]
 extern crate log;
 use anyhow::Result;
-use clap::Parser;
-use std::fs::OpenOptions;
-use std::path::PathBuf;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use std::fs::File;
+use std::io::{BufReader, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
 use url::Url;
 
-use playcaster::Channel;
+use playcaster::categories::Category;
+use playcaster::{
+    BatchOptions, Channel, ChannelSummary, CustomElement, DateSource, DeletionDecision,
+    FeedProfile, FeedSettings, GuidScheme, LinkFileFormat, OrderBy, OrderDirection, OutputFormat,
+    SourceOrder, ThumbnailPreference, TitleDisambiguator, TitleSource, UpdateOptions,
+    UpdateSummary, WriteOptions,
+};
 
 #[derive(Parser, Debug)]
 #[clap(version)]
+#[clap(group(clap::ArgGroup::new("deletions").multiple(true).args(["confirm_deletes", "purge"])))]
 /// Turn any playlist into a Podcast feed
 struct Args {
-    /// Path to the channel's RSS feed file
+    /// Path to the channel's RSS feed file.
+    /// When using `--playlists-file`, the directory to write each playlist's feed file to.
     #[clap(value_parser)]
     feed_file: PathBuf,
 
@@ -21,80 +32,1014 @@ struct Args {
 
     /// Playlist URL to download videos from.
     /// Required if creating a new feed, or if the feed's link element doesn't already point to a playlist URL.
-    #[clap(long)]
+    #[clap(long, conflicts_with = "playlists_file")]
     playlist_url: Option<Url>,
 
-    /// Maximum number of videos to download for the given channel
-    #[clap(default_value = "30", long)]
-    limit: usize,
+    /// Path to a file listing playlist URLs, one per line, to create or update a feed for each.
+    /// Blank lines and lines starting with `#` are ignored.
+    #[clap(long)]
+    playlists_file: Option<PathBuf>,
+
+    /// When using `--playlists-file`, stop if any feed fails validation, rather than skipping just that feed
+    #[clap(long, requires = "playlists_file")]
+    abort_on_validation_error: bool,
+
+    /// When using `--playlists-file`, seconds to wait between updating one feed and the next, to avoid rate limits
+    #[clap(long, value_name = "SECONDS", requires = "playlists_file", value_parser = parse_seconds)]
+    inter_channel_delay: Option<Duration>,
+
+    /// When using `--playlists-file`, write each feed while the next one updates, with up to this many more waiting to be written
+    #[clap(
+        long,
+        value_name = "COUNT",
+        requires = "playlists_file",
+        conflicts_with = "confirm_deletes"
+    )]
+    write_queue: Option<usize>,
+
+    /// After updating the feeds listed by `--playlists-file`, also write a feed of the newest episodes across all of them
+    #[clap(long, value_name = "PATH", requires = "playlists_file")]
+    combined_feed: Option<PathBuf>,
+
+    /// Number of episodes to include in `--combined-feed`
+    #[clap(default_value = "50", long, requires = "combined_feed")]
+    combined_feed_limit: usize,
+
+    /// Maximum number of videos to download for the given channel, or 0 to download the whole playlist [default: 30]
+    #[clap(long)]
+    limit: Option<usize>,
 
     /// Maximum number of videos to keep for the given channel.
     /// Any older videos will be deleted when the feed updates.
-    /// Should be greater than or equal to `limit`.
+    /// Should be greater than or equal to `limit`, and must be at least 1.
     #[clap(long)]
     keep: Option<usize>,
 
+    /// Forget the `--keep`, `--limit` and `--category` settings stored in the feed, so only those given
+    /// this time are used, and stored in their place
+    #[clap(long)]
+    clear_feed_settings: bool,
+
+    /// Maximum number of videos to keep the files of, which can be fewer than `keep`.
+    /// Older videos stay in the feed, but their files are deleted, so they can't be played unless downloaded again.
+    #[clap(long)]
+    keep_files: Option<usize>,
+
+    /// Maximum number of new videos to download and add to the feed per run, newest first, so a first run
+    /// doesn't flood subscribers. The rest are added by later runs. Unlike `--limit`, only new videos count.
+    #[clap(long)]
+    max_new_per_run: Option<usize>,
+
+    /// Keep repeated entries of the same video in the playlist as separate items, rather than only the first.
+    /// The repeats share a GUID, so many podcast apps will merge them or reject the feed.
+    #[clap(long)]
+    no_dedup: bool,
+
+    /// Move the files `keep` removes into this directory, rather than deleting them
+    #[clap(long)]
+    trash_dir: Option<PathBuf>,
+
+    /// List the files `keep` would delete and ask before deleting them.
+    /// When not run interactively, the files are only listed, unless `--yes` is given.
+    #[clap(long)]
+    confirm_deletes: bool,
+
+    /// Delete the files listed by `--confirm-deletes` or `--purge` without asking
+    #[clap(long, requires = "deletions")]
+    yes: bool,
+
+    /// Directory to store the media files in, rather than one named after the feed file next to it
+    #[clap(long, conflicts_with = "playlists_file")]
+    media_dir: Option<PathBuf>,
+
+    /// Path under `base_url` the media files are served from, rather than the media directory's name
+    #[clap(long, conflicts_with = "playlists_file")]
+    url_path_prefix: Option<String>,
+
+    /// Check the existing feed validates and that every item's media file is on disk, without changing
+    /// anything or downloading. Lists any problems found, exiting with an error if there are any.
+    #[clap(long, conflicts_with_all = ["playlists_file", "rebase_url"])]
+    check: bool,
+
+    /// Retire the feed, deleting the feed file, the files written beside it and its whole media directory.
+    /// Lists what would be deleted and asks first; when not run interactively, nothing is deleted unless `--yes` is given.
+    #[clap(long, conflicts_with_all = ["playlists_file", "check", "rebase_url", "no_write_feed", "diff"])]
+    purge: bool,
+
+    /// With `--purge`, only list what would be deleted
+    #[clap(long, requires = "purge")]
+    dry_run: bool,
+
+    /// With `--purge`, delete the media directory even if it holds files the feed doesn't reference
+    #[clap(long, requires = "purge")]
+    force: bool,
+
+    /// Print the playlist's metadata as `yt-dlp` reports it, as JSON, without downloading anything or writing the feed
+    #[clap(long, conflicts_with_all = ["playlists_file", "check", "rebase_url", "purge"])]
+    dump_metadata: bool,
+
+    /// Print Playcaster's version, the detected `yt-dlp` version and whether it's new enough, as JSON
+    #[clap(long, exclusive = true)]
+    version_json: bool,
+
+    /// Rewrite the existing feed's enclosure URLs, and any artwork served alongside the media, to point at `base_url`,
+    /// without downloading anything. Useful after moving the media to a new server.
+    #[clap(long)]
+    rebase_url: bool,
+
     /// Do not write the updated RSS feed to disk; just print it to the terminal
     #[clap(long)]
     no_write_feed: bool,
 
+    /// List the episodes an update would add, remove or change, without downloading anything or writing the feed
+    #[clap(long, conflicts_with_all = ["no_write_feed", "json_summary", "check", "dump_metadata"])]
+    diff: bool,
+
+    /// Build the feed from the playlist's metadata without downloading anything, writing it to `<feed>.preview.xml`
+    /// to preview. Its enclosures point where the media will be once downloaded.
+    #[clap(long, conflicts_with_all = ["no_write_feed", "diff", "check", "dump_metadata", "rebase_url"])]
+    preview_feed: bool,
+
+    /// Append each run's summary, as a timestamped JSON line, to this file.
+    /// Without a path, `<feed>.history.jsonl` next to each feed file is used.
+    #[clap(long, num_args = 0..=1, require_equals = true, value_name = "PATH")]
+    history_file: Option<Option<PathBuf>>,
+
+    /// Print a JSON summary of each channel's update to stdout on completion, instead of progress messages
+    #[clap(long, conflicts_with = "no_write_feed")]
+    json_summary: bool,
+
+    /// Exit with status 3, rather than 0, if the run didn't add any new items to any feed
+    #[clap(long)]
+    fail_if_no_new: bool,
+
     /// Write terse RSS XML to disk, rather than the default pretty-printed version
     #[clap(long)]
     no_pretty: bool,
 
-    /// Additional arguments to be passed to `yt-dlp`
+    /// Write a stripped-down RSS feed for bandwidth-constrained clients, truncating descriptions and
+    /// omitting optional elements like keywords, summaries and `content:encoded`
+    #[clap(long)]
+    minimal: bool,
+
+    /// Tailor the RSS feed to a directory or validator, refusing to write it if it lacks anything the profile requires
+    #[clap(long, value_enum)]
+    profile: Option<FeedProfile>,
+
+    /// Character encoding to write the RSS feed in, which is declared in its XML declaration
+    #[clap(default_value = "UTF-8", long)]
+    encoding: String,
+
+    /// Format to write the feed in; may be given more than once to write several.
+    /// `jsonfeed` is written next to the feed file, with a `json` extension.
+    #[clap(default_value = "rss", long = "output-format", value_enum)]
+    output_formats: Vec<OutputFormat>,
+
+    /// URL to documentation for the feed format, emitted as the channel's `<docs>` element
+    #[clap(long)]
+    docs: Option<Url>,
+
+    /// Sort order to pass to `yt-dlp`'s `--format-sort`, for finer control over which format is picked
+    #[clap(long)]
+    format_sort: Option<String>,
+
+    /// Upgrade `http` artwork URLs to `https`, as required by Apple Podcasts, rather than just warning about them
+    #[clap(long)]
+    require_https_images: bool,
+
+    /// How to order the items in the feed; `playlist` preserves the playlist's own order, for curated playlists
+    #[clap(default_value = "date", long, value_enum)]
+    order: SourceOrder,
+
+    /// Order the items by a metadata field instead; items missing it (e.g. the views of videos no longer in the playlist) sort last
+    #[clap(long, value_enum)]
+    order_by: Option<OrderBy>,
+
+    /// Which way to order by `--order-by`; by default, dates, durations and views are descending, titles and indexes ascending
+    #[clap(long, value_enum, requires = "order_by")]
+    order_dir: Option<OrderDirection>,
+
+    /// Directory for `yt-dlp` to store its cache in, rather than its default location
+    #[clap(long)]
+    cache_dir: Option<PathBuf>,
+
+    /// Disable `yt-dlp`'s filesystem cache entirely
+    #[clap(long, conflicts_with = "cache_dir")]
+    no_cache_dir: bool,
+
+    /// Accept a playlist URL which points to a single video, producing a one-item feed
+    #[clap(long, visible_alias = "single")]
+    single_ok: bool,
+
+    /// Build the channel's summary from the titles of this many of the newest episodes
+    #[clap(long)]
+    summary_from_episodes: Option<usize>,
+
+    /// Update an existing feed's title to match the playlist's current title, rather than keeping the original title
+    #[clap(long)]
+    sync_title: bool,
+
+    /// File to read the channel's description from, which may be HTML. Used when creating a new feed,
+    /// or for an existing one with `--sync-description`.
+    #[clap(long)]
+    description_file: Option<PathBuf>,
+
+    /// Replace an existing feed's description with the contents of `--description-file`
+    #[clap(long, requires = "description_file")]
+    sync_description: bool,
+
+    /// Author to credit the feed and every episode to. By default, episodes are credited to their video's uploader or
+    /// channel, then the playlist's uploader, then the playlist's title, and the feed to the last two of those.
+    #[clap(long)]
+    author: Option<String>,
+
+    /// Apple Podcasts category to file the feed under, as `Category` or `Category/Subcategory`.
+    /// May be given more than once. New feeds default to "TV & Film".
+    #[clap(long = "category")]
+    categories: Vec<Category>,
+
+    /// Which of each video's dates to publish its episode at: `timestamp`, `upload` or `release` (for premieres)
+    #[clap(default_value = "timestamp", long, value_enum)]
+    date_source: DateSource,
+
+    /// Which of each video's fields to title its episode with: `title`, `track` (for music) or `alt-title`,
+    /// falling back to the title for videos without it
+    #[clap(default_value = "title", long, value_enum)]
+    title_from: TitleSource,
+
+    /// Tell apart episodes sharing a title by appending their `date` or `id`, only when titles collide
+    #[clap(long, value_enum)]
+    disambiguate_titles: Option<TitleDisambiguator>,
+
+    /// Comma-separated keywords for the feed, rather than deriving them from the videos' tags
+    #[clap(long, value_delimiter = ',')]
+    channel_keywords: Vec<String>,
+
+    /// Custom element to set on the channel, as `prefix:name=value`, such as one a hosting platform
+    /// requires. May be given more than once. The prefix needs a namespace, from `--namespace` or the existing feed.
+    #[clap(long)]
+    channel_element: Vec<CustomElement>,
+
+    /// Namespace to declare for `--channel-element` and `--item-overrides`, as `prefix=uri`. May be given more than once.
+    #[clap(long, value_parser = parse_query_pair)]
+    namespace: Vec<(String, String)>,
+
+    /// JSON file of changes to make to particular videos' items, by video id, such as custom elements to add
+    #[clap(long)]
+    item_overrides: Option<PathBuf>,
+
+    /// Emit the `--category` categories sorted, rather than in the order given
+    #[clap(long)]
+    sort_categories: bool,
+
+    /// Fail on problems with the feed's contents, like unknown categories or relative enclosure URLs, rather than warning
+    #[clap(long)]
+    strict: bool,
+
+    /// Query parameter to append to every new episode's enclosure URL, as `key=value`.
+    /// May be given more than once.
+    #[clap(long = "enclosure-query", value_parser = parse_query_pair)]
+    enclosure_query: Vec<(String, String)>,
+
+    /// Update episodes already in the feed with the playlist's current titles, descriptions and images.
+    /// Their publication dates are kept.
+    #[clap(long)]
+    prefer_fresh_metadata: bool,
+
+    /// Use each new episode's enclosure URL as its GUID, rather than the source video's id
+    #[clap(long)]
+    guid_from_enclosure: bool,
+
+    /// How to build each new episode's GUID: `id`, `enclosure` or `prefix:TEXT`
+    #[clap(long, conflicts_with = "guid_from_enclosure")]
+    guid_scheme: Option<GuidScheme>,
+
+    /// Build the feed from `yt-dlp --dump-single-json` output captured earlier, rather than running `yt-dlp`.
+    /// Nothing is downloaded, so the media files should already be on disk.
+    #[clap(long, value_name = "PATH", conflicts_with_all = ["playlists_file", "rebase_url", "check", "dump_metadata", "migrate_guids"])]
+    from_json: Option<PathBuf>,
+
+    /// Rewrite the existing feed's GUIDs using the given scheme (`id`, `enclosure` or `prefix:TEXT`),
+    /// without downloading anything. Subscribers will likely see every episode as new.
+    #[clap(long, conflicts_with_all = ["playlists_file", "rebase_url", "check", "dump_metadata"])]
+    migrate_guids: Option<GuidScheme>,
+
+    /// Write the playlist's metadata to a `.info.json` file next to the feed file, for archiving
+    #[clap(long)]
+    write_channel_info: bool,
+
+    /// Omit all iTunes podcast elements from the feed, for a plain RSS feed
+    #[clap(long)]
+    no_itunes: bool,
+
+    /// Embed chapter markers into the downloaded media files, for players which read them. Requires `ffmpeg`.
+    #[clap(long)]
+    embed_chapters: bool,
+
+    /// Embed each video's metadata into its media file. Only Matroska (`mkv`/`mka`) files support this,
+    /// so MP4 and WebM downloads are unaffected. Requires `ffmpeg`.
+    #[clap(long)]
+    embed_info_json: bool,
+
+    /// Write a link file next to each media file, pointing back to the video's page: `url` (Windows), `webloc`
+    /// (macOS) or `desktop` (Linux). It's removed along with the media file.
+    #[clap(long, value_enum, value_name = "FORMAT")]
+    write_link_file: Option<LinkFileFormat>,
+
+    /// Which of each video's thumbnails to use as its episode image: `maxres`, `square`, `first` or `index:N`
+    #[clap(long)]
+    thumbnail_preference: Option<ThumbnailPreference>,
+
+    /// Convert thumbnails to the given format (e.g. `jpg`), passed to `yt-dlp`'s `--convert-thumbnails`
+    #[clap(long)]
+    convert_thumbnails: Option<String>,
+
+    /// Embed each video's thumbnail into its media file as cover art, without keeping the thumbnail files
+    /// (unless `--write-thumbnail` is passed to `yt-dlp`). Requires `ffmpeg`.
+    #[clap(long)]
+    embed_thumbnail: bool,
+
+    /// Keep media files' names ASCII-only and free of spaces and shell characters, via `yt-dlp`'s
+    /// `--restrict-filenames`, for sources whose ids aren't already
+    #[clap(long)]
+    restrict_filenames: bool,
+
+    /// Give each new item a `<source>` element linking to the playlist it came from
+    #[clap(long)]
+    with_source: bool,
+
+    /// Record when each new episode's media was downloaded, in a `playcaster:downloaded` element
+    #[clap(long)]
+    write_downloaded_timestamp: bool,
+
+    /// Read the duration of downloaded files with `ffprobe` when their metadata doesn't include one
+    #[clap(long)]
+    probe_duration: bool,
+
+    /// Number each item's `itunes:episode` by its position in the playlist, leaving items without one unnumbered
+    #[clap(long)]
+    episode_numbers: bool,
+
+    /// Set each item's `dc:language` from its video's language, for channels which mix languages
+    #[clap(long)]
+    per_item_language: bool,
+
+    /// Download livestreams from their start, passed to `yt-dlp`'s `--live-from-start`
+    #[clap(long)]
+    live_from_start: bool,
+
+    /// Two-letter country code (e.g. `AU`) to request metadata for, passed to `yt-dlp`'s `--geo-bypass-country`
+    #[clap(long, alias = "xff")]
+    geo_bypass_country: Option<String>,
+
+    /// Seconds to sleep before each download, passed to `yt-dlp`'s `--sleep-interval`
+    #[clap(long)]
+    sleep_interval: Option<f64>,
+
+    /// Sleep a random number of seconds, between `--sleep-interval` and this, before each download
+    #[clap(long, requires = "sleep_interval")]
+    max_sleep_interval: Option<f64>,
+
+    /// Seconds to sleep between requests while fetching metadata, passed to `yt-dlp`'s `--sleep-requests`
+    #[clap(long)]
+    sleep_requests: Option<f64>,
+
+    /// Only download videos matching this `yt-dlp` filter expression, e.g. `view_count > 1000 & duration < 1200`.
+    /// Applied by `yt-dlp` while downloading; when repeated, videos matching any of the filters are kept.
+    #[clap(long = "match-filter")]
+    match_filters: Vec<String>,
+
+    /// Attempt members-only and premium videos without cookies or credentials, rather than skipping them
+    #[clap(long)]
+    include_members_only: bool,
+
+    /// Skip videos with fewer views than this
+    #[clap(long)]
+    min_views: Option<u64>,
+
+    /// Keep videos whose view count isn't known when using `--min-views`, rather than skipping them
+    #[clap(long, requires = "min_views")]
+    include_unknown_views: bool,
+
+    /// Skip videos lasting exactly this many seconds, such as the length of a known placeholder. May be given more than once.
+    #[clap(long = "skip-duration-eq", value_name = "SECS")]
+    skip_durations: Vec<u64>,
+
+    /// Remove a channel's media directory if it's left empty after old files are removed
+    #[clap(long)]
+    prune_empty_dirs: bool,
+
+    /// Before downloading, delete partial downloads (`.part` and `.ytdl` files) left in the media directory
+    /// for more than this many hours, e.g. by a crash, so they're downloaded afresh. Defaults to 24 hours.
+    #[clap(long, num_args = 0..=1, require_equals = true, default_missing_value = "24", value_name = "HOURS")]
+    clean_stale_partials: Option<u64>,
+
+    /// Comma-separated languages to download subtitles in (e.g. `en,es,fr`), each emitted as a transcript
+    #[clap(long, value_delimiter = ',')]
+    sub_langs: Vec<String>,
+
+    /// MIME type to use for enclosures with a file extension, as `ext=mime`, overriding the built-in types.
+    /// May be given more than once.
+    #[clap(long, value_parser = parse_query_pair)]
+    mime_map: Vec<(String, String)>,
+
+    /// Prefer VP9/Opus formats in a WebM container over H.264/AAC in MP4, passed to `yt-dlp`'s `--prefer-free-formats`
+    #[clap(long)]
+    prefer_free_formats: bool,
+
+    /// Run `yt-dlp` in simulate mode and skip all media file operations, while still writing the feed
+    #[clap(long, hide = true, env = "PLAYCASTER_SKIP_DOWNLOAD", value_parser = clap::builder::FalseyValueParser::new())]
+    skip_download: bool,
+
+    /// Don't download any media, and point the enclosures at the source's own media URLs instead, where
+    /// it allows that. Only single-file formats are used, and source URLs may expire, so update regularly.
+    #[clap(long, conflicts_with = "keep_files")]
+    no_download: bool,
+
+    /// File of additional arguments to be passed to `yt-dlp`, one per line, after any given on the command line.
+    /// Blank lines and lines starting with `#` are ignored.
+    #[clap(long)]
+    args_file: Option<PathBuf>,
+
+    /// Additional arguments to be passed to `yt-dlp`, after any from `PLAYCASTER_DEFAULT_ARGS`
     downloader_arguments: Vec<String>,
 }
 
-fn main() -> Result<()> {
-    env_logger::init();
+/// Parses a `key=value` pair, as used by `--enclosure-query` and `--mime-map`
+fn parse_query_pair(text: &str) -> Result<(String, String), String> {
+    text.split_once('=')
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("\"{}\" should be in the form `key=value`", text))
+}
+
+/// Parses a non-negative, possibly fractional number of seconds, as used by `--inter-channel-delay`
+fn parse_seconds(text: &str) -> Result<Duration, String> {
+    let seconds: f64 = text.parse().map_err(|error| format!("{}", error))?;
+
+    Duration::try_from_secs_f64(seconds).map_err(|_| {
+        format!(
+            "\"{}\" should be a non-negative, finite number of seconds",
+            text
+        )
+    })
+}
+
+/// Deletes the files an update left pending, once confirmed by `--yes` or at an interactive prompt
+fn confirm_deletions(pending_deletions: &[PathBuf], args: &Args) -> std::io::Result<bool> {
+    let interactive = std::io::stdin().is_terminal();
+
+    match playcaster::deletion_decision(pending_deletions, args.yes, interactive) {
+        DeletionDecision::Delete => {}
+        DeletionDecision::Keep => {
+            if !pending_deletions.is_empty() {
+                info!(
+                    "Not deleting {} files without `--yes`: {:?}",
+                    pending_deletions.len(),
+                    pending_deletions
+                );
+            }
+            return Ok(false);
+        }
+        DeletionDecision::Ask => {
+            println!("The following files are no longer in the feed:");
+            for path in pending_deletions {
+                println!("  {}", path.display());
+            }
+
+            if !ask("Delete them?")? {
+                return Ok(false);
+            }
+        }
+    }
+
+    for path in pending_deletions {
+        playcaster::remove_media_file(path, args.trash_dir.as_deref())
+            .unwrap_or_else(|err| warn!("Couldn't remove file: {:?}", err));
+    }
+
+    Ok(true)
+}
+
+/// Asks a yes or no question at the terminal, defaulting to no
+fn ask(question: &str) -> std::io::Result<bool> {
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Lists what purging the channel deletes, then deletes it once confirmed by `--yes` or at an
+/// interactive prompt, unless it's a `--dry-run`
+fn purge_channel(channel: &Channel, args: &Args) -> Result<()> {
+    let plan = channel.purge_plan(args.force)?;
+
+    println!("Purging {} deletes:", channel.feed_file.display());
+    for path in &plan.feed_files {
+        println!("  {}", path.display());
+    }
+    if let Some(ref media_dir) = plan.media_dir {
+        println!(
+            "  {} ({} files, {} of them not in the feed)",
+            media_dir.display(),
+            plan.referenced_files.len() + plan.unreferenced_files.len(),
+            plan.unreferenced_files.len()
+        );
+    }
+
+    if args.dry_run {
+        return Ok(());
+    }
+
+    if !args.yes {
+        if !std::io::stdin().is_terminal() {
+            info!("Not purging {:?} without `--yes`", channel.feed_file);
+            return Ok(());
+        }
+
+        if !ask("Delete them?")? {
+            return Ok(());
+        }
+    }
+
+    channel.purge(args.force)?;
+    println!("Purged {}", channel.feed_file.display());
+
+    Ok(())
+}
+
+/// Reports any inconsistencies between the channel's feed and its media files
+fn check_channel(channel: &Channel, base_url: &Url) -> Result<()> {
+    let problems = channel.check(base_url)?;
+
+    if problems.is_empty() {
+        println!("{}: OK", channel.feed_file.display());
+        return Ok(());
+    }
+
+    for problem in &problems {
+        println!("{}: {}", channel.feed_file.display(), problem);
+    }
+
+    Err(anyhow::anyhow!(
+        "found {} problems with {}",
+        problems.len(),
+        channel.feed_file.display()
+    ))
+}
+
+/// Prints the changes to a feed's episodes between `previous` and `rss_channel`, one per line
+fn print_diff(feed_file: &Path, previous: Option<&rss::Channel>, rss_channel: &rss::Channel) {
+    let changes = playcaster::diff_items(previous, rss_channel);
+
+    if changes.is_empty() {
+        println!("No changes to the episodes in {:?}", feed_file);
+        return;
+    }
+
+    println!("Changes to the episodes in {:?}:", feed_file);
 
-    let args = Args::parse();
+    for change in changes {
+        println!("{}", change);
+    }
+}
+
+/// Writes a feed of the newest episodes across the feeds in `feed_files` which exist, such as
+/// those which failed validation having been skipped
+fn write_combined_feed(path: &Path, feed_files: &[PathBuf], args: &Args) -> Result<()> {
+    let channels = feed_files
+        .iter()
+        .filter(|feed_file| feed_file.exists())
+        .map(|feed_file| Channel::new(feed_file.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
 
-    println!(
-        "{} v{} Starting up...",
-        playcaster::PKG_NAME,
-        playcaster::PKG_VERSION
+    let channel = Channel::new_combined(
+        path.to_path_buf(),
+        args.base_url.clone(),
+        &channels,
+        args.combined_feed_limit,
     );
 
-    trace!("{:?}", args);
+    if args.no_write_feed {
+        print!("{:#}", channel.rss_channel.unwrap_or_default().to_string());
+        return Ok(());
+    }
+
+    if !args.json_summary {
+        println!(
+            "Writing the newest episodes across {} feeds to {:?}",
+            channels.len(),
+            path
+        );
+    }
+
+    // Profiles aren't applied, as the combined feed has none of the artwork or categories they need
+    channel.write(&WriteOptions {
+        pretty: !args.no_pretty,
+        encoding: args.encoding.clone(),
+        formats: args.output_formats.clone(),
+        minimal: args.minimal,
+        profile: None,
+        preview: args.preview_feed,
+        channel_info: false,
+    })?;
+
+    Ok(())
+}
+
+/// A channel which has been updated, but not yet written, by `update_channel`
+struct UpdatedChannel {
+    channel: Channel,
+    summary: UpdateSummary,
+
+    /// The feed before the update, with `--diff`
+    previous: Option<Option<rss::Channel>>,
+}
+
+fn update_channel(
+    mut channel: Channel,
+    base_url: &Url,
+    options: &UpdateOptions,
+    args: &Args,
+) -> Result<UpdatedChannel, playcaster::Error> {
+    let previous = args.diff.then(|| channel.rss_channel.clone());
+
+    // Settings stored in the feed apply wherever they aren't given on the command line, and the
+    // result is stored back, so the feed keeps any given this time
+    let mut options = options.clone();
+    let settings = FeedSettings {
+        keep: args.keep,
+        download_limit: args.limit,
+        categories: args.categories.clone(),
+    };
+    match args.clear_feed_settings {
+        true => settings,
+        false => settings.or(channel.feed_settings()),
+    }
+    .apply_to(&mut options);
+    let options = &options;
 
-    let mut channel = match args.playlist_url {
-        Some(url) => Channel::new_with_url(args.feed_file.clone(), url),
-        None => Channel::new(args.feed_file.clone()),
-    }?;
+    let summary = if args.rebase_url {
+        if !args.json_summary {
+            println!(
+                "Rebasing RSS feed in {:?} onto {}...",
+                channel.feed_file, base_url
+            );
+        }
+
+        channel.rebase(base_url.clone())?;
+
+        UpdateSummary {
+            total: channel
+                .rss_channel
+                .as_ref()
+                .map_or(0, |rss_channel| rss_channel.items.len()),
+            ..Default::default()
+        }
+    } else if let Some(ref scheme) = args.migrate_guids {
+        warn!("Podcast apps identify episodes by their GUIDs, so subscribers will likely see every migrated episode as new");
+
+        let migrated = channel.migrate_guids(scheme);
 
-    println!("Updating RSS feed in {:?} with contents of playlist {}... (this can take a pretty long time)", channel.feed_file, channel.playlist_url);
+        if !args.json_summary {
+            println!(
+                "Migrated {} GUIDs in {:?}. Subscribers will likely see those episodes as new; pass the same `--guid-scheme` to future updates.",
+                migrated, channel.feed_file
+            );
+        }
+
+        UpdateSummary {
+            total: channel
+                .rss_channel
+                .as_ref()
+                .map_or(0, |rss_channel| rss_channel.items.len()),
+            ..Default::default()
+        }
+    } else if let Some(ref from_json) = args.from_json {
+        if !args.json_summary {
+            println!(
+                "Updating RSS feed in {:?} with playlist JSON from {:?}...",
+                channel.feed_file, from_json
+            );
+        }
 
-    channel.update_with_args(
-        args.base_url,
-        args.limit,
-        args.keep,
-        args.downloader_arguments,
-    )?;
+        let playlist = playcaster::read_playlist_json(BufReader::new(File::open(from_json)?))?;
+
+        channel.update_with_playlist(base_url.clone(), options, playlist)?
+    } else {
+        if !args.json_summary {
+            println!("Updating RSS feed in {:?} with contents of playlist {}... (this can take a pretty long time)", channel.feed_file, channel.playlist_url);
+        }
+
+        channel.update_with_options(base_url.clone(), options)?
+    };
+
+    Ok(UpdatedChannel {
+        channel,
+        summary,
+        previous,
+    })
+}
+
+/// Writes a channel `update_channel` updated, then deletes any files it no longer refers to
+fn write_channel(
+    updated: UpdatedChannel,
+    args: &Args,
+) -> Result<ChannelSummary, playcaster::Error> {
+    let UpdatedChannel {
+        channel,
+        mut summary,
+        previous,
+    } = updated;
 
     match channel.rss_channel {
         Some(ref rss_channel) => {
-            if args.no_write_feed {
+            if let Some(ref previous) = previous {
+                print_diff(&channel.feed_file, previous.as_ref(), rss_channel);
+            } else if args.no_write_feed {
                 print!("{:#}", rss_channel.to_string());
             } else {
-                let file = OpenOptions::new()
-                    .write(true)
-                    .create(true)
-                    .truncate(true)
-                    .open(args.feed_file)?;
-
-                if args.no_pretty {
-                    rss_channel.write_to(file)?;
-                } else {
-                    rss_channel.pretty_write_to(file, b' ', 2)?;
-                }
+                channel.write(&WriteOptions {
+                    pretty: !args.no_pretty,
+                    encoding: args.encoding.clone(),
+                    formats: args.output_formats.clone(),
+                    minimal: args.minimal,
+                    profile: args.profile,
+                    preview: args.preview_feed,
+                    channel_info: args.write_channel_info,
+                })?;
             }
         }
         None => warn!("No RSS channel generated"),
     }
 
-    println!("Done!");
+    // Only delete files once the feed no longer refers to them
+    let dry_run = args.no_write_feed || args.diff || args.preview_feed;
 
-    Ok(())
+    if !dry_run && confirm_deletions(&summary.pending_deletions, args)? {
+        let pending_deletions = std::mem::take(&mut summary.pending_deletions);
+        summary
+            .deleted_files
+            .extend(pending_deletions.into_iter().filter(|path| !path.exists()));
+
+        if args.prune_empty_dirs {
+            channel.prune_media_dir()?;
+        }
+    }
+
+    let history_file = match args.history_file {
+        Some(Some(ref history_file)) => Some(history_file.clone()),
+        Some(None) => Some(channel.history_file()),
+        None => None,
+    };
+
+    let channel_summary = ChannelSummary {
+        feed_file: channel.feed_file,
+        playlist_url: channel.playlist_url,
+        summary,
+    };
+
+    if let (Some(history_file), false) = (history_file, dry_run) {
+        playcaster::append_history(&history_file, &channel_summary)?;
+    }
+
+    Ok(channel_summary)
+}
+
+fn main() -> Result<ExitCode> {
+    // Prefixes each line with the feed being worked on, to tell channels apart in batch mode
+    env_logger::Builder::from_default_env()
+        .format(|buf, record| {
+            let level_style = buf.default_level_style(record.level());
+            let context = playcaster::log_context()
+                .map(|feed_file| format!(" {}", feed_file))
+                .unwrap_or_default();
+
+            writeln!(
+                buf,
+                "[{} {level_style}{:<5}{level_style:#} {}{}] {}",
+                buf.timestamp(),
+                record.level(),
+                record.target(),
+                context,
+                record.args()
+            )
+        })
+        .init();
+
+    // As `--version-json` is exclusive, clap doesn't require the feed file and base URL alongside
+    // it, but `Args` can't be built without them, so it's checked for first
+    let matches = Args::command().get_matches();
+
+    if matches.get_flag("version_json") {
+        let report = playcaster::VersionReport::new(&playcaster::YtDlp);
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+
+    if !args.json_summary && !args.dump_metadata {
+        println!(
+            "{} v{} Starting up...",
+            playcaster::PKG_NAME,
+            playcaster::PKG_VERSION
+        );
+    }
+
+    trace!("{:?}", args);
+
+    let mut additional_args = playcaster::default_args();
+    additional_args.extend(args.downloader_arguments.iter().cloned());
+
+    if let Some(ref args_file) = args.args_file {
+        let reader = BufReader::new(File::open(args_file)?);
+        additional_args.extend(playcaster::read_args_file(reader)?);
+    }
+
+    let description = match args.description_file {
+        Some(ref description_file) => Some(
+            std::fs::read_to_string(description_file)?
+                .trim()
+                .to_string(),
+        ),
+        None => None,
+    };
+
+    let item_overrides = match args.item_overrides {
+        Some(ref item_overrides) => playcaster::read_item_overrides(File::open(item_overrides)?)?,
+        None => Default::default(),
+    };
+
+    let options = UpdateOptions {
+        download_limit: args.limit.unwrap_or(playcaster::DEFAULT_DOWNLOAD_LIMIT),
+        keep: args.keep,
+        keep_files: args.keep_files,
+        max_new_per_run: args.max_new_per_run,
+        no_dedup: args.no_dedup,
+        additional_args,
+        docs: args.docs.clone(),
+        format_sort: args.format_sort.clone(),
+        require_https_images: args.require_https_images,
+        order: args.order,
+        order_by: args.order_by,
+        order_dir: args.order_dir,
+        cache_dir: args.cache_dir.clone(),
+        no_cache_dir: args.no_cache_dir,
+        single_ok: args.single_ok,
+        summary_from_episodes: args.summary_from_episodes,
+        sync_title: args.sync_title,
+        description,
+        sync_description: args.sync_description,
+        author: args.author.clone(),
+        categories: args.categories.clone(),
+        channel_keywords: args.channel_keywords.clone(),
+        channel_elements: args.channel_element.clone(),
+        namespaces: args.namespace.clone(),
+        item_overrides,
+        sort_categories: args.sort_categories,
+        date_source: args.date_source,
+        title_source: args.title_from,
+        disambiguate_titles: args.disambiguate_titles,
+        strict: args.strict,
+        enclosure_query: args.enclosure_query.clone(),
+        prefer_fresh_metadata: args.prefer_fresh_metadata,
+        guid_from_enclosure: args.guid_from_enclosure,
+        guid_scheme: args.guid_scheme.clone(),
+        no_itunes: args.no_itunes,
+        embed_chapters: args.embed_chapters,
+        embed_info_json: args.embed_info_json,
+        link_file: args.write_link_file,
+        thumbnail_preference: args.thumbnail_preference,
+        convert_thumbnails: args.convert_thumbnails.clone(),
+        embed_thumbnail: args.embed_thumbnail,
+        restrict_filenames: args.restrict_filenames,
+        with_source: args.with_source,
+        write_downloaded_timestamp: args.write_downloaded_timestamp,
+        probe_duration: args.probe_duration,
+        trash_dir: args.trash_dir.clone(),
+        confirm_deletes: args.confirm_deletes,
+        live_from_start: args.live_from_start,
+        per_item_language: args.per_item_language,
+        episode_numbers: args.episode_numbers,
+        geo_bypass_country: args.geo_bypass_country.clone(),
+        sleep_interval: args.sleep_interval,
+        max_sleep_interval: args.max_sleep_interval,
+        sleep_requests: args.sleep_requests,
+        match_filters: args.match_filters.clone(),
+        include_members_only: args.include_members_only,
+        min_views: args.min_views,
+        include_unknown_views: args.include_unknown_views,
+        skip_durations: args.skip_durations.clone(),
+        prune_empty_dirs: args.prune_empty_dirs,
+        clean_stale_partials: args
+            .clean_stale_partials
+            .map(|hours| Duration::from_secs(hours.saturating_mul(60 * 60))),
+        sub_langs: args.sub_langs.clone(),
+        mime_map: args.mime_map.clone(),
+        prefer_free_formats: args.prefer_free_formats,
+        // A diff or preview only needs the metadata, not the media
+        skip_download: args.skip_download || args.diff || args.preview_feed,
+        provisional: args.preview_feed,
+        // Filled in for each channel from its feed's stored settings, in `update_channel`
+        feed_settings: None,
+        remote_enclosures: args.no_download,
+    };
+
+    let mut summaries = vec![];
+
+    if let Some(ref playlists_file) = args.playlists_file {
+        let reader = BufReader::new(File::open(playlists_file)?);
+
+        let channels = playcaster::read_playlists(reader)?
+            .into_iter()
+            .map(|playlist_url| {
+                let feed_file = playcaster::feed_file_for_playlist(&args.feed_file, &playlist_url);
+                Channel::new_with_url(feed_file, playlist_url)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Check before updating any of them, rather than mixing their media partway through
+        playcaster::check_media_dirs(&channels)?;
+
+        let feed_files: Vec<_> = channels
+            .iter()
+            .map(|channel| channel.feed_file.clone())
+            .collect();
+
+        let batch_options = BatchOptions {
+            abort_on_validation_error: args.abort_on_validation_error,
+            inter_channel_delay: args.inter_channel_delay,
+            write_queue: args.write_queue,
+            ..Default::default()
+        };
+
+        playcaster::install_shutdown_handler(batch_options.shutdown.clone());
+
+        summaries = playcaster::update_and_write_channels(
+            channels,
+            &batch_options,
+            |channel| update_channel(channel, &args.base_url, &options, &args),
+            |updated| write_channel(updated, &args),
+        )?;
+
+        if let Some(ref combined_feed) = args.combined_feed {
+            write_combined_feed(combined_feed, &feed_files, &args)?;
+        }
+    } else {
+        let mut channel = match args.playlist_url {
+            Some(ref url) => Channel::new_with_url(args.feed_file.clone(), url.clone()),
+            None => Channel::new(args.feed_file.clone()),
+        }?;
+
+        channel.media_dir = args.media_dir.clone();
+        channel.url_path_prefix = args.url_path_prefix.clone();
+
+        // A prefix not ending in the media directory's name usually means a typo, unless the server maps it
+        if let (Some(ref media_dir), Some(ref url_path_prefix)) =
+            (&args.media_dir, &args.url_path_prefix)
+        {
+            let last_segment = url_path_prefix.trim_matches('/').rsplit('/').next();
+
+            if media_dir.file_name().and_then(|name| name.to_str()) != last_segment {
+                warn!(
+                    "URL path prefix {:?} doesn't end in the media directory's name {:?}; check the server maps one to the other",
+                    url_path_prefix, media_dir
+                );
+            }
+        }
+
+        if args.check {
+            return check_channel(&channel, &args.base_url).map(|()| ExitCode::SUCCESS);
+        }
+
+        if args.purge {
+            return purge_channel(&channel, &args).map(|()| ExitCode::SUCCESS);
+        }
+
+        if args.dump_metadata {
+            let playlist = channel.fetch_metadata(&options)?;
+            println!("{}", serde_json::to_string_pretty(&playlist)?);
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        // Another feed beside this one, updated on its own, would mix its media with this one's
+        playcaster::check_sibling_media_dirs(&channel)?;
+
+        let updated = update_channel(channel, &args.base_url, &options, &args)?;
+        summaries.push(write_channel(updated, &args)?);
+    }
+
+    if args.json_summary {
+        println!("{}", serde_json::to_string_pretty(&summaries)?);
+    } else {
+        println!("Done!");
+    }
+
+    Ok(ExitCode::from(playcaster::exit_code(
+        &summaries,
+        args.fail_if_no_new,
+    )))
 }